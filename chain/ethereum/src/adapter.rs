@@ -4,12 +4,12 @@ use futures::Future;
 use mockall::automock;
 use mockall::predicate::*;
 use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::marker::Unpin;
 use thiserror::Error;
 use tiny_keccak::keccak256;
-use web3::types::{Address, Block, Log, H256};
+use web3::types::{Address, Block, Log, H256, U256};
 
 use graph::{
     blockchain as bc,
@@ -31,8 +31,43 @@ pub struct EthereumContractCall {
     pub block_ptr: BlockPtr,
     pub function: Function,
     pub args: Vec<Token>,
+    /// The sender of the call, if it should not be the zero address.
+    pub from: Option<Address>,
+    /// The gas limit for the call, if it should not be `GRAPH_ETH_CALL_GAS`.
+    pub gas: Option<u64>,
 }
 
+/// A contract call whose call data was already encoded, e.g. because the
+/// function is only known by its selector and not from an ABI.
+#[derive(Clone, Debug)]
+pub struct EthereumRawContractCall {
+    pub address: Address,
+    pub block_ptr: BlockPtr,
+    pub call_data: Vec<u8>,
+    /// The sender of the call, if it should not be the zero address.
+    pub from: Option<Address>,
+    /// The gas limit for the call, if it should not be `GRAPH_ETH_CALL_GAS`.
+    pub gas: Option<u64>,
+}
+
+/// Changes to an account that an `eth_call` with state overrides sees
+/// instead of what is on chain. Serializes to the override object that Geth
+/// accepts as the third parameter of `eth_call`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EthereumAccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<web3::types::Bytes>,
+    /// Storage slots to change, leaving all others as they are.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub state_diff: BTreeMap<H256, H256>,
+}
+
+/// State overrides for an `eth_call`, by account.
+pub type EthereumStateOverrides = BTreeMap<Address, EthereumAccountOverride>;
+
 #[derive(Error, Debug)]
 pub enum EthereumContractCallError {
     #[error("ABI error: {0}")]
@@ -653,6 +688,25 @@ pub trait EthereumAdapter: Send + Sync + 'static {
         call: EthereumContractCall,
         cache: Arc<dyn EthereumCallCache>,
     ) -> Box<dyn Future<Item = Vec<Token>, Error = EthereumContractCallError> + Send>;
+
+    /// Call a smart contract with call data that is already encoded and
+    /// return its output without decoding it.
+    fn raw_contract_call(
+        &self,
+        logger: &Logger,
+        call: EthereumRawContractCall,
+        cache: Arc<dyn EthereumCallCache>,
+    ) -> Box<dyn Future<Item = Vec<u8>, Error = EthereumContractCallError> + Send>;
+
+    /// Call the function of a smart contract against the state of the
+    /// block with the accounts in `overrides` changed. Since the result
+    /// does not follow from the chain, it is never cached.
+    fn contract_call_with_overrides(
+        &self,
+        logger: &Logger,
+        call: EthereumContractCall,
+        overrides: EthereumStateOverrides,
+    ) -> Box<dyn Future<Item = Vec<Token>, Error = EthereumContractCallError> + Send>;
 }
 
 #[cfg(test)]
@@ -31,14 +31,17 @@ use graph::{
     prelude::web3::types::{Trace, TraceFilter, TraceFilterBuilder, H160},
 };
 use web3::api::Web3;
+use web3::helpers::{self, CallFuture};
 use web3::transports::batch::Batch;
 use web3::types::Filter;
+use web3::Transport as _;
 
 use crate::{
     adapter::{
         EthGetLogsFilter, EthereumAdapter as EthereumAdapterTrait, EthereumBlockFilter,
         EthereumCallFilter, EthereumContractCall, EthereumContractCallError, EthereumLogFilter,
-        ProviderEthRpcMetrics, SubgraphEthRpcMetrics,
+        EthereumRawContractCall, EthereumStateOverrides, ProviderEthRpcMetrics,
+        SubgraphEthRpcMetrics,
     },
     transport::Transport,
     trigger::{EthereumBlockTriggerType, EthereumTrigger},
@@ -438,6 +441,9 @@ impl EthereumAdapter {
         contract_address: Address,
         call_data: Bytes,
         block_ptr: BlockPtr,
+        from: Option<Address>,
+        gas: Option<u64>,
+        overrides: Option<EthereumStateOverrides>,
     ) -> impl Future<Item = Bytes, Error = EthereumContractCallError> + Send {
         let web3 = self.web3.clone();
 
@@ -458,14 +464,27 @@ impl EthereumAdapter {
             .timeout_secs(*JSON_RPC_TIMEOUT)
             .run(move || {
                 let req = CallRequest {
-                    from: None,
+                    from,
                     to: contract_address,
-                    gas: Some(web3::types::U256::from(*ETH_CALL_GAS)),
+                    gas: Some(web3::types::U256::from(gas.unwrap_or(*ETH_CALL_GAS))),
                     gas_price: None,
                     value: None,
                     data: Some(call_data.clone()),
                 };
-                web3.eth().call(req, Some(block_id)).then(|result| {
+                let call = match &overrides {
+                    // `web3` has no way to pass the state overrides, which
+                    // are an optional third parameter of `eth_call`
+                    Some(overrides) => CallFuture::new(web3.transport().execute(
+                        "eth_call",
+                        vec![
+                            helpers::serialize(&req),
+                            helpers::serialize(&block_id),
+                            helpers::serialize(overrides),
+                        ],
+                    )),
+                    None => web3.eth().call(req, Some(block_id)),
+                };
+                call.then(|result| {
                     // Try to check if the call was reverted. The JSON-RPC response for reverts is
                     // not standardized, so we have ad-hoc checks for each of Geth, Parity and
                     // Ganache.
@@ -1241,26 +1260,72 @@ impl EthereumAdapterTrait for EthereumAdapter {
         call: EthereumContractCall,
         cache: Arc<dyn EthereumCallCache>,
     ) -> Box<dyn Future<Item = Vec<Token>, Error = EthereumContractCallError> + Send> {
-        // Emit custom error for type mismatches.
-        for (token, kind) in call
-            .args
-            .iter()
-            .zip(call.function.inputs.iter().map(|p| &p.kind))
-        {
-            if !token.type_check(kind) {
-                return Box::new(future::err(EthereumContractCallError::TypeError(
-                    token.clone(),
-                    kind.clone(),
-                )));
-            }
-        }
+        let call_data = match encode_contract_call(&call) {
+            Ok(data) => data,
+            Err(e) => return Box::new(future::err(e)),
+        };
 
-        // Encode the call parameters according to the ABI
-        let call_data = match call.function.encode_input(&call.args) {
+        let raw_call = EthereumRawContractCall {
+            address: call.address,
+            block_ptr: call.block_ptr.clone(),
+            call_data,
+            from: call.from,
+            gas: call.gas,
+        };
+        Box::new(
+            self.raw_contract_call(logger, raw_call, cache)
+                .and_then(move |output| decode_contract_call_output(&call.function, &output)),
+        )
+    }
+
+    fn contract_call_with_overrides(
+        &self,
+        logger: &Logger,
+        call: EthereumContractCall,
+        overrides: EthereumStateOverrides,
+    ) -> Box<dyn Future<Item = Vec<Token>, Error = EthereumContractCallError> + Send> {
+        let call_data = match encode_contract_call(&call) {
             Ok(data) => data,
-            Err(e) => return Box::new(future::err(EthereumContractCallError::EncodingError(e))),
+            Err(e) => return Box::new(future::err(e)),
         };
 
+        if *ETH_CALL_FULL_LOG {
+            trace!(logger, "eth_call with state overrides";
+                "address" => hex::encode(&call.address),
+                "data" => hex::encode(&call_data),
+                "overrides" => overrides.len()
+            );
+        }
+
+        Box::new(
+            self.call(
+                logger.clone(),
+                call.address,
+                Bytes(call_data),
+                call.block_ptr.clone(),
+                call.from,
+                call.gas,
+                Some(overrides),
+            )
+            .and_then(move |output| {
+                // Like for calls without overrides, an empty response is a revert
+                if output.0.is_empty() {
+                    Err(EthereumContractCallError::Revert("empty response".into()))
+                } else {
+                    decode_contract_call_output(&call.function, &output.0)
+                }
+            }),
+        )
+    }
+
+    fn raw_contract_call(
+        &self,
+        logger: &Logger,
+        call: EthereumRawContractCall,
+        cache: Arc<dyn EthereumCallCache>,
+    ) -> Box<dyn Future<Item = Vec<u8>, Error = EthereumContractCallError> + Send> {
+        let call_data = call.call_data.clone();
+
         if *ETH_CALL_FULL_LOG {
             trace!(logger, "eth_call";
                 "address" => hex::encode(&call.address),
@@ -1268,14 +1333,21 @@ impl EthereumAdapterTrait for EthereumAdapter {
             );
         }
 
-        // Check if we have it cached, if not do the call and cache.
-        Box::new(
-            match cache
+        // The call cache is keyed by address, call data and block only, so
+        // calls that override the sender or gas limit bypass it.
+        let cacheable = call.from.is_none() && call.gas.is_none();
+        let cached = match cacheable {
+            true => cache
                 .get_call(call.address, &call_data, call.block_ptr.clone())
                 .map_err(|e| error!(logger, "call cache get error"; "error" => e.to_string()))
                 .ok()
-                .flatten()
-            {
+                .flatten(),
+            false => None,
+        };
+
+        // Check if we have it cached, if not do the call and cache.
+        Box::new(
+            match cached {
                 Some(result) => {
                     Box::new(future::ok(result)) as Box<dyn Future<Item = _, Error = _> + Send>
                 }
@@ -1289,8 +1361,15 @@ impl EthereumAdapterTrait for EthereumAdapter {
                             call.address,
                             Bytes(call_data.clone()),
                             call.block_ptr.clone(),
+                            call.from,
+                            call.gas,
+                            None,
                         )
                         .map(move |result| {
+                            if !cacheable {
+                                return result.0;
+                            }
+
                             // Don't block handler execution on writing to the cache.
                             let for_cache = result.0.clone();
                             let _ = graph::spawn_blocking_allow_panic(move || {
@@ -1306,7 +1385,6 @@ impl EthereumAdapterTrait for EthereumAdapter {
                     )
                 }
             }
-            // Decode the return values according to the ABI
             .and_then(move |output| {
                 if output.is_empty() {
                     // We got a `0x` response. For old Geth, this can mean a revert. It can also be
@@ -1314,11 +1392,7 @@ impl EthereumAdapterTrait for EthereumAdapter {
                     // to return something, so we treat empty responses the same as reverts.
                     Err(EthereumContractCallError::Revert("empty response".into()))
                 } else {
-                    // Decode failures are reverts. The reasoning is that if Solidity fails to
-                    // decode an argument, that's a revert, so the same goes for the output.
-                    call.function.decode_output(&output).map_err(|e| {
-                        EthereumContractCallError::Revert(format!("failed to decode output: {}", e))
-                    })
+                    Ok(output)
                 }
             }),
         )
@@ -1361,6 +1435,39 @@ impl EthereumAdapterTrait for EthereumAdapter {
     }
 }
 
+/// Encodes the call parameters according to the ABI.
+fn encode_contract_call(call: &EthereumContractCall) -> Result<Vec<u8>, EthereumContractCallError> {
+    // Emit custom error for type mismatches.
+    for (token, kind) in call
+        .args
+        .iter()
+        .zip(call.function.inputs.iter().map(|p| &p.kind))
+    {
+        if !token.type_check(kind) {
+            return Err(EthereumContractCallError::TypeError(
+                token.clone(),
+                kind.clone(),
+            ));
+        }
+    }
+
+    call.function
+        .encode_input(&call.args)
+        .map_err(EthereumContractCallError::EncodingError)
+}
+
+/// Decodes the return values according to the ABI.
+fn decode_contract_call_output(
+    function: &ethabi::Function,
+    output: &[u8],
+) -> Result<Vec<Token>, EthereumContractCallError> {
+    // Decode failures are reverts. The reasoning is that if Solidity fails to
+    // decode an argument, that's a revert, so the same goes for the output.
+    function
+        .decode_output(output)
+        .map_err(|e| EthereumContractCallError::Revert(format!("failed to decode output: {}", e)))
+}
+
 /// Returns blocks with triggers, corresponding to the specified range and filters.
 /// If a block contains no triggers, there may be no corresponding item in the stream.
 /// However the `to` block will always be present, even if triggers are empty.
@@ -18,7 +18,8 @@ mod network;
 mod trigger;
 
 pub use crate::adapter::{
-    EthereumAdapter as EthereumAdapterTrait, EthereumContractCall, EthereumContractCallError,
+    EthereumAccountOverride, EthereumAdapter as EthereumAdapterTrait, EthereumContractCall,
+    EthereumContractCallError, EthereumRawContractCall, EthereumStateOverrides,
     MockEthereumAdapter, ProviderEthRpcMetrics, SubgraphEthRpcMetrics, TriggerFilter,
 };
 pub use crate::chain::{Chain, WrappedBlockFinality};
@@ -42,6 +42,14 @@ impl LinkResolverTrait for TextResolver {
             .map(|text| text.to_owned().into_bytes())
     }
 
+    async fn ls(
+        &self,
+        _logger: &Logger,
+        _link: &Link,
+    ) -> Result<Vec<(String, String)>, anyhow::Error> {
+        unimplemented!()
+    }
+
     async fn json_stream(
         &self,
         _logger: &Logger,
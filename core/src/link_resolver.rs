@@ -117,7 +117,7 @@ async fn select_fastest_client_with_stat(
             Ok((stat, index)) => {
                 return Ok((stat, clients[index].cheap_clone()));
             }
-            Err(e) => err = Some(e.into()),
+            Err(e) => err = Some(ipfs_error(&path, e)),
         }
     }
 
@@ -129,6 +129,16 @@ async fn select_fastest_client_with_stat(
     }))
 }
 
+/// Reports a 404 response as `LinkNotFound` so that callers can tell missing
+/// content apart from other failures.
+fn ipfs_error(path: &str, e: reqwest::Error) -> Error {
+    if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
+        LinkNotFound(path.to_owned()).into()
+    } else {
+        e.into()
+    }
+}
+
 // Returns an error if the stat is bigger than `max_file_bytes`
 fn restrict_file_size(
     path: &str,
@@ -186,20 +196,15 @@ impl From<Vec<IpfsClient>> for LinkResolver {
     }
 }
 
-#[async_trait]
-impl LinkResolverTrait for LinkResolver {
-    fn with_timeout(mut self, timeout: Duration) -> Self {
-        self.timeout = timeout;
-        self
-    }
-
-    fn with_retries(mut self) -> Self {
-        self.retry = true;
-        self
-    }
-
-    /// Supports links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`.
-    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+impl LinkResolver {
+    /// Fetches `link` from whichever of `clients` has it first. Supports
+    /// links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`.
+    async fn cat_with_clients(
+        &self,
+        logger: &Logger,
+        link: &Link,
+        clients: Arc<Vec<Arc<IpfsClient>>>,
+    ) -> Result<Vec<u8>, Error> {
         // Discard the `/ipfs/` prefix (if present) to get the hash.
         let path = link.link.trim_start_matches("/ipfs/").to_owned();
 
@@ -210,7 +215,7 @@ impl LinkResolverTrait for LinkResolver {
         trace!(logger, "IPFS cache miss"; "hash" => &path);
 
         let (stat, client) = select_fastest_client_with_stat(
-            self.clients.cheap_clone(),
+            clients,
             logger.cheap_clone(),
             path.clone(),
             self.timeout,
@@ -223,13 +228,13 @@ impl LinkResolverTrait for LinkResolver {
         let max_file_size: Option<u64> = read_u64_from_env(MAX_IPFS_FILE_SIZE_VAR);
         restrict_file_size(&path, &stat, &max_file_size)?;
 
-        let path = path.clone();
         let this = self.clone();
         let timeout = self.timeout.clone();
         let logger = logger.clone();
+        let cat_path = path.clone();
         let data = retry_policy(self.retry, "ipfs.cat", &logger)
             .run(move || {
-                let path = path.clone();
+                let path = cat_path.clone();
                 let client = client.clone();
                 let this = this.clone();
                 let logger = logger.clone();
@@ -254,10 +259,95 @@ impl LinkResolverTrait for LinkResolver {
                 .compat()
             })
             .compat()
-            .await?;
+            .await
+            .map_err(|e| ipfs_error(&path, e))?;
 
         Ok(data)
     }
+}
+
+#[async_trait]
+impl LinkResolverTrait for LinkResolver {
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn with_retries(mut self) -> Self {
+        self.retry = true;
+        self
+    }
+
+    /// Supports links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`.
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        self.cat_with_clients(logger, link, self.clients.cheap_clone())
+            .await
+    }
+
+    /// Fetches from the IPFS node whose URL is `gateway`, if it is one of
+    /// the configured nodes, rather than from whichever node responds first.
+    async fn cat_from_gateway(
+        &self,
+        logger: &Logger,
+        link: &Link,
+        gateway: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        let clients = match gateway {
+            Some(gateway) => {
+                let gateway = gateway.trim_end_matches('/');
+                match self
+                    .clients
+                    .iter()
+                    .find(|client| client.base().to_string().trim_end_matches('/') == gateway)
+                {
+                    Some(client) => Arc::new(vec![client.cheap_clone()]),
+                    None => {
+                        debug!(logger, "Unknown IPFS gateway, using all configured nodes";
+                               "gateway" => gateway);
+                        self.clients.cheap_clone()
+                    }
+                }
+            }
+            None => self.clients.cheap_clone(),
+        };
+        self.cat_with_clients(logger, link, clients).await
+    }
+
+    /// Supports links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`.
+    async fn ls(&self, logger: &Logger, link: &Link) -> Result<Vec<(String, String)>, Error> {
+        // Discard the `/ipfs/` prefix (if present) to get the hash.
+        let path = link.link.trim_start_matches("/ipfs/").to_owned();
+
+        let (_, client) = select_fastest_client_with_stat(
+            self.clients.cheap_clone(),
+            logger.cheap_clone(),
+            path.clone(),
+            self.timeout,
+            self.retry,
+        )
+        .await?;
+
+        let timeout = self.timeout;
+        let ls_path = path.clone();
+        let response = retry_policy(self.retry, "ipfs.ls", logger)
+            .run(move || {
+                let path = ls_path.clone();
+                let client = client.clone();
+                async move { client.ls(path, timeout).await }
+                    .boxed()
+                    .compat()
+            })
+            .compat()
+            .await
+            .map_err(|e| ipfs_error(&path, e))?;
+
+        Ok(response
+            .objects
+            .into_iter()
+            .flat_map(|object| object.links)
+            .map(|link| (link.name, link.hash))
+            .collect())
+    }
 
     async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error> {
         // Discard the `/ipfs/` prefix (if present) to get the hash.
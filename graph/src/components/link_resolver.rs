@@ -20,6 +20,13 @@ pub struct JsonStreamValue {
 pub type JsonValueStream =
     Pin<Box<dyn Stream<Item = Result<JsonStreamValue, Error>> + Send + 'static>>;
 
+/// The error a `LinkResolver` returns when the content behind a link does
+/// not exist, as opposed to being unavailable at the moment, e.g., because of
+/// a timeout.
+#[derive(Debug, thiserror::Error)]
+#[error("Content of `{0}` not found")]
+pub struct LinkNotFound(pub String);
+
 /// Resolves links to subgraph manifests and resources referenced by them.
 #[async_trait]
 pub trait LinkResolver: Send + Sync + 'static {
@@ -36,9 +43,62 @@ pub trait LinkResolver: Send + Sync + 'static {
     /// Fetches the link contents as bytes.
     async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error>;
 
+    /// Like `cat`, but fetches the contents from `gateway` if the resolver
+    /// knows an IPFS node by that URL. Otherwise, and if no gateway is given,
+    /// the contents are fetched like with `cat`, which is also what the
+    /// default implementation does.
+    async fn cat_from_gateway(
+        &self,
+        logger: &Logger,
+        link: &Link,
+        _gateway: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        self.cat(logger, link).await
+    }
+
+    /// Fetches the link contents as bytes, together with their MIME type if
+    /// it is known. By default, the type is sniffed from the contents.
+    async fn cat_with_content_type(
+        &self,
+        logger: &Logger,
+        link: &Link,
+    ) -> Result<(Vec<u8>, Option<String>), Error> {
+        let bytes = self.cat(logger, link).await?;
+        let content_type = sniff_content_type(&bytes).map(str::to_owned);
+        Ok((bytes, content_type))
+    }
+
+    /// Lists the entries of the directory behind `link` as pairs of entry
+    /// name and link to the entry.
+    async fn ls(&self, logger: &Logger, link: &Link) -> Result<Vec<(String, String)>, Error>;
+
     /// Read the contents of `link` and deserialize them into a stream of JSON
     /// values. The values must each be on a single line; newlines are significant
     /// as they are used to split the file contents and each line is deserialized
     /// separately.
     async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error>;
 }
+
+/// Guesses the MIME type of `bytes` from their contents. Recognizes JSON, a
+/// few common binary formats by their magic numbers, and falls back to
+/// `text/plain` for any other valid UTF-8.
+pub fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+    ];
+
+    if let Some((_, content_type)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+    {
+        return Some(*content_type);
+    }
+    if serde_json::from_slice::<Value>(bytes).is_ok() {
+        return Some("application/json");
+    }
+    std::str::from_utf8(bytes).ok().map(|_| "text/plain")
+}
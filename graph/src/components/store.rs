@@ -1044,6 +1044,15 @@ pub trait WritableStore: Send + Sync + 'static {
         ids_for_type: BTreeMap<&EntityType, Vec<&str>>,
     ) -> Result<BTreeMap<EntityType, Vec<Entity>>, StoreError>;
 
+    /// Look up the entities of type `entity_type` that match `filter` as of
+    /// the latest block, returning at most `first` of them.
+    fn find_matching(
+        &self,
+        entity_type: &EntityType,
+        filter: EntityFilter,
+        first: u32,
+    ) -> Result<Vec<Entity>, QueryExecutionError>;
+
     /// The deployment `id` finished syncing, mark it as synced in the database
     /// and promote it to the current version in the subgraphs where it was the
     /// pending version so far
@@ -1214,6 +1223,15 @@ impl WritableStore for MockStore {
         self.get_many_mock(ids_for_type)
     }
 
+    fn find_matching(
+        &self,
+        _: &EntityType,
+        _: EntityFilter,
+        _: u32,
+    ) -> Result<Vec<Entity>, QueryExecutionError> {
+        unimplemented!()
+    }
+
     fn is_deployment_synced(&self) -> Result<bool, Error> {
         unimplemented!()
     }
@@ -1567,6 +1585,70 @@ impl EntityCache {
         Ok(entity)
     }
 
+    /// Whether the last change to the entity with `key` in this block removed
+    /// it. This is also the case if the entity did not exist when it was
+    /// removed.
+    pub fn is_removed(&self, key: &EntityKey) -> bool {
+        let op = self
+            .handler_updates
+            .get(key)
+            .or_else(|| self.updates.get(key));
+        matches!(op, Some(EntityOp::Remove))
+    }
+
+    /// Looks up the entities of type `entity_type` whose `field` is `value`,
+    /// returning at most `first` of them. Entities with changes that have not
+    /// been written to the store yet are matched in their changed form.
+    ///
+    /// This looks at every entity of `entity_type` that was changed in this
+    /// block, and then queries the store, excluding the changed entities.
+    /// Calling it for every change to entities of one type is therefore
+    /// quadratic in the number of changes in the block.
+    pub fn find_by_field(
+        &mut self,
+        entity_type: &EntityType,
+        field: &str,
+        value: &Value,
+        first: usize,
+    ) -> Result<Vec<Entity>, QueryExecutionError> {
+        let mut changed: Vec<EntityKey> = self
+            .updates
+            .keys()
+            .chain(self.handler_updates.keys())
+            .filter(|key| &key.entity_type == entity_type)
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        changed.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+
+        let mut entities = Vec::new();
+        for key in &changed {
+            if let Some(entity) = self.get(key)? {
+                if entity.get(field) == Some(value) {
+                    entities.push(entity);
+                }
+            }
+        }
+        if entities.len() >= first {
+            entities.truncate(first);
+            return Ok(entities);
+        }
+
+        // The store only has the old version of changed entities
+        let mut filter = EntityFilter::Equal(field.to_string(), value.clone());
+        if !changed.is_empty() {
+            let ids = changed
+                .into_iter()
+                .map(|key| Value::String(key.entity_id))
+                .collect();
+            filter = EntityFilter::And(vec![filter, EntityFilter::NotIn("id".to_string(), ids)]);
+        }
+        let remaining = (first - entities.len()) as u32;
+        entities.extend(self.store.find_matching(entity_type, filter, remaining)?);
+        Ok(entities)
+    }
+
     pub fn remove(&mut self, key: EntityKey) {
         self.entity_op(key, EntityOp::Remove);
     }
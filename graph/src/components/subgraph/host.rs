@@ -67,6 +67,7 @@ pub trait RuntimeHost<C: Blockchain>: Send + Sync + 'static {
 pub struct HostMetrics {
     handler_execution_time: Box<HistogramVec>,
     host_fn_execution_time: Box<HistogramVec>,
+    eth_call_execution_time: Box<HistogramVec>,
     pub stopwatch: StopwatchMetrics,
 }
 
@@ -101,9 +102,19 @@ impl HostMetrics {
                 vec![0.025, 0.05, 0.2, 2.0, 8.0, 20.0],
             )
             .expect("failed to create `deployment_host_fn_execution_time` histogram");
+        let eth_call_execution_time = registry
+            .new_deployment_histogram_vec(
+                "deployment_eth_call_execution_time",
+                "Measures the execution time for `ethereum.call`, by where the result came from",
+                subgraph,
+                vec![String::from("source")],
+                vec![0.001, 0.025, 0.05, 0.2, 2.0, 8.0, 20.0],
+            )
+            .expect("failed to create `deployment_eth_call_execution_time` histogram");
         Self {
             handler_execution_time,
             host_fn_execution_time,
+            eth_call_execution_time,
             stopwatch,
         }
     }
@@ -120,6 +131,21 @@ impl HostMetrics {
             .observe(duration);
     }
 
+    /// `source` is one of `memo`, `call_cache` or `node`; the cache hit rate
+    /// is the share of calls not answered by the node.
+    pub fn observe_eth_call_execution_time(&self, duration: f64, source: &str) {
+        self.eth_call_execution_time
+            .with_label_values(&[source][..])
+            .observe(duration);
+    }
+
+    /// The number of `ethereum.call`s observed for `source`.
+    pub fn eth_call_count(&self, source: &str) -> u64 {
+        self.eth_call_execution_time
+            .with_label_values(&[source][..])
+            .get_sample_count()
+    }
+
     pub fn time_host_fn_execution_region(
         self: Arc<HostMetrics>,
         fn_name: &'static str,
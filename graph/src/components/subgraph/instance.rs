@@ -1,7 +1,8 @@
-use crate::blockchain::Blockchain;
+use crate::blockchain::{Blockchain, DataSourceTemplate as _};
 use crate::prelude::*;
 use crate::util::lfu_cache::LfuCache;
 use crate::{components::store::WritableStore, data::subgraph::schema::SubgraphError};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Debug)]
 pub struct DataSourceTemplateInfo<C: Blockchain> {
@@ -20,6 +21,25 @@ pub struct BlockState<C: Blockchain> {
     // Data sources created in the current handler.
     handler_created_data_sources: Vec<DataSourceTemplateInfo<C>>,
 
+    // Number of data sources created in this block by handlers that have
+    // completed. Unlike `created_data_sources`, this is not reset by
+    // `drain_created_data_sources`.
+    created_data_sources_count: usize,
+
+    // Template names and params of the data sources counted in
+    // `created_data_sources_count`.
+    created_data_source_keys: HashSet<(String, Vec<String>)>,
+
+    // Number of times each entity was set or removed in this block by
+    // handlers that have completed.
+    entity_writes: HashMap<EntityKey, u64>,
+
+    // Number of times each entity was set or removed in the current handler.
+    handler_entity_writes: HashMap<EntityKey, u64>,
+
+    // The next value that `next_sequence` returns.
+    sequence: u64,
+
     // Marks whether a handler is currently executing.
     in_handler: bool,
 }
@@ -34,6 +54,11 @@ impl<C: Blockchain> BlockState<C> {
             deterministic_errors: Vec::new(),
             created_data_sources: Vec::new(),
             handler_created_data_sources: Vec::new(),
+            created_data_sources_count: 0,
+            created_data_source_keys: HashSet::new(),
+            entity_writes: HashMap::new(),
+            handler_entity_writes: HashMap::new(),
+            sequence: 0,
             in_handler: false,
         }
     }
@@ -46,12 +71,29 @@ impl<C: Blockchain> BlockState<C> {
             deterministic_errors,
             created_data_sources,
             handler_created_data_sources,
+            created_data_sources_count,
+            created_data_source_keys,
+            entity_writes,
+            handler_entity_writes,
+            sequence: _,
             in_handler,
         } = self;
 
         match in_handler {
             true => handler_created_data_sources.extend(other.created_data_sources),
-            false => created_data_sources.extend(other.created_data_sources),
+            false => {
+                *created_data_sources_count += other.created_data_sources.len();
+                created_data_source_keys
+                    .extend(other.created_data_sources.iter().map(Self::data_source_key));
+                created_data_sources.extend(other.created_data_sources)
+            }
+        }
+        let entity_writes = match in_handler {
+            true => handler_entity_writes,
+            false => entity_writes,
+        };
+        for (key, writes) in other.entity_writes {
+            *entity_writes.entry(key).or_insert(0) += writes;
         }
         deterministic_errors.extend(other.deterministic_errors);
         entity_cache.extend(other.entity_cache);
@@ -71,6 +113,55 @@ impl<C: Blockchain> BlockState<C> {
         std::mem::replace(&mut self.created_data_sources, Vec::new())
     }
 
+    /// The number of data sources created while processing this block so
+    /// far, including those created by the current handler.
+    pub fn created_data_sources_count(&self) -> usize {
+        self.created_data_sources_count + self.handler_created_data_sources.len()
+    }
+
+    /// Whether a data source from template `name` with `params` was already
+    /// created while processing this block, including by the current
+    /// handler.
+    pub fn has_created_data_source(&self, name: &str, params: &[String]) -> bool {
+        self.created_data_source_keys
+            .contains(&(name.to_owned(), params.to_vec()))
+            || self
+                .handler_created_data_sources
+                .iter()
+                .any(|ds| ds.template.name() == name && ds.params == params)
+    }
+
+    fn data_source_key(ds: &DataSourceTemplateInfo<C>) -> (String, Vec<String>) {
+        (ds.template.name().to_owned(), ds.params.clone())
+    }
+
+    pub fn record_entity_write(&mut self, key: &EntityKey) {
+        let entity_writes = match self.in_handler {
+            true => &mut self.handler_entity_writes,
+            false => &mut self.entity_writes,
+        };
+        *entity_writes.entry(key.clone()).or_insert(0) += 1;
+    }
+
+    /// The number of times the entity with `key` was set or removed while
+    /// processing this block so far, including by the current handler.
+    /// Writes of handlers whose changes were discarded do not count.
+    pub fn entity_writes(&self, key: &EntityKey) -> u64 {
+        self.entity_writes.get(key).copied().unwrap_or(0)
+            + self.handler_entity_writes.get(key).copied().unwrap_or(0)
+    }
+
+    /// Returns 0 on the first call while processing a block, and one more
+    /// than the previous value on every further call. Since each block
+    /// starts with a new state, the sequence restarts with every block. The
+    /// separate states that `ipfs.map` callbacks run with have their own
+    /// sequences, and merging them into this state leaves it unchanged.
+    pub fn next_sequence(&mut self) -> u64 {
+        let value = self.sequence;
+        self.sequence += 1;
+        value
+    }
+
     pub fn enter_handler(&mut self) {
         assert!(!self.in_handler);
         self.in_handler = true;
@@ -80,8 +171,17 @@ impl<C: Blockchain> BlockState<C> {
     pub fn exit_handler(&mut self) {
         assert!(self.in_handler);
         self.in_handler = false;
+        self.created_data_sources_count += self.handler_created_data_sources.len();
+        self.created_data_source_keys.extend(
+            self.handler_created_data_sources
+                .iter()
+                .map(Self::data_source_key),
+        );
         self.created_data_sources
             .extend(self.handler_created_data_sources.drain(..));
+        for (key, writes) in self.handler_entity_writes.drain() {
+            *self.entity_writes.entry(key).or_insert(0) += writes;
+        }
         self.entity_cache.exit_handler()
     }
 
@@ -89,6 +189,7 @@ impl<C: Blockchain> BlockState<C> {
         assert!(self.in_handler);
         self.in_handler = false;
         self.handler_created_data_sources.clear();
+        self.handler_entity_writes.clear();
         self.entity_cache.exit_handler_and_discard_changes();
         self.deterministic_errors.push(e);
     }
@@ -16,6 +16,105 @@ pub enum ProofOfIndexingEvent<'a> {
         id: &'a str,
         data: &'a HashMap<String, Value>,
     },
+    /// A value that a mapping computed and wants covered by the PoI without
+    /// storing it in an entity.
+    Custom {
+        name: &'a str,
+        data: &'a HashMap<String, Value>,
+    },
+    /// An `eth_call` made against a state with the accounts in `overrides`
+    /// changed, whose result can therefore not be checked against the chain.
+    EthereumCallWithOverrides {
+        address: &'a str,
+        call_data: &'a str,
+        overrides: &'a str,
+    },
+}
+
+/// An owned copy of a `ProofOfIndexingEvent`, for events that are kept to be
+/// written later.
+pub(super) enum RecordedEvent {
+    RemoveEntity {
+        entity_type: String,
+        id: String,
+    },
+    SetEntity {
+        entity_type: String,
+        id: String,
+        data: HashMap<String, Value>,
+    },
+    Custom {
+        name: String,
+        data: HashMap<String, Value>,
+    },
+    EthereumCallWithOverrides {
+        address: String,
+        call_data: String,
+        overrides: String,
+    },
+}
+
+impl From<&ProofOfIndexingEvent<'_>> for RecordedEvent {
+    fn from(event: &ProofOfIndexingEvent<'_>) -> Self {
+        use ProofOfIndexingEvent::*;
+        match event {
+            RemoveEntity { entity_type, id } => RecordedEvent::RemoveEntity {
+                entity_type: entity_type.to_string(),
+                id: id.to_string(),
+            },
+            SetEntity {
+                entity_type,
+                id,
+                data,
+            } => RecordedEvent::SetEntity {
+                entity_type: entity_type.to_string(),
+                id: id.to_string(),
+                data: (*data).clone(),
+            },
+            Custom { name, data } => RecordedEvent::Custom {
+                name: name.to_string(),
+                data: (*data).clone(),
+            },
+            EthereumCallWithOverrides {
+                address,
+                call_data,
+                overrides,
+            } => RecordedEvent::EthereumCallWithOverrides {
+                address: address.to_string(),
+                call_data: call_data.to_string(),
+                overrides: overrides.to_string(),
+            },
+        }
+    }
+}
+
+impl RecordedEvent {
+    pub(super) fn as_event(&self) -> ProofOfIndexingEvent<'_> {
+        match self {
+            RecordedEvent::RemoveEntity { entity_type, id } => {
+                ProofOfIndexingEvent::RemoveEntity { entity_type, id }
+            }
+            RecordedEvent::SetEntity {
+                entity_type,
+                id,
+                data,
+            } => ProofOfIndexingEvent::SetEntity {
+                entity_type,
+                id,
+                data,
+            },
+            RecordedEvent::Custom { name, data } => ProofOfIndexingEvent::Custom { name, data },
+            RecordedEvent::EthereumCallWithOverrides {
+                address,
+                call_data,
+                overrides,
+            } => ProofOfIndexingEvent::EthereumCallWithOverrides {
+                address,
+                call_data,
+                overrides,
+            },
+        }
+    }
 }
 
 impl StableHash for ProofOfIndexingEvent<'_> {
@@ -37,6 +136,19 @@ impl StableHash for ProofOfIndexingEvent<'_> {
                 id.stable_hash(sequence_number.next_child(), state);
                 data.stable_hash(sequence_number.next_child(), state);
             }
+            Custom { name, data } => {
+                name.stable_hash(sequence_number.next_child(), state);
+                data.stable_hash(sequence_number.next_child(), state);
+            }
+            EthereumCallWithOverrides {
+                address,
+                call_data,
+                overrides,
+            } => {
+                address.stable_hash(sequence_number.next_child(), state);
+                call_data.stable_hash(sequence_number.next_child(), state);
+                overrides.stable_hash(sequence_number.next_child(), state);
+            }
         }
     }
 }
@@ -62,6 +174,19 @@ impl fmt::Debug for ProofOfIndexingEvent<'_> {
                 builder.field("id", id);
                 builder.field("data", &data.iter().collect::<BTreeMap<_, _>>());
             }
+            Self::Custom { name, data } => {
+                builder.field("name", name);
+                builder.field("data", &data.iter().collect::<BTreeMap<_, _>>());
+            }
+            Self::EthereumCallWithOverrides {
+                address,
+                call_data,
+                overrides,
+            } => {
+                builder.field("address", address);
+                builder.field("call_data", call_data);
+                builder.field("overrides", overrides);
+            }
         }
         builder.finish()
     }
@@ -261,4 +261,60 @@ mod tests {
             }
         }
     }
+
+    /// Replaying recorded events must give the same digests as writing them
+    /// directly.
+    #[test]
+    fn recording_replay() {
+        let logger = Logger::root(Discard, o!());
+        let data = hashmap! {
+            "val".to_owned() => Value::Int(1)
+        };
+        let events = vec![
+            (
+                "eth",
+                ProofOfIndexingEvent::SetEntity {
+                    entity_type: "type",
+                    id: "id",
+                    data: &data,
+                },
+            ),
+            (
+                "ipfs",
+                ProofOfIndexingEvent::Custom {
+                    name: "name",
+                    data: &data,
+                },
+            ),
+            (
+                "eth",
+                ProofOfIndexingEvent::RemoveEntity {
+                    entity_type: "type",
+                    id: "id",
+                },
+            ),
+        ];
+        let digests = |proof_of_indexing: ProofOfIndexing| {
+            let mut digests = proof_of_indexing
+                .take()
+                .into_iter()
+                .map(|(region, stream)| (region, stream.pause(None)))
+                .collect::<Vec<_>>();
+            digests.sort();
+            digests
+        };
+
+        let mut direct = ProofOfIndexing::new(1);
+        let mut recording = ProofOfIndexing::recording();
+        for (region, event) in &events {
+            direct.write(&logger, region, event);
+            recording.write(&logger, region, event);
+        }
+        let mut replayed = ProofOfIndexing::new(1);
+        recording.replay(&logger, &mut replayed);
+
+        let direct = digests(direct);
+        assert_eq!(2, direct.len());
+        assert_eq!(direct, digests(replayed));
+    }
 }
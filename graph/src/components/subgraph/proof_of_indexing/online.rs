@@ -2,6 +2,7 @@
 //! Any hash constructed from here should be the same as if the same data was given
 //! to the reference implementation, but this is updated incrementally
 
+use super::event::RecordedEvent;
 use super::ProofOfIndexingEvent;
 use crate::{
     blockchain::BlockPtr,
@@ -110,6 +111,10 @@ pub struct ProofOfIndexing {
     /// state with other data sources. This may also give us some freedom to change
     /// the order of triggers in the future.
     per_causality_region: HashMap<String, BlockEventStream>,
+    /// The events written to a `ProofOfIndexing` created with `recording`,
+    /// with their causality region. `None` for one that digests events as
+    /// they are written.
+    recorded: Option<Vec<(String, RecordedEvent)>>,
 }
 
 impl fmt::Debug for ProofOfIndexing {
@@ -123,8 +128,30 @@ impl ProofOfIndexing {
         Self {
             block_number,
             per_causality_region: HashMap::new(),
+            recorded: None,
         }
     }
+
+    /// A `ProofOfIndexing` that keeps the events written to it instead of
+    /// digesting them, so that `replay` can write them to another one later.
+    /// Work that runs in parallel can use this to contribute its events in a
+    /// deterministic order.
+    pub fn recording() -> Self {
+        Self {
+            recorded: Some(Vec::new()),
+            ..Self::default()
+        }
+    }
+
+    /// Writes the events kept by a `ProofOfIndexing` created with `recording`
+    /// to `target`, in the order in which they were written. Does nothing for
+    /// any other `ProofOfIndexing`.
+    pub fn replay(self, logger: &Logger, target: &mut ProofOfIndexing) {
+        for (causality_region, event) in self.recorded.into_iter().flatten() {
+            target.write(logger, &causality_region, &event.as_event());
+        }
+    }
+
     /// Adds an event to the digest of the ProofOfIndexingStream local to the causality region
     pub fn write(
         &mut self,
@@ -132,6 +159,11 @@ impl ProofOfIndexing {
         causality_region: &str,
         event: &ProofOfIndexingEvent<'_>,
     ) {
+        if let Some(recorded) = self.recorded.as_mut() {
+            recorded.push((causality_region.to_owned(), RecordedEvent::from(event)));
+            return;
+        }
+
         if *LOG_EVENTS {
             debug!(
                 logger,
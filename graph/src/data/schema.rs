@@ -53,6 +53,8 @@ pub enum SchemaValidationError {
     InterfaceFieldsMissing(String, String, Strings), // (type, interface, missing_fields)
     #[error("Field `{1}` in type `{0}` has invalid @derivedFrom: {2}")]
     InvalidDerivedFrom(String, String, String), // (type, field, reason)
+    #[error("Field `{1}` in type `{0}` has invalid @unique: {2}")]
+    InvalidUnique(String, String, String), // (type, field, reason)
     #[error("_Schema_ type is only for @imports and must not have any fields")]
     SchemaTypeWithFields,
     #[error("Imported subgraph name `{0}` is invalid")]
@@ -651,6 +653,7 @@ impl Schema {
         self.validate_directives_on_schema_type()
             .unwrap_or_else(|err| errors.push(err));
         errors.append(&mut self.validate_fields());
+        errors.append(&mut self.validate_unique());
         errors.append(&mut self.validate_import_directives());
         errors.append(&mut self.validate_fulltext_directives());
         errors.append(&mut self.validate_imported_types(schemas));
@@ -1075,6 +1078,45 @@ impl Schema {
         }
     }
 
+    /// Validate that `@unique` is only used on fields whose values can be
+    /// compared directly: scalars that are neither lists nor derived.
+    fn validate_unique(&self) -> Vec<SchemaValidationError> {
+        fn is_list_type(field_type: &Type) -> bool {
+            match field_type {
+                Type::ListType(_) => true,
+                Type::NonNullType(inner) => is_list_type(inner),
+                Type::NamedType(_) => false,
+            }
+        }
+
+        self.document
+            .get_object_type_definitions()
+            .iter()
+            .flat_map(|object_type| {
+                object_type
+                    .fields
+                    .iter()
+                    .filter(|field| field.find_directive("unique").is_some())
+                    .filter_map(move |field| {
+                        let reason = if field.find_directive("derivedFrom").is_some() {
+                            "derived fields can not be unique"
+                        } else if is_list_type(&field.field_type) {
+                            "list fields can not be unique"
+                        } else if !ValueType::is_scalar(field.field_type.get_base_type()) {
+                            "only fields with a scalar type can be unique"
+                        } else {
+                            return None;
+                        };
+                        Some(SchemaValidationError::InvalidUnique(
+                            object_type.name.to_owned(),
+                            field.name.to_owned(),
+                            reason.to_owned(),
+                        ))
+                    })
+            })
+            .collect()
+    }
+
     fn validate_derived_from(&self) -> Result<(), SchemaValidationError> {
         // Helper to construct a DerivedFromInvalid
         fn invalid(
@@ -1379,6 +1421,48 @@ type Account implements Address @entity { id: ID!, txn: Transaction! @derivedFro
     validate("j: B @derivedFrom(field: \"id\")", "ok");
 }
 
+#[test]
+fn test_unique_validation() {
+    const OTHER_TYPES: &str = "
+type B @entity { id: ID! a: A }
+enum E { X, Y }";
+
+    fn validate(field: &str, errmsg: &str) {
+        let raw = format!("type A @entity {{ id: ID!\n {} }}\n{}", field, OTHER_TYPES);
+
+        let document = graphql_parser::parse_schema(&raw)
+            .expect("Failed to parse raw schema")
+            .into_static();
+        let schema = Schema::new(DeploymentHash::new("id").unwrap(), document);
+        match schema.validate_unique().as_slice() {
+            [] => {
+                if errmsg != "ok" {
+                    panic!("expected validation for `{}` to fail", field)
+                }
+            }
+            [SchemaValidationError::InvalidUnique(_, _, msg)] => assert_eq!(errmsg, msg),
+            errors => panic!("expected one InvalidUnique error, got {:?}", errors),
+        }
+    }
+
+    validate("a: String @unique", "ok");
+    validate("b: BigInt! @unique", "ok");
+    validate("c: Bytes @unique", "ok");
+    validate("d: [String!]! @unique", "list fields can not be unique");
+    validate(
+        "e: B @unique",
+        "only fields with a scalar type can be unique",
+    );
+    validate(
+        "f: E @unique",
+        "only fields with a scalar type can be unique",
+    );
+    validate(
+        "g: B @derivedFrom(field: \"a\") @unique",
+        "derived fields can not be unique",
+    );
+}
+
 #[test]
 fn test_reserved_type_with_fields() {
     const ROOT_SCHEMA: &str = "
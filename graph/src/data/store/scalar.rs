@@ -14,7 +14,7 @@ use stable_hash::{
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Display, Formatter};
 use std::io::Write;
-use std::ops::{Add, BitAnd, BitOr, Deref, Div, Mul, Rem, Shl, Shr, Sub};
+use std::ops::{Add, BitAnd, BitOr, Deref, Div, Mul, Neg, Rem, Shl, Shr, Sub};
 use std::str::FromStr;
 
 pub use num_bigint::Sign as BigIntSign;
@@ -56,10 +56,41 @@ impl BigDecimal {
         BigDecimal(bigdecimal::BigDecimal::zero())
     }
 
+    pub fn abs(&self) -> BigDecimal {
+        Self::from(self.0.abs())
+    }
+
     pub fn as_bigint_and_exponent(&self) -> (num_bigint::BigInt, i64) {
         self.0.as_bigint_and_exponent()
     }
 
+    /// Divides `self` by `other` and rounds the quotient to `prec`
+    /// significant digits. Since the result is normalized, a `prec` larger
+    /// than `MAX_SIGNFICANT_DIGITS` has no effect.
+    pub fn div_with_prec(self, other: BigDecimal, prec: u64) -> BigDecimal {
+        if other == BigDecimal::from(0) {
+            panic!("Cannot divide by zero-valued `BigDecimal`!")
+        }
+
+        Self::from(self.0.div(other.0).with_prec(prec))
+    }
+
+    /// Splits `self` into its integer part, rounded towards zero, and the
+    /// remaining fraction, which has the same sign as `self`.
+    pub fn split_fraction(&self) -> (BigInt, BigDecimal) {
+        use num_traits::pow::Pow;
+
+        let (digits, scale) = self.0.as_bigint_and_exponent();
+        if scale <= 0 {
+            let int = digits * num_bigint::BigInt::from(10).pow((-scale) as u64);
+            return (BigInt(int), BigDecimal::zero());
+        }
+        let divisor = num_bigint::BigInt::from(10).pow(scale as u64);
+        let int = &digits / &divisor;
+        let fraction = bigdecimal::BigDecimal::new(digits % divisor, scale);
+        (BigInt(int), BigDecimal::from(fraction))
+    }
+
     pub(crate) fn digits(&self) -> u64 {
         self.0.digits()
     }
@@ -148,6 +179,14 @@ impl Mul for BigDecimal {
     }
 }
 
+impl Neg for BigDecimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from(self.0.neg())
+    }
+}
+
 impl Div for BigDecimal {
     type Output = Self;
 
@@ -316,6 +355,39 @@ impl BigInt {
         U256::from_little_endian(&bytes)
     }
 
+    /// Computes `self ^ exponent mod modulus` without building the power.
+    /// The result is in `[0, modulus)` for a positive `modulus`, also when
+    /// `self` is negative.
+    ///
+    /// Panics if `exponent` is negative or `modulus` is zero.
+    pub fn mod_pow(&self, exponent: &BigInt, modulus: &BigInt) -> BigInt {
+        BigInt(self.0.modpow(&exponent.0, &modulus.0))
+    }
+
+    /// The nearest `f64` to `self`, or `None` if `self` is too large to be
+    /// represented as a finite `f64`.
+    pub fn to_f64(&self) -> Option<f64> {
+        num_traits::ToPrimitive::to_f64(&self.0).filter(|x| x.is_finite())
+    }
+
+    /// Formats `self` in base `radix`, with lowercase letters for digits
+    /// above 9.
+    ///
+    /// Panics if `radix` is not between 2 and 36.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        self.0.to_str_radix(radix)
+    }
+
+    /// Parses `s` as a number in base `radix`. Letters can be upper or lower
+    /// case.
+    ///
+    /// Panics if `radix` is not between 2 and 36.
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<BigInt, num_bigint::ParseBigIntError> {
+        use num_traits::Num;
+
+        num_bigint::BigInt::from_str_radix(s, radix).map(BigInt)
+    }
+
     pub fn pow(self, exponent: u8) -> Self {
         use num_traits::pow::Pow;
 
@@ -28,6 +28,26 @@ pub struct AddResponse {
     pub size: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LsResponse {
+    pub objects: Vec<LsObject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LsObject {
+    pub hash: String,
+    pub links: Vec<LsLink>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LsLink {
+    pub name: String,
+    pub hash: String,
+}
+
 #[derive(Clone)]
 pub struct IpfsClient {
     base: Arc<Uri>,
@@ -51,6 +71,11 @@ impl IpfsClient {
         })
     }
 
+    /// The URL of the IPFS node this client talks to.
+    pub fn base(&self) -> &Uri {
+        &self.base
+    }
+
     pub fn localhost() -> Self {
         IpfsClient {
             client: Arc::new(reqwest::Client::new()),
@@ -70,6 +95,14 @@ impl IpfsClient {
             .await
     }
 
+    /// Calls `ls` to list the links of a directory.
+    pub async fn ls(&self, cid: String, timeout: Duration) -> Result<LsResponse, reqwest::Error> {
+        self.call(self.url("ls", cid), None, Some(timeout))
+            .await?
+            .json()
+            .await
+    }
+
     /// Download the entire contents.
     pub async fn cat_all(&self, cid: String, timeout: Duration) -> Result<Bytes, reqwest::Error> {
         self.call(self.url("cat", cid), None, Some(timeout))
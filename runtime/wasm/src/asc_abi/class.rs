@@ -478,6 +478,38 @@ pub(crate) struct AscLogParam {
 
 pub(crate) type Bytes = Uint8Array;
 
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscIpfsFile {
+    pub data: AscPtr<Uint8Array>,
+    pub content_type: AscPtr<AscString>,
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscIpfsLink {
+    pub name: AscPtr<AscString>,
+    pub cid: AscPtr<AscString>,
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEntityWithVersion {
+    pub entity: AscPtr<AscEntity>,
+    pub _padding: u32, // Make padding explicit.
+    pub version: u64,
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEntityWithStatus {
+    /// Null unless the entity is present.
+    pub entity: AscPtr<AscEntity>,
+    /// 0 if the entity is absent, 1 if it is present, and 2 if it was
+    /// deleted in the current block.
+    pub status: u32,
+}
+
 /// Big ints are represented using signed number representation. Note: This differs
 /// from how U256 and U128 are represented (they use two's complement). So whenever
 /// we convert between them, we need to make sure we handle signed and unsigned
@@ -19,8 +19,9 @@ use graph::{
 use graph_chain_ethereum::MappingTrigger;
 use graph_chain_ethereum::{EthereumAdapterTrait, EthereumNetworks};
 
+use crate::host_exports::{ArweaveCache, HostExports, HostExportsBuilder};
 use crate::mapping::{MappingContext, MappingRequest};
-use crate::{host_exports::HostExports, module::ExperimentalFeatures};
+use crate::module::ExperimentalFeatures;
 
 lazy_static! {
     static ref TIMEOUT: Option<Duration> = std::env::var("GRAPH_MAPPING_HANDLER_TIMEOUT")
@@ -33,6 +34,17 @@ lazy_static! {
         std::env::var("GRAPH_ALLOW_NON_DETERMINISTIC_3BOX").is_ok();
     static ref ALLOW_NON_DETERMINISTIC_ARWEAVE: bool =
         std::env::var("GRAPH_ALLOW_NON_DETERMINISTIC_ARWEAVE").is_ok();
+    static ref ALLOW_CUSTOM_POI_EVENTS: bool =
+        std::env::var("GRAPH_ALLOW_CUSTOM_POI_EVENTS").is_ok();
+    static ref CHECK_ETHEREUM_DECODE: bool =
+        std::env::var("GRAPH_CHECK_ETHEREUM_DECODE").is_ok();
+
+    /// Size of the Arweave transaction data cache in kilobytes, defaults to 10MB.
+    static ref ARWEAVE_CACHE_SIZE: usize = std::env::var("GRAPH_ARWEAVE_CACHE_SIZE")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_ARWEAVE_CACHE_SIZE"))
+        .unwrap_or(10000)
+        * 1000;
 }
 
 pub struct RuntimeHostBuilder<S, CC> {
@@ -41,6 +53,7 @@ pub struct RuntimeHostBuilder<S, CC> {
     store: Arc<S>,
     caches: Arc<CC>,
     arweave_adapter: Arc<dyn ArweaveAdapter>,
+    arweave_cache: Arc<ArweaveCache>,
     three_box_adapter: Arc<dyn ThreeBoxAdapter>,
 }
 
@@ -56,6 +69,7 @@ where
             store: self.store.clone(),
             caches: self.caches.clone(),
             arweave_adapter: self.arweave_adapter.cheap_clone(),
+            arweave_cache: self.arweave_cache.cheap_clone(),
             three_box_adapter: self.three_box_adapter.cheap_clone(),
         }
     }
@@ -80,6 +94,7 @@ where
             store,
             caches,
             arweave_adapter,
+            arweave_cache: Arc::new(ArweaveCache::new(*ARWEAVE_CACHE_SIZE)),
             three_box_adapter,
         }
     }
@@ -108,6 +123,8 @@ where
             allow_non_deterministic_arweave: *ALLOW_NON_DETERMINISTIC_ARWEAVE,
             allow_non_deterministic_3box: *ALLOW_NON_DETERMINISTIC_3BOX,
             allow_non_deterministic_ipfs: *ALLOW_NON_DETERMINISTIC_IPFS,
+            allow_custom_poi_events: *ALLOW_CUSTOM_POI_EVENTS,
+            check_ethereum_decode: *CHECK_ETHEREUM_DECODE,
         };
         crate::mapping::spawn_module(
             raw_module,
@@ -145,7 +162,7 @@ where
             .ethereum_networks
             .adapter_with_capabilities(network_name.clone(), &required_capabilities)?;
 
-        Ok(RuntimeHost::new(
+        RuntimeHost::new(
             ethereum_adapter.clone(),
             self.link_resolver.clone(),
             self.store.clone(),
@@ -157,8 +174,9 @@ where
             mapping_request_sender,
             metrics,
             self.arweave_adapter.cheap_clone(),
+            self.arweave_cache.cheap_clone(),
             self.three_box_adapter.cheap_clone(),
-        ))
+        )
     }
 }
 
@@ -186,29 +204,31 @@ where
         mapping_request_sender: Sender<MappingRequest<C>>,
         metrics: Arc<HostMetrics>,
         arweave_adapter: Arc<dyn ArweaveAdapter>,
+        arweave_cache: Arc<ArweaveCache>,
         three_box_adapter: Arc<dyn ThreeBoxAdapter>,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         // Create new instance of externally hosted functions invoker. The `Arc` is simply to avoid
         // implementing `Clone` for `HostExports`.
-        let host_exports = Arc::new(HostExports::new(
-            subgraph_id,
-            &data_source,
-            network_name,
-            templates,
-            ethereum_adapter,
-            link_resolver,
-            store,
-            call_cache,
-            arweave_adapter,
-            three_box_adapter,
-        ));
+        let host_exports = Arc::new(
+            HostExportsBuilder::new(subgraph_id, &data_source)
+                .data_source_network(network_name)
+                .templates(templates)
+                .ethereum_adapter(ethereum_adapter)
+                .link_resolver(link_resolver)
+                .store(store)
+                .call_cache(call_cache)
+                .arweave_adapter(arweave_adapter)
+                .arweave_cache(arweave_cache)
+                .three_box_adapter(three_box_adapter)
+                .build()?,
+        );
 
-        RuntimeHost {
+        Ok(RuntimeHost {
             data_source,
             mapping_request_sender,
             host_exports,
             metrics,
-        }
+        })
     }
 
     /// Sends a MappingRequest to the thread which owns the host,
@@ -1,37 +1,274 @@
 use crate::{error::DeterminismLevel, module::IntoTrap, UnresolvedContractCall};
+use atomic_refcell::AtomicRefCell;
 use ethabi::param_type::Reader;
-use ethabi::{decode, encode, Address, Token};
+use ethabi::{decode, encode, Address, ParamType, Token};
 use graph::blockchain::{Blockchain, DataSourceTemplate as _};
 use graph::components::store::EntityKey;
-use graph::components::subgraph::{ProofOfIndexingEvent, SharedProofOfIndexing};
+use graph::components::subgraph::{ProofOfIndexing, ProofOfIndexingEvent, SharedProofOfIndexing};
 use graph::components::three_box::ThreeBoxAdapter;
 use graph::components::{arweave::ArweaveAdapter, store::EntityType};
+use graph::data::graphql::ext::DirectiveFinder;
+use graph::data::graphql::{DirectiveExt, DocumentExt, ObjectTypeExt, TypeExt};
 use graph::data::store;
 use graph::prelude::serde_json;
 use graph::prelude::{slog::b, slog::record_static, *};
 use graph::runtime::DeterministicHostError;
+use graph::util::lfu_cache::LfuCache;
 use graph::{blockchain::DataSource, bytes::Bytes};
-use graph_chain_ethereum::{EthereumAdapterTrait, EthereumContractCall, EthereumContractCallError};
+use graph_chain_ethereum::{
+    EthereumAdapterTrait, EthereumContractCall, EthereumContractCallError, EthereumRawContractCall,
+};
 use never::Never;
+use ring::constant_time::verify_slices_are_equal;
+use ring::hmac;
+use ring::signature::{UnparsedPublicKey, ED25519};
 use semver::Version;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use web3::types::H160;
+use web3::types::{H160, H256};
 
 use graph::ensure;
 use graph_graphql::prelude::validate_entity;
+use graph_graphql::schema::ast::is_list_or_non_null_list_field;
 use wasmtime::Trap;
 
-use crate::module::{WasmInstance, WasmInstanceContext};
+use crate::mapping::{MappingContext, ValidModule};
+use crate::module::{ExperimentalFeatures, WasmInstance, WasmInstanceContext};
+
+lazy_static! {
+    /// Maximum number of log messages a single handler invocation may emit.
+    /// Further messages are dropped. Unlimited by default.
+    static ref MAX_LOGS_PER_HANDLER: Option<usize> = std::env::var("GRAPH_MAX_LOGS_PER_HANDLER")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_MAX_LOGS_PER_HANDLER"));
+
+    static ref CONTRACT_CALL_FILTER: ContractCallFilter = ContractCallFilter::from_env();
+
+    /// Maximum size in bytes of a single string or bytes field (or of all the
+    /// strings and bytes in a list) that `store.set` accepts. 256MiB by default.
+    static ref MAX_ENTITY_FIELD_BYTES: usize = std::env::var("GRAPH_MAX_ENTITY_FIELD_BYTES")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_MAX_ENTITY_FIELD_BYTES"))
+        .unwrap_or(256 * 1024 * 1024);
+
+    /// Maximum combined size in bytes of the string and bytes fields of an
+    /// entity passed to `store.set`. 1GiB by default.
+    static ref MAX_ENTITY_BYTES: usize = std::env::var("GRAPH_MAX_ENTITY_BYTES")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_MAX_ENTITY_BYTES"))
+        .unwrap_or(1024 * 1024 * 1024);
+
+    /// Maximum size, roughly in bytes, of the values that `ethereum.decode`
+    /// produces. 64MiB by default.
+    static ref MAX_ETHEREUM_DECODE_BYTES: usize = std::env::var("GRAPH_MAX_ETHEREUM_DECODE_BYTES")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_MAX_ETHEREUM_DECODE_BYTES"))
+        .unwrap_or(64 * 1024 * 1024);
+
+    /// Maximum number of data sources that handlers may create while
+    /// processing a single block. 100,000 by default.
+    static ref MAX_DATA_SOURCES_PER_BLOCK: usize = std::env::var("GRAPH_MAX_DATA_SOURCES_PER_BLOCK")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_MAX_DATA_SOURCES_PER_BLOCK"))
+        .unwrap_or(100_000);
+
+    /// Whether `dataSource.create` skips data sources that were already
+    /// created with the same template name and params in the same block.
+    static ref DEDUPLICATE_DATA_SOURCES: bool =
+        std::env::var("GRAPH_DEDUPLICATE_DATA_SOURCES").is_ok();
+
+    /// Number of consecutive `ethereum.call`s that may fail because of the
+    /// Ethereum node before the failure is no longer treated as a possible
+    /// reorg. 10 by default.
+    static ref MAX_TRANSIENT_CALL_FAILURES: usize = std::env::var("GRAPH_ETH_CALL_MAX_TRANSIENT_FAILURES")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_ETH_CALL_MAX_TRANSIENT_FAILURES"))
+        .unwrap_or(10);
+
+    /// Number of `ipfs.map` callbacks that may run in parallel, at most
+    /// `MAX_IPFS_MAP_CONCURRENCY`. 1 by default.
+    static ref IPFS_MAP_CONCURRENCY: usize = std::env::var("GRAPH_IPFS_MAP_CONCURRENCY")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_IPFS_MAP_CONCURRENCY"))
+        .map(|n| {
+            assert!(
+                n <= MAX_IPFS_MAP_CONCURRENCY,
+                "GRAPH_IPFS_MAP_CONCURRENCY must be at most {}",
+                MAX_IPFS_MAP_CONCURRENCY
+            );
+            n
+        })
+        .unwrap_or(1);
+
+    /// Number of characters of the string that the warning about invalid
+    /// UTF-8 in `typeConversion.bytesToString` includes. 1024 by default.
+    static ref BYTES_TO_STRING_WARNING_CHARS: usize = std::env::var("GRAPH_BYTES_TO_STRING_WARNING_CHARS")
+        .ok()
+        .map(|s| usize::from_str(&s).expect("Invalid value for GRAPH_BYTES_TO_STRING_WARNING_CHARS"))
+        .unwrap_or(1024);
+
+    /// If set, `store.set` sets the field with this name to the timestamp of
+    /// the current block on every entity whose type has such a field. Not
+    /// set by default.
+    static ref STORE_SET_TIMESTAMP_FIELD: Option<String> =
+        std::env::var("GRAPH_STORE_SET_TIMESTAMP_FIELD").ok();
+}
+
+/// Upper bound for `GRAPH_IPFS_MAP_CONCURRENCY`. Every callback that runs in
+/// parallel occupies a thread of the blocking pool and holds its own wasm
+/// instance.
+const MAX_IPFS_MAP_CONCURRENCY: usize = 16;
+
+/// RFC 4648 base32 alphabet in lowercase, as used by multibase.
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
 
 pub(crate) enum EthereumCallError {
     /// We might have detected a reorg.
     PossibleReorg(anyhow::Error),
+    /// The call was rejected for a reason that does not depend on the node.
+    Deterministic(anyhow::Error),
     Unknown(anyhow::Error),
 }
 
+/// What `store_get_with_status` found for an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum EntityStatus {
+    Present(Entity),
+    /// The entity was removed earlier in the current block.
+    DeletedThisBlock,
+    Absent,
+}
+
+/// Results of the `ethereum.call`s made while handling one trigger, keyed by
+/// contract address, call data, sender, gas limit and block.
+pub(crate) type EthereumCallMemo =
+    HashMap<(Address, Vec<u8>, Option<Address>, Option<u64>, BlockPtr), Option<Vec<Token>>>;
+
+/// Names found by the `ens.nameByHash` lookups made while handling one
+/// trigger, keyed by hash.
+pub(crate) type EnsNameMemo = HashMap<String, Option<String>>;
+
+/// The keccak256 hashers started with `crypto.keccak256Init` while handling
+/// one trigger. A handle is an index into this list; finalized hashers are
+/// `None`.
+pub(crate) type KeccakHashers = Vec<Option<tiny_keccak::Keccak>>;
+
+type ContractCallFuture<T> = Box<dyn Future<Item = T, Error = EthereumContractCallError> + Send>;
+
+/// Wraps the call cache handed to the Ethereum adapter to find out whether
+/// the adapter answered a call from the cache.
+struct CallCacheProbe {
+    cache: Arc<dyn EthereumCallCache>,
+    hit: AtomicBool,
+}
+
+impl EthereumCallCache for CallCacheProbe {
+    fn get_call(
+        &self,
+        contract_address: Address,
+        encoded_call: &[u8],
+        block: BlockPtr,
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let result = self.cache.get_call(contract_address, encoded_call, block);
+        if let Ok(Some(_)) = &result {
+            self.hit.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    fn set_call(
+        &self,
+        contract_address: Address,
+        encoded_call: &[u8],
+        block: BlockPtr,
+        return_value: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        self.cache
+            .set_call(contract_address, encoded_call, block, return_value)
+    }
+}
+
+/// How `big_decimal_to_big_int` rounds a value with a fractional part. The
+/// discriminants are the values mappings pass in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RoundingMode {
+    /// Towards zero.
+    Truncate = 0,
+    /// Towards negative infinity.
+    Floor = 1,
+    /// Towards positive infinity.
+    Ceil = 2,
+    /// To the nearest integer, and away from zero if both are equally near.
+    HalfUp = 3,
+}
+
+impl TryFrom<u32> for RoundingMode {
+    type Error = DeterministicHostError;
+
+    fn try_from(mode: u32) -> Result<Self, Self::Error> {
+        match mode {
+            0 => Ok(RoundingMode::Truncate),
+            1 => Ok(RoundingMode::Floor),
+            2 => Ok(RoundingMode::Ceil),
+            3 => Ok(RoundingMode::HalfUp),
+            _ => Err(DeterministicHostError(anyhow!(
+                "invalid rounding mode {}",
+                mode
+            ))),
+        }
+    }
+}
+
+/// Restricts the contracts that mappings may call with `ethereum.call`.
+#[derive(Clone, Debug)]
+pub(crate) enum ContractCallFilter {
+    AllowAll,
+    Allow(HashSet<Address>),
+    Deny(HashSet<Address>),
+}
+
+impl ContractCallFilter {
+    /// Reads comma-separated lists of addresses from `GRAPH_ETH_CALL_ALLOWLIST`
+    /// or `GRAPH_ETH_CALL_DENYLIST`. Allows all calls if neither is set.
+    fn from_env() -> Self {
+        fn addresses(var: &str) -> Option<HashSet<Address>> {
+            std::env::var(var).ok().map(|s| {
+                s.split(',')
+                    .map(|address| {
+                        string_to_h160(address.trim())
+                            .unwrap_or_else(|e| panic!("Invalid address in {}: {:#}", var, e.0))
+                    })
+                    .collect()
+            })
+        }
+
+        match (
+            addresses("GRAPH_ETH_CALL_ALLOWLIST"),
+            addresses("GRAPH_ETH_CALL_DENYLIST"),
+        ) {
+            (None, None) => ContractCallFilter::AllowAll,
+            (Some(allowed), None) => ContractCallFilter::Allow(allowed),
+            (None, Some(denied)) => ContractCallFilter::Deny(denied),
+            (Some(_), Some(_)) => {
+                panic!("GRAPH_ETH_CALL_ALLOWLIST and GRAPH_ETH_CALL_DENYLIST can not both be set")
+            }
+        }
+    }
+
+    pub(crate) fn allows(&self, address: &Address) -> bool {
+        match self {
+            ContractCallFilter::AllowAll => true,
+            ContractCallFilter::Allow(allowed) => allowed.contains(address),
+            ContractCallFilter::Deny(denied) => !denied.contains(address),
+        }
+    }
+}
+
 impl From<anyhow::Error> for EthereumCallError {
     fn from(e: anyhow::Error) -> Self {
         EthereumCallError::Unknown(e)
@@ -67,6 +304,161 @@ impl IntoTrap for HostExportError {
     }
 }
 
+/// Arweave transactions are immutable and addressed by their id, so their data
+/// can be cached across handlers and data sources.
+pub(crate) struct ArweaveCache {
+    cache: Mutex<LfuCache<String, ArweaveTxData>>,
+    max_weight: usize,
+}
+
+#[derive(Clone, Default)]
+struct ArweaveTxData(Bytes);
+
+impl CacheWeight for ArweaveTxData {
+    fn indirect_weight(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl ArweaveCache {
+    /// `max_weight` is the approximate size of the cache in bytes.
+    pub(crate) fn new(max_weight: usize) -> Self {
+        ArweaveCache {
+            cache: Mutex::new(LfuCache::new()),
+            max_weight,
+        }
+    }
+
+    fn get(&self, tx_id: &str) -> Option<Bytes> {
+        let mut cache = self.cache.lock().unwrap();
+        cache.get(&tx_id.to_owned()).map(|data| data.0.clone())
+    }
+
+    fn insert(&self, tx_id: String, data: Bytes) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(tx_id, ArweaveTxData(data));
+        cache.evict(self.max_weight);
+    }
+}
+
+/// Causality region for entity changes made while processing IPFS files.
+/// Files may be unavailable to some indexers, so their effects are kept
+/// apart from those of the chain in the proof of indexing.
+pub(crate) const IPFS_CAUSALITY_REGION: &str = "ipfs";
+
+/// Where the data that a mapping is currently processing comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DataOrigin {
+    /// A trigger from the data source's chain.
+    Chain,
+    /// A file read by `ipfs.map`.
+    Ipfs,
+}
+
+/// The kind of trigger whose handler is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TriggerKind {
+    Event,
+    Call,
+    Block,
+    /// An `ipfs.map` callback.
+    Ipfs,
+}
+
+impl TriggerKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            TriggerKind::Event => "event",
+            TriggerKind::Call => "call",
+            TriggerKind::Block => "block",
+            TriggerKind::Ipfs => "ipfs",
+        }
+    }
+}
+
+/// Everything needed to run an `ipfs.map` callback in a fresh instance.
+struct IpfsMapCallback<C: Blockchain> {
+    valid_module: Arc<ValidModule>,
+    ctx: MappingContext<C>,
+    host_metrics: Arc<HostMetrics>,
+    timeout: Option<Duration>,
+    experimental_features: ExperimentalFeatures,
+    callback: String,
+    user_data: store::Value,
+}
+
+impl<C: Blockchain> IpfsMapCallback<C> {
+    /// A callback with its own block state that can run in parallel with
+    /// this one. If there is a proof of indexing, the new callback records
+    /// its events rather than writing them, see `call_all`.
+    fn derive(&self) -> Self {
+        let mut ctx = self.ctx.derive_with_empty_block_state();
+        ctx.proof_of_indexing = ctx
+            .proof_of_indexing
+            .as_ref()
+            .map(|_| Arc::new(AtomicRefCell::new(ProofOfIndexing::recording())));
+        IpfsMapCallback {
+            valid_module: self.valid_module.cheap_clone(),
+            ctx,
+            host_metrics: self.host_metrics.cheap_clone(),
+            timeout: self.timeout,
+            experimental_features: self.experimental_features,
+            callback: self.callback.clone(),
+            user_data: self.user_data.clone(),
+        }
+    }
+
+    fn call(&self, value: &serde_json::Value) -> Result<BlockState<C>, anyhow::Error> {
+        let module = WasmInstance::from_valid_module_with_ctx(
+            self.valid_module.cheap_clone(),
+            self.ctx.derive_with_empty_block_state(),
+            self.host_metrics.cheap_clone(),
+            self.timeout,
+            self.experimental_features,
+        )?;
+        module.instance_ctx_mut().data_origin = DataOrigin::Ipfs;
+        module.instance_ctx_mut().trigger_kind = Some(TriggerKind::Ipfs);
+        module.handle_json_callback(&self.callback, value, &self.user_data)
+    }
+
+    /// Calls the callback for each value, in parallel on the blocking thread
+    /// pool if there is more than one. `WasmInstance` is not `Send`, so every
+    /// task creates its own. The events that the callbacks record for the
+    /// proof of indexing are written to it in the order of the values, just
+    /// like if the callbacks had run one after the other.
+    fn call_all(&self, values: Vec<JsonStreamValue>) -> Result<Vec<BlockState<C>>, anyhow::Error> {
+        if values.len() == 1 {
+            return Ok(vec![self.call(&values[0].value)?]);
+        }
+
+        let handles: Vec<_> = values
+            .into_iter()
+            .map(|sv| {
+                let runner = self.derive();
+                let recorded = runner.ctx.proof_of_indexing.cheap_clone();
+                let handle = graph::spawn_blocking_allow_panic(move || runner.call(&sv.value));
+                (handle, recorded)
+            })
+            .collect();
+
+        // Waiting in spawn order keeps the states in the order of the values.
+        handles
+            .into_iter()
+            .map(|(handle, recorded)| {
+                let state = block_on03(handle)
+                    .unwrap_or_else(|_| Err(anyhow!("ipfs_map callback panicked")))?;
+                if let (Some(recorded), Some(proof_of_indexing)) =
+                    (recorded, self.ctx.proof_of_indexing.as_ref())
+                {
+                    let recorded = std::mem::take(&mut *recorded.borrow_mut());
+                    recorded.replay(&self.ctx.logger, &mut proof_of_indexing.borrow_mut());
+                }
+                Ok(state)
+            })
+            .collect()
+    }
+}
+
 pub(crate) struct HostExports<C: Blockchain> {
     pub(crate) subgraph_id: DeploymentHash,
     pub(crate) api_version: Version,
@@ -76,8 +468,8 @@ pub(crate) struct HostExports<C: Blockchain> {
     data_source_context: Arc<Option<DataSourceContext>>,
     /// Some data sources have indeterminism or different notions of time. These
     /// need to be each be stored separately to separate causality between them,
-    /// and merge the results later. Right now, this is just the ethereum
-    /// networks but will be expanded for ipfs and the availability chain.
+    /// and merge the results later. This is the region for changes caused by
+    /// the ethereum network; see `causality_region` for the others.
     causality_region: String,
     templates: Arc<Vec<C::DataSourceTemplate>>,
     abis: Vec<Arc<MappingABI>>,
@@ -86,7 +478,27 @@ pub(crate) struct HostExports<C: Blockchain> {
     call_cache: Arc<dyn EthereumCallCache>,
     store: Arc<dyn crate::RuntimeStore>,
     arweave_adapter: Arc<dyn ArweaveAdapter>,
+    arweave_cache: Arc<ArweaveCache>,
     three_box_adapter: Arc<dyn ThreeBoxAdapter>,
+    pub(crate) max_logs_per_handler: Option<usize>,
+    pub(crate) contract_call_filter: ContractCallFilter,
+    pub(crate) max_entity_field_bytes: usize,
+    pub(crate) max_entity_bytes: usize,
+    pub(crate) ipfs_map_concurrency: usize,
+    pub(crate) max_ethereum_decode_bytes: usize,
+    pub(crate) max_data_sources_per_block: usize,
+    pub(crate) deduplicate_data_sources: bool,
+    pub(crate) max_transient_call_failures: usize,
+    pub(crate) bytes_to_string_warning_chars: usize,
+    pub(crate) timestamp_field: Option<String>,
+    /// In read-only mode, `store.set` and `store.remove` are validated and
+    /// logged, but neither change the entity cache nor write to the proof
+    /// of indexing. Meant for test and preview runs of mappings.
+    pub(crate) read_only: bool,
+    /// Number of `ethereum.call`s in a row that failed with a possible reorg.
+    /// This outlives a single block so that it keeps counting while the
+    /// block is retried.
+    transient_call_failures: AtomicUsize,
 }
 
 // Not meant to be useful, only to allow deriving.
@@ -96,7 +508,150 @@ impl<C: Blockchain> std::fmt::Debug for HostExports<C> {
     }
 }
 
+/// Builds `HostExports` from named parts. The properties of the data source
+/// are taken from it directly; everything else must be set before calling
+/// `build`.
+pub(crate) struct HostExportsBuilder<C: Blockchain> {
+    subgraph_id: DeploymentHash,
+    api_version: Version,
+    data_source_name: String,
+    data_source_address: Option<Address>,
+    data_source_context: Arc<Option<DataSourceContext>>,
+    abis: Vec<Arc<MappingABI>>,
+    data_source_network: Option<String>,
+    templates: Option<Arc<Vec<C::DataSourceTemplate>>>,
+    ethereum_adapter: Option<Arc<dyn EthereumAdapterTrait>>,
+    link_resolver: Option<Arc<dyn LinkResolver>>,
+    store: Option<Arc<dyn crate::RuntimeStore>>,
+    call_cache: Option<Arc<dyn EthereumCallCache>>,
+    arweave_adapter: Option<Arc<dyn ArweaveAdapter>>,
+    arweave_cache: Option<Arc<ArweaveCache>>,
+    three_box_adapter: Option<Arc<dyn ThreeBoxAdapter>>,
+    read_only: bool,
+}
+
+impl<C: Blockchain> HostExportsBuilder<C> {
+    pub(crate) fn new(subgraph_id: DeploymentHash, data_source: &impl DataSource<C>) -> Self {
+        Self {
+            subgraph_id,
+            api_version: data_source.mapping().api_version.clone(),
+            data_source_name: data_source.name().to_owned(),
+            data_source_address: data_source.source().address.clone(),
+            data_source_context: data_source.context().cheap_clone(),
+            abis: data_source.mapping().abis.clone(),
+            data_source_network: None,
+            templates: None,
+            ethereum_adapter: None,
+            link_resolver: None,
+            store: None,
+            call_cache: None,
+            arweave_adapter: None,
+            arweave_cache: None,
+            three_box_adapter: None,
+            read_only: false,
+        }
+    }
+
+    pub(crate) fn data_source_network(mut self, data_source_network: String) -> Self {
+        self.data_source_network = Some(data_source_network);
+        self
+    }
+
+    pub(crate) fn templates(mut self, templates: Arc<Vec<C::DataSourceTemplate>>) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    pub(crate) fn ethereum_adapter(
+        mut self,
+        ethereum_adapter: Arc<dyn EthereumAdapterTrait>,
+    ) -> Self {
+        self.ethereum_adapter = Some(ethereum_adapter);
+        self
+    }
+
+    pub(crate) fn link_resolver(mut self, link_resolver: Arc<dyn LinkResolver>) -> Self {
+        self.link_resolver = Some(link_resolver);
+        self
+    }
+
+    pub(crate) fn store(mut self, store: Arc<dyn crate::RuntimeStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub(crate) fn call_cache(mut self, call_cache: Arc<dyn EthereumCallCache>) -> Self {
+        self.call_cache = Some(call_cache);
+        self
+    }
+
+    pub(crate) fn arweave_adapter(mut self, arweave_adapter: Arc<dyn ArweaveAdapter>) -> Self {
+        self.arweave_adapter = Some(arweave_adapter);
+        self
+    }
+
+    pub(crate) fn arweave_cache(mut self, arweave_cache: Arc<ArweaveCache>) -> Self {
+        self.arweave_cache = Some(arweave_cache);
+        self
+    }
+
+    pub(crate) fn three_box_adapter(mut self, three_box_adapter: Arc<dyn ThreeBoxAdapter>) -> Self {
+        self.three_box_adapter = Some(three_box_adapter);
+        self
+    }
+
+    /// Optional, `false` by default. See `HostExports::read_only`.
+    pub(crate) fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Fails if any of the required fields has not been set.
+    pub(crate) fn build(self) -> Result<HostExports<C>, anyhow::Error> {
+        fn required<T>(field: Option<T>, name: &str) -> Result<T, anyhow::Error> {
+            field.ok_or_else(|| anyhow!("HostExportsBuilder: `{}` was not set", name))
+        }
+
+        let data_source_network = required(self.data_source_network, "data_source_network")?;
+        let causality_region = format!("ethereum/{}", data_source_network);
+
+        Ok(HostExports {
+            subgraph_id: self.subgraph_id,
+            api_version: self.api_version,
+            data_source_name: self.data_source_name,
+            data_source_address: self.data_source_address,
+            data_source_network,
+            data_source_context: self.data_source_context,
+            causality_region,
+            templates: required(self.templates, "templates")?,
+            abis: self.abis,
+            ethereum_adapter: required(self.ethereum_adapter, "ethereum_adapter")?,
+            link_resolver: required(self.link_resolver, "link_resolver")?,
+            call_cache: required(self.call_cache, "call_cache")?,
+            store: required(self.store, "store")?,
+            arweave_adapter: required(self.arweave_adapter, "arweave_adapter")?,
+            arweave_cache: required(self.arweave_cache, "arweave_cache")?,
+            three_box_adapter: required(self.three_box_adapter, "three_box_adapter")?,
+            max_logs_per_handler: *MAX_LOGS_PER_HANDLER,
+            contract_call_filter: CONTRACT_CALL_FILTER.clone(),
+            max_entity_field_bytes: *MAX_ENTITY_FIELD_BYTES,
+            max_entity_bytes: *MAX_ENTITY_BYTES,
+            ipfs_map_concurrency: *IPFS_MAP_CONCURRENCY,
+            max_ethereum_decode_bytes: *MAX_ETHEREUM_DECODE_BYTES,
+            max_data_sources_per_block: *MAX_DATA_SOURCES_PER_BLOCK,
+            deduplicate_data_sources: *DEDUPLICATE_DATA_SOURCES,
+            max_transient_call_failures: *MAX_TRANSIENT_CALL_FAILURES,
+            bytes_to_string_warning_chars: *BYTES_TO_STRING_WARNING_CHARS,
+            timestamp_field: STORE_SET_TIMESTAMP_FIELD.clone(),
+            read_only: self.read_only,
+            transient_call_failures: AtomicUsize::new(0),
+        })
+    }
+}
+
 impl<C: Blockchain> HostExports<C> {
+    /// Positional constructor, equivalent to setting every field of a
+    /// `HostExportsBuilder`.
     pub(crate) fn new(
         subgraph_id: DeploymentHash,
         data_source: &impl DataSource<C>,
@@ -107,35 +662,33 @@ impl<C: Blockchain> HostExports<C> {
         store: Arc<dyn crate::RuntimeStore>,
         call_cache: Arc<dyn EthereumCallCache>,
         arweave_adapter: Arc<dyn ArweaveAdapter>,
+        arweave_cache: Arc<ArweaveCache>,
         three_box_adapter: Arc<dyn ThreeBoxAdapter>,
     ) -> Self {
-        let causality_region = format!("ethereum/{}", data_source_network);
-
-        Self {
-            subgraph_id,
-            api_version: data_source.mapping().api_version.clone(),
-            data_source_name: data_source.name().to_owned(),
-            data_source_address: data_source.source().address.clone(),
-            data_source_network,
-            data_source_context: data_source.context().cheap_clone(),
-            causality_region,
-            templates,
-            abis: data_source.mapping().abis.clone(),
-            ethereum_adapter,
-            link_resolver,
-            call_cache,
-            store,
-            arweave_adapter,
-            three_box_adapter,
-        }
+        HostExportsBuilder::new(subgraph_id, data_source)
+            .data_source_network(data_source_network)
+            .templates(templates)
+            .ethereum_adapter(ethereum_adapter)
+            .link_resolver(link_resolver)
+            .store(store)
+            .call_cache(call_cache)
+            .arweave_adapter(arweave_adapter)
+            .arweave_cache(arweave_cache)
+            .three_box_adapter(three_box_adapter)
+            .build()
+            .expect("all fields of `HostExportsBuilder` are set")
     }
 
+    /// `backtrace` names the wasm functions that were on the stack, innermost
+    /// first. It only depends on the code path that led to the abort, so the
+    /// message is the same whenever the trigger is processed.
     pub(crate) fn abort(
         &self,
         message: Option<String>,
         file_name: Option<String>,
         line_number: Option<u32>,
         column_number: Option<u32>,
+        backtrace: Vec<String>,
     ) -> Result<Never, DeterministicHostError> {
         let message = message
             .map(|message| format!("message: {}", message))
@@ -152,29 +705,90 @@ impl<C: Blockchain> HostExports<C> {
             ),
             _ => unreachable!(),
         };
+        let backtrace = match backtrace.is_empty() {
+            true => String::new(),
+            false => {
+                let frames: Vec<_> = backtrace
+                    .iter()
+                    .enumerate()
+                    .map(|(i, frame)| format!("  {}: {}", i, frame))
+                    .collect();
+                format!("\nwasm backtrace:\n{}", frames.join("\n"))
+            }
+        };
         Err(DeterministicHostError(anyhow::anyhow!(
-            "Mapping aborted at {}, with {}",
+            "Mapping aborted at {}, with {}{}",
             location,
-            message
+            message,
+            backtrace
         )))
     }
 
+    /// The causality region under which entity changes caused by data from
+    /// `data_origin` are recorded in the proof of indexing.
+    pub(crate) fn causality_region(&self, data_origin: DataOrigin) -> &str {
+        match data_origin {
+            DataOrigin::Chain => &self.causality_region,
+            DataOrigin::Ipfs => IPFS_CAUSALITY_REGION,
+        }
+    }
+
+    /// Records `data` under `name` in the proof of indexing without touching
+    /// the store, so that indexers can compare the results of mappings that
+    /// do not write them to entities.
+    pub(crate) fn proof_of_indexing_write(
+        &self,
+        logger: &Logger,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        name: String,
+        data: HashMap<String, Value>,
+    ) -> Result<(), DeterministicHostError> {
+        if let Some(proof_of_indexing) = proof_of_indexing {
+            let mut proof_of_indexing = proof_of_indexing.deref().borrow_mut();
+            proof_of_indexing.write(
+                logger,
+                self.causality_region(data_origin),
+                &ProofOfIndexingEvent::Custom {
+                    name: &name,
+                    data: &data,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the given fields on the entity. Fields that are not mentioned in
+    /// `data` keep their current value, so consecutive calls for the same
+    /// entity within a block merge rather than replace each other.
+    ///
+    /// Like the `id`, the `timestamp_field`, if configured and the entity
+    /// type has it, is set to `block_timestamp` automatically; passing a
+    /// different value for it is an error.
     pub(crate) fn store_set(
         &self,
         logger: &Logger,
         state: &mut BlockState<C>,
         proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
         entity_type: String,
         entity_id: String,
         mut data: HashMap<String, Value>,
+        block_timestamp: Option<&BigInt>,
         stopwatch: &StopwatchMetrics,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<(), HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+
         let poi_section = stopwatch.start_section("host_export_store_set__proof_of_indexing");
-        if let Some(proof_of_indexing) = proof_of_indexing {
+        if let Some(proof_of_indexing) = proof_of_indexing.as_ref().filter(|_| !self.read_only) {
             let mut proof_of_indexing = proof_of_indexing.deref().borrow_mut();
             proof_of_indexing.write(
                 logger,
-                &self.causality_region,
+                self.causality_region(data_origin),
                 &ProofOfIndexingEvent::SetEntity {
                     entity_type: &entity_type,
                     id: &entity_id,
@@ -185,30 +799,37 @@ impl<C: Blockchain> HostExports<C> {
         poi_section.end();
 
         let id_insert_section = stopwatch.start_section("host_export_store_set__insert_id");
-        // Automatically add an "id" value
-        match data.insert("id".to_string(), Value::String(entity_id.clone())) {
-            Some(ref v) if v != &Value::String(entity_id.clone()) => {
-                return Err(anyhow!(
-                    "Value of {} attribute 'id' conflicts with ID passed to `store.set()`: \
-                     {} != {}",
-                    entity_type,
-                    v,
-                    entity_id,
-                ));
-            }
-            _ => (),
-        }
-
+        self.add_implicit_fields(
+            &schema,
+            &entity_type,
+            &entity_id,
+            &mut data,
+            block_timestamp,
+        )?;
         id_insert_section.end();
         let validation_section = stopwatch.start_section("host_export_store_set__validation");
+        self.check_entity_size(&entity_type, &entity_id, &data)?;
         let key = EntityKey {
             subgraph_id: self.subgraph_id.clone(),
             entity_type: EntityType::new(entity_type),
             entity_id,
         };
         let entity = Entity::from(data);
-        let schema = self.store.input_schema(&self.subgraph_id)?;
         let is_valid = validate_entity(&schema.document, &key, &entity).is_ok();
+        let unique_fields: Vec<String> = unique_fields(&schema, key.entity_type.as_str())
+            .filter(|field| entity.get(field).is_some())
+            .collect();
+        if self.read_only {
+            if !is_valid {
+                let entity = self.entity_after_set(state, &key, entity)?;
+                validate_entity(&schema.document, &key, &entity)?;
+            }
+            debug!(logger, "Not setting entity in read-only mode";
+                   "entity_type" => key.entity_type.as_str(),
+                   "id" => &key.entity_id);
+            return Ok(());
+        }
+        state.record_entity_write(&key);
         state.entity_cache.set(key.clone(), entity);
 
         validation_section.end();
@@ -223,62 +844,626 @@ impl<C: Blockchain> HostExports<C> {
                 .expect("we just stored this entity");
             validate_entity(&schema.document, &key, &entity)?;
         }
+        if !unique_fields.is_empty() {
+            let unique_section = stopwatch.start_section("host_export_store_set__unique_fields");
+            self.check_unique_fields(state, &key, &unique_fields)?;
+            unique_section.end();
+        }
         Ok(())
     }
 
-    pub(crate) fn store_remove(
+    /// Fails if another entity of the same type has the same value as the
+    /// entity for `key` in one of `fields`. Every field needs a lookup that
+    /// can go to the store, which is why uniqueness is only checked for
+    /// fields that ask for it with `@unique` in the schema, and only when
+    /// they are set. Null values are never in conflict.
+    fn check_unique_fields(
         &self,
-        logger: &Logger,
         state: &mut BlockState<C>,
-        proof_of_indexing: &SharedProofOfIndexing,
-        entity_type: String,
-        entity_id: String,
+        key: &EntityKey,
+        fields: &[String],
     ) -> Result<(), HostExportError> {
-        if let Some(proof_of_indexing) = proof_of_indexing {
-            let mut proof_of_indexing = proof_of_indexing.deref().borrow_mut();
-            proof_of_indexing.write(
-                logger,
-                &self.causality_region,
-                &ProofOfIndexingEvent::RemoveEntity {
-                    entity_type: &entity_type,
-                    id: &entity_id,
-                },
-            );
+        let entity = state
+            .entity_cache
+            .get(key)
+            .map_err(|e| HostExportError::Unknown(e.into()))?
+            .expect("we just stored this entity");
+        for field in fields {
+            let value = match entity.get(field) {
+                None | Some(Value::Null) => continue,
+                Some(value) => value,
+            };
+            // Besides the entity itself, one more is enough to find a conflict
+            let others = state
+                .entity_cache
+                .find_by_field(&key.entity_type, field, value, 2)
+                .map_err(|e| HostExportError::Unknown(e.into()))?;
+            if let Some(other) = others
+                .iter()
+                .filter_map(|other| other.id().ok())
+                .find(|id| id != &key.entity_id)
+            {
+                return Err(HostExportError::Deterministic(anyhow!(
+                    "Value {} of {} field `{}` is not unique: entity `{}` has it, too",
+                    value,
+                    key.entity_type,
+                    field,
+                    other
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the fields to `data` that `store_set` fills in automatically: the
+    /// `id`, and the block timestamp if the entity has a field for it.
+    fn add_implicit_fields(
+        &self,
+        schema: &Schema,
+        entity_type: &str,
+        entity_id: &str,
+        data: &mut HashMap<String, Value>,
+        block_timestamp: Option<&BigInt>,
+    ) -> Result<(), HostExportError> {
+        // Automatically add an "id" value
+        match data.insert("id".to_string(), Value::String(entity_id.to_owned())) {
+            Some(ref v) if v != &Value::String(entity_id.to_owned()) => {
+                return Err(anyhow!(
+                    "Value of {} attribute 'id' conflicts with ID passed to `store.set()`: \
+                     {} != {}",
+                    entity_type,
+                    v,
+                    entity_id,
+                )
+                .into());
+            }
+            _ => (),
         }
-        let key = EntityKey {
-            subgraph_id: self.subgraph_id.clone(),
-            entity_type: EntityType::new(entity_type),
-            entity_id,
-        };
-        state.entity_cache.remove(key);
 
+        // Automatically add the block timestamp if the entity has a field for it
+        let timestamp_field = self.timestamp_field.as_ref().filter(|field| {
+            schema
+                .document
+                .get_object_type_definition(entity_type)
+                .and_then(|object_type| object_type.field(field))
+                .is_some()
+        });
+        if let (Some(field), Some(timestamp)) = (timestamp_field, block_timestamp) {
+            let timestamp = Value::BigInt(timestamp.clone());
+            match data.insert(field.clone(), timestamp.clone()) {
+                Some(ref v) if v != &timestamp => {
+                    return Err(anyhow!(
+                        "Value of {} attribute '{}' conflicts with the block timestamp: \
+                         {} != {}",
+                        entity_type,
+                        field,
+                        v,
+                        timestamp,
+                    )
+                    .into());
+                }
+                _ => (),
+            }
+        }
         Ok(())
     }
 
-    pub(crate) fn store_get(
+    /// The entity that `state` would hold for `key` after setting `update`
+    /// on it, without setting it.
+    fn entity_after_set(
         &self,
         state: &mut BlockState<C>,
+        key: &EntityKey,
+        update: Entity,
+    ) -> Result<Entity, HostExportError> {
+        let mut entity = state
+            .entity_cache
+            .get(key)
+            .map_err(|e| HostExportError::Unknown(e.into()))?
+            .unwrap_or_default();
+        entity.merge_remove_null_fields(update);
+        Ok(entity)
+    }
+
+    /// Like `store_set`, but returns the entity as it is stored afterwards,
+    /// with the `id` filled in and merged with any fields it already had.
+    pub(crate) fn store_set_returning(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
         entity_type: String,
         entity_id: String,
-    ) -> Result<Option<Entity>, anyhow::Error> {
-        let store_key = EntityKey {
+        data: HashMap<String, Value>,
+        block_timestamp: Option<&BigInt>,
+        stopwatch: &StopwatchMetrics,
+    ) -> Result<Entity, HostExportError> {
+        let key = EntityKey {
             subgraph_id: self.subgraph_id.clone(),
             entity_type: EntityType::new(entity_type.clone()),
             entity_id: entity_id.clone(),
         };
-
-        Ok(state.entity_cache.get(&store_key)?)
+        // In read-only mode, return the entity as it would have been stored
+        let update = if self.read_only {
+            Some(data.clone())
+        } else {
+            None
+        };
+        self.store_set(
+            logger,
+            state,
+            proof_of_indexing,
+            data_origin,
+            entity_type,
+            entity_id.clone(),
+            data,
+            block_timestamp,
+            stopwatch,
+        )?;
+        if let Some(mut update) = update {
+            update.insert("id".to_string(), Value::String(entity_id));
+            return self.entity_after_set(state, &key, Entity::from(update));
+        }
+        let entity = state
+            .entity_cache
+            .get(&key)
+            .map_err(|e| HostExportError::Unknown(e.into()))?
+            .expect("we just stored this entity");
+        Ok(entity)
     }
 
-    /// Returns `Ok(None)` if the call was reverted.
-    pub(crate) fn ethereum_call(
+    /// Adds `delta` to `field` of the entity, creating the entity if it
+    /// does not exist yet. The field must be of type `BigInt` or
+    /// `BigDecimal`, and counts as zero if it is not set. The updated entity
+    /// is written with `store_set`, so it is validated and added to the
+    /// proof of indexing like any other write.
+    pub(crate) fn store_increment(
         &self,
         logger: &Logger,
-        block_ptr: &BlockPtr,
-        unresolved_call: UnresolvedContractCall,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        entity_type: String,
+        entity_id: String,
+        field: String,
+        delta: BigInt,
+        block_timestamp: Option<&BigInt>,
+        stopwatch: &StopwatchMetrics,
+    ) -> Result<(), HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+        let field_type = schema
+            .document
+            .get_object_type_definition(&entity_type)
+            .and_then(|object_type| object_type.field(&field))
+            .map(|field| field.field_type.get_base_type().to_owned());
+
+        let mut data: HashMap<String, Value> = self
+            .store_get(state, entity_type.clone(), entity_id.clone())?
+            .map(|entity| entity.sorted().into_iter().collect())
+            .unwrap_or_default();
+        let value = match (field_type.as_deref(), data.remove(&field)) {
+            (Some("BigInt"), None) | (Some("BigInt"), Some(Value::Null)) => Value::BigInt(delta),
+            (Some("BigInt"), Some(Value::BigInt(x))) => Value::BigInt(x + delta),
+            (Some("BigDecimal"), None) | (Some("BigDecimal"), Some(Value::Null)) => {
+                Value::BigDecimal(BigDecimal::new(delta, 0))
+            }
+            (Some("BigDecimal"), Some(Value::BigDecimal(x))) => {
+                Value::BigDecimal(x + BigDecimal::new(delta, 0))
+            }
+            _ => {
+                return Err(HostExportError::Deterministic(anyhow!(
+                    "Can not increment field `{}` of entity {}[{}]: only fields of type \
+                     BigInt or BigDecimal can be incremented",
+                    field,
+                    entity_type,
+                    entity_id
+                )))
+            }
+        };
+        data.insert(field, value);
+        // The timestamp of an earlier block would conflict with this one
+        if let Some(timestamp_field) = &self.timestamp_field {
+            data.remove(timestamp_field);
+        }
+
+        self.store_set(
+            logger,
+            state,
+            proof_of_indexing,
+            data_origin,
+            entity_type,
+            entity_id,
+            data,
+            block_timestamp,
+            stopwatch,
+        )
+    }
+
+    /// Rejects entities with oversized string or bytes fields before they
+    /// reach the entity cache and, eventually, the database.
+    fn check_entity_size(
+        &self,
+        entity_type: &str,
+        entity_id: &str,
+        data: &HashMap<String, Value>,
+    ) -> Result<(), HostExportError> {
+        fn value_bytes(value: &Value) -> usize {
+            match value {
+                Value::String(s) => s.len(),
+                Value::Bytes(bytes) => bytes.as_slice().len(),
+                Value::List(values) => values.iter().map(value_bytes).sum(),
+                _ => 0,
+            }
+        }
+
+        let mut total = 0;
+        for (field, value) in data {
+            let size = value_bytes(value);
+            if size > self.max_entity_field_bytes {
+                return Err(HostExportError::Deterministic(anyhow!(
+                    "Field `{}` of entity {}[{}] is {} bytes long, which exceeds the limit \
+                     of {} bytes",
+                    field,
+                    entity_type,
+                    entity_id,
+                    size,
+                    self.max_entity_field_bytes
+                )));
+            }
+            total += size;
+        }
+        if total > self.max_entity_bytes {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "Entity {}[{}] is {} bytes long, which exceeds the limit of {} bytes",
+                entity_type,
+                entity_id,
+                total,
+                self.max_entity_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn store_remove(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        entity_type: String,
+        entity_id: String,
+    ) -> Result<(), HostExportError> {
+        if self.read_only {
+            debug!(logger, "Not removing entity in read-only mode";
+                   "entity_type" => &entity_type,
+                   "id" => &entity_id);
+            return Ok(());
+        }
+        if let Some(proof_of_indexing) = proof_of_indexing {
+            let mut proof_of_indexing = proof_of_indexing.deref().borrow_mut();
+            proof_of_indexing.write(
+                logger,
+                self.causality_region(data_origin),
+                &ProofOfIndexingEvent::RemoveEntity {
+                    entity_type: &entity_type,
+                    id: &entity_id,
+                },
+            );
+        }
+        let key = EntityKey {
+            subgraph_id: self.subgraph_id.clone(),
+            entity_type: EntityType::new(entity_type),
+            entity_id,
+        };
+        state.record_entity_write(&key);
+        state.entity_cache.remove(key);
+
+        Ok(())
+    }
+
+    /// Removes the entity together with its children, the entities that its
+    /// `@derivedFrom` fields refer to. A child is found when its field named
+    /// by the directive holds the id of the entity; children that refer to
+    /// it from a list field are not found. Only one level of children is
+    /// removed unless `recursive` is set. Each removal is written to the
+    /// proof of indexing like with `store_remove`, children before parents.
+    pub(crate) fn store_remove_cascade(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        entity_type: String,
+        entity_id: String,
+        recursive: bool,
+    ) -> Result<(), HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+        self.remove_with_children(
+            logger,
+            state,
+            proof_of_indexing,
+            data_origin,
+            &schema,
+            entity_type,
+            entity_id,
+            if recursive { usize::MAX } else { 1 },
+            &mut HashSet::new(),
+        )
+    }
+
+    /// Removes the entity and its children down to `depth` levels below it.
+    /// `removed` keeps entities that refer to each other from being visited
+    /// twice.
+    fn remove_with_children(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        schema: &Schema,
+        entity_type: String,
+        entity_id: String,
+        depth: usize,
+        removed: &mut HashSet<(String, String)>,
+    ) -> Result<(), HostExportError> {
+        if !removed.insert((entity_type.clone(), entity_id.clone())) {
+            return Ok(());
+        }
+
+        let mut children = Vec::new();
+        let derived_fields = schema
+            .document
+            .get_object_type_definition(&entity_type)
+            .filter(|_| depth > 0)
+            .into_iter()
+            .flat_map(|object_type| object_type.fields.iter())
+            .filter_map(|field| {
+                match field
+                    .find_directive("derivedFrom")
+                    .and_then(|directive| directive.argument("field"))
+                {
+                    Some(s::Value::String(child_field)) => Some((field, child_field)),
+                    _ => None,
+                }
+            });
+        for (field, child_field) in derived_fields {
+            // Children of an interface type can be of any type implementing it
+            let base_type = EntityType::new(field.field_type.get_base_type().to_owned());
+            let child_types = match schema.types_for_interface.get(&base_type) {
+                Some(object_types) => object_types
+                    .iter()
+                    .map(|object_type| EntityType::new(object_type.name.clone()))
+                    .collect(),
+                None => vec![base_type],
+            };
+            for child_type in child_types {
+                let entities = state
+                    .entity_cache
+                    .find_by_field(
+                        &child_type,
+                        child_field,
+                        &Value::String(entity_id.clone()),
+                        u32::MAX as usize,
+                    )
+                    .map_err(|e| HostExportError::Unknown(e.into()))?;
+                for entity in entities {
+                    children.push((child_type.as_str().to_owned(), entity.id()?));
+                }
+            }
+        }
+
+        for (child_type, child_id) in children {
+            self.remove_with_children(
+                logger,
+                state,
+                proof_of_indexing,
+                data_origin,
+                schema,
+                child_type,
+                child_id,
+                depth - 1,
+                removed,
+            )?;
+        }
+        self.store_remove(
+            logger,
+            state,
+            proof_of_indexing,
+            data_origin,
+            entity_type,
+            entity_id,
+        )
+    }
+
+    /// Removes the entity only if its `field` is equal to `expected`, where
+    /// a field that is not set counts as `Value::Null`. Returns whether the
+    /// entity was removed; only an actual removal is recorded in the proof
+    /// of indexing.
+    pub(crate) fn store_remove_if(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        entity_type: String,
+        entity_id: String,
+        field: String,
+        expected: Value,
+    ) -> Result<bool, HostExportError> {
+        let matches = match self.store_get(state, entity_type.clone(), entity_id.clone())? {
+            Some(entity) => entity.get(&field).unwrap_or(&Value::Null) == &expected,
+            None => false,
+        };
+        if matches {
+            self.store_remove(
+                logger,
+                state,
+                proof_of_indexing,
+                data_origin,
+                entity_type,
+                entity_id,
+            )?;
+        }
+        Ok(matches)
+    }
+
+    pub(crate) fn store_get(
+        &self,
+        state: &mut BlockState<C>,
+        entity_type: String,
+        entity_id: String,
+    ) -> Result<Option<Entity>, HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+
+        let store_key = EntityKey {
+            subgraph_id: self.subgraph_id.clone(),
+            entity_type: EntityType::new(entity_type.clone()),
+            entity_id: entity_id.clone(),
+        };
+
+        state
+            .entity_cache
+            .get(&store_key)
+            .map_err(|e| HostExportError::Unknown(e.into()))
+    }
+
+    /// Like `store_get`, but tells an entity that was removed earlier in the
+    /// current block apart from one that does not exist. See
+    /// `EntityCache::is_removed` for when an entity counts as removed.
+    pub(crate) fn store_get_with_status(
+        &self,
+        state: &mut BlockState<C>,
+        entity_type: String,
+        entity_id: String,
+    ) -> Result<EntityStatus, HostExportError> {
+        let key = EntityKey {
+            subgraph_id: self.subgraph_id.clone(),
+            entity_type: EntityType::new(entity_type.clone()),
+            entity_id: entity_id.clone(),
+        };
+        Ok(match self.store_get(state, entity_type, entity_id)? {
+            Some(entity) => EntityStatus::Present(entity),
+            None if state.entity_cache.is_removed(&key) => EntityStatus::DeletedThisBlock,
+            None => EntityStatus::Absent,
+        })
+    }
+
+    /// Looks up the entity whose `field` is `value`, for fields that identify
+    /// an entity just like its `id`, e.g. a transaction hash. The field must
+    /// be a scalar field that is stored on the entity, and at most one entity
+    /// may have `value` in it; finding more is a deterministic error.
+    pub(crate) fn store_get_by_field(
+        &self,
+        state: &mut BlockState<C>,
+        entity_type: String,
+        field: String,
+        value: Value,
+    ) -> Result<Option<Entity>, HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+        let field_def = schema
+            .document
+            .get_object_type_definition(&entity_type)
+            .and_then(|object_type| object_type.field(&field))
+            .ok_or_else(|| {
+                HostExportError::Deterministic(anyhow!(
+                    "Entity type '{}' has no field `{}`",
+                    entity_type,
+                    field
+                ))
+            })?;
+        if is_list_or_non_null_list_field(field_def)
+            || field_def.find_directive("derivedFrom").is_some()
+        {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "Can not look up {} entities by `{}`: only scalar fields that are not derived \
+                 can identify an entity",
+                entity_type,
+                field
+            )));
+        }
+
+        let mut entities = state
+            .entity_cache
+            .find_by_field(&EntityType::new(entity_type.clone()), &field, &value, 2)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        if entities.len() > 1 {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "Field `{}` does not identify a single {} entity: more than one has the value {}",
+                field,
+                entity_type,
+                value
+            )));
+        }
+        Ok(entities.pop())
+    }
+
+    /// Like `store_get`, but also returns a version of the entity. The version
+    /// grows whenever the entity is set or removed, so a mapping can tell
+    /// whether an entity changed since it last read it. It is made up of the
+    /// block number and the number of writes to the entity in that block, and
+    /// therefore also grows from one block to the next when the entity did
+    /// not change.
+    pub(crate) fn store_get_with_version(
+        &self,
+        state: &mut BlockState<C>,
+        block_number: BlockNumber,
+        entity_type: String,
+        entity_id: String,
+    ) -> Result<Option<(Entity, u64)>, HostExportError> {
+        let key = EntityKey {
+            subgraph_id: self.subgraph_id.clone(),
+            entity_type: EntityType::new(entity_type.clone()),
+            entity_id: entity_id.clone(),
+        };
+        let version = ((block_number as u64) << 32) | state.entity_writes(&key);
+        Ok(self
+            .store_get(state, entity_type, entity_id)?
+            .map(|entity| (entity, version)))
+    }
+
+    /// Returns `Ok(None)` if the call was reverted. Calls with state
+    /// overrides are written to the proof of indexing and are not memoized.
+    pub(crate) fn ethereum_call(
+        &self,
+        logger: &Logger,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        block_ptr: &BlockPtr,
+        unresolved_call: UnresolvedContractCall,
+        call_memo: &mut EthereumCallMemo,
+        host_metrics: &HostMetrics,
+        deadline: Option<Instant>,
     ) -> Result<Option<Vec<Token>>, EthereumCallError> {
         let start_time = Instant::now();
 
+        if !self
+            .contract_call_filter
+            .allows(&unresolved_call.contract_address)
+        {
+            return Err(EthereumCallError::Deterministic(anyhow::anyhow!(
+                "Calling contract \"{}\" at address {:?} is not allowed on this node",
+                unresolved_call.contract_name,
+                unresolved_call.contract_address,
+            )));
+        }
+
         // Obtain the path to the contract ABI
         let contract = self
             .abis
@@ -336,16 +1521,221 @@ impl<C: Blockchain> HostExports<C> {
             block_ptr: block_ptr.cheap_clone(),
             function: function.clone(),
             args: unresolved_call.function_args.clone(),
+            from: unresolved_call.from,
+            gas: unresolved_call.gas_limit,
+        };
+
+        // The overrides determine the result as much as the chain does
+        if let (Some(overrides), Some(proof_of_indexing)) =
+            (&unresolved_call.state_overrides, proof_of_indexing)
+        {
+            // Calls that can not be encoded fail in the adapter
+            if let Ok(call_data) = function.encode_input(&unresolved_call.function_args) {
+                let overrides = serde_json::to_string(overrides)
+                    .map_err(|e| EthereumCallError::Unknown(e.into()))?;
+                let mut proof_of_indexing = proof_of_indexing.deref().borrow_mut();
+                proof_of_indexing.write(
+                    logger,
+                    self.causality_region(data_origin),
+                    &ProofOfIndexingEvent::EthereumCallWithOverrides {
+                        address: &format!("{:?}", call.address),
+                        call_data: &::hex::encode(&call_data),
+                        overrides: &overrides,
+                    },
+                );
+            }
+        }
+
+        // Identical calls made by the same handler are answered from `call_memo`
+        // without going through the adapter and its call cache.
+        let memo_key = function
+            .encode_input(&unresolved_call.function_args)
+            .ok()
+            .filter(|_| unresolved_call.state_overrides.is_none())
+            .map(|call_data| {
+                (
+                    call.address,
+                    call_data,
+                    call.from,
+                    call.gas,
+                    block_ptr.cheap_clone(),
+                )
+            });
+        if let Some(tokens) = memo_key.as_ref().and_then(|key| call_memo.get(key)) {
+            host_metrics
+                .observe_eth_call_execution_time(start_time.elapsed().as_secs_f64(), "memo");
+            return Ok(tokens.clone());
+        }
+
+        let description = format!(
+            "function \"{}\" of contract \"{}\"",
+            unresolved_call.function_name, unresolved_call.contract_name
+        );
+        let logger1 = logger.clone();
+        let state_overrides = unresolved_call.state_overrides.clone();
+        let result = self.run_contract_call(
+            logger,
+            &description,
+            host_metrics,
+            start_time,
+            deadline,
+            move |eth_adapter, call_cache| match state_overrides {
+                Some(overrides) => {
+                    eth_adapter.contract_call_with_overrides(&logger1, call, overrides)
+                }
+                None => eth_adapter.contract_call(&logger1, call, call_cache),
+            },
+        );
+
+        if let (Some(key), Ok(tokens)) = (memo_key, &result) {
+            call_memo.insert(key, tokens.clone());
+        }
+
+        trace!(logger, "Contract call finished";
+              "address" => &unresolved_call.contract_address.to_string(),
+              "contract" => &unresolved_call.contract_name,
+              "function" => &unresolved_call.function_name,
+              "function_signature" => &unresolved_call.function_signature,
+              "time" => format!("{}ms", start_time.elapsed().as_millis()));
+
+        result
+    }
+
+    /// Calls the function with the 4-byte `selector` on the contract at
+    /// `address`, for functions that are not in any of the ABIs of the data
+    /// source, e.g. behind a proxy. The call data is the selector followed by
+    /// the encoded `args`, and the output is decoded as `return_types`, e.g.
+    /// `(uint256,address)`. Returns `Ok(None)` if the call was reverted.
+    pub(crate) fn ethereum_call_by_selector(
+        &self,
+        logger: &Logger,
+        block_ptr: &BlockPtr,
+        address: Address,
+        selector: Vec<u8>,
+        args: Vec<Token>,
+        return_types: String,
+        host_metrics: &HostMetrics,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<Token>>, EthereumCallError> {
+        let start_time = Instant::now();
+
+        if !self.contract_call_filter.allows(&address) {
+            return Err(EthereumCallError::Deterministic(anyhow::anyhow!(
+                "Calling contract at address {:?} is not allowed on this node",
+                address,
+            )));
+        }
+        if selector.len() != 4 {
+            return Err(EthereumCallError::Deterministic(anyhow::anyhow!(
+                "A function selector must be 4 bytes long, got {}",
+                selector.len()
+            )));
+        }
+        let return_types = match Reader::read(&return_types) {
+            Ok(ParamType::Tuple(types)) => types.iter().map(|t| ParamType::clone(t)).collect(),
+            Ok(param_type) => vec![param_type],
+            Err(e) => {
+                return Err(EthereumCallError::Deterministic(anyhow::anyhow!(
+                    "Failed to read return types `{}`: {}",
+                    return_types,
+                    e
+                )))
+            }
         };
 
+        let mut call_data = selector.clone();
+        call_data.extend(encode(&args));
+        let call = EthereumRawContractCall {
+            address,
+            block_ptr: block_ptr.cheap_clone(),
+            call_data,
+            from: None,
+            gas: None,
+        };
+
+        let description = format!(
+            "selector 0x{} of contract {:?}",
+            ::hex::encode(&selector),
+            address
+        );
+        let logger1 = logger.clone();
+        let result = self.run_contract_call(
+            logger,
+            &description,
+            host_metrics,
+            start_time,
+            deadline,
+            move |eth_adapter, call_cache| {
+                let tokens: ContractCallFuture<Vec<Token>> = Box::new(
+                    eth_adapter
+                        .raw_contract_call(&logger1, call, call_cache)
+                        .and_then(move |output| {
+                            // Like for calls from an ABI, output that can not be
+                            // decoded counts as a revert
+                            decode(&return_types, &output).map_err(|e| {
+                                EthereumContractCallError::Revert(format!(
+                                    "failed to decode output: {}",
+                                    e
+                                ))
+                            })
+                        }),
+                );
+                tokens
+            },
+        );
+
+        trace!(logger, "Contract call by selector finished";
+              "address" => &address.to_string(),
+              "selector" => ::hex::encode(&selector),
+              "time" => format!("{}ms", start_time.elapsed().as_millis()));
+
+        result
+    }
+
+    /// Runs the contract call that `call` makes with the adapter and call
+    /// cache it is given, and turns its outcome into the result of a host
+    /// export. Reverts become `Ok(None)`; errors from the node are treated
+    /// as possible reorgs. `description` names what was called in errors.
+    fn run_contract_call<T, F>(
+        &self,
+        logger: &Logger,
+        description: &str,
+        host_metrics: &HostMetrics,
+        start_time: Instant,
+        deadline: Option<Instant>,
+        call: F,
+    ) -> Result<Option<T>, EthereumCallError>
+    where
+        T: Send + 'static,
+        F: FnOnce(
+                Arc<dyn EthereumAdapterTrait>,
+                Arc<dyn EthereumCallCache>,
+            ) -> ContractCallFuture<T>
+            + Send,
+    {
         // Run Ethereum call in tokio runtime
         let eth_adapter = self.ethereum_adapter.clone();
-        let logger1 = logger.clone();
-        let call_cache = self.call_cache.clone();
-        let result = match block_on(future::lazy(move || {
-            eth_adapter.contract_call(&logger1, call, call_cache)
-        })) {
-            Ok(tokens) => Ok(Some(tokens)),
+        let probe = Arc::new(CallCacheProbe {
+            cache: self.call_cache.clone(),
+            hit: AtomicBool::new(false),
+        });
+        let call_cache: Arc<dyn EthereumCallCache> = probe.clone();
+        let call_result = match block_on_until(
+            future::lazy(move || call(eth_adapter, call_cache)),
+            deadline,
+        ) {
+            Ok(call_result) => call_result,
+
+            // The handler ran out of time while waiting for the node. That is
+            // neither deterministic nor a sign of a reorg.
+            Err(e) => {
+                return Err(EthereumCallError::Unknown(
+                    anyhow::Error::from(e).context(format!("Failed to call {}", description)),
+                ))
+            }
+        };
+        let result = match call_result {
+            Ok(output) => Ok(Some(output)),
             Err(EthereumContractCallError::Revert(reason)) => {
                 info!(logger, "Contract call reverted"; "reason" => reason);
                 Ok(None)
@@ -354,36 +1744,56 @@ impl<C: Blockchain> HostExports<C> {
             // Any error reported by the Ethereum node could be due to the block no longer being on
             // the main chain. This is very unespecific but we don't want to risk failing a
             // subgraph due to a transient error such as a reorg.
-            Err(EthereumContractCallError::Web3Error(e)) => Err(EthereumCallError::PossibleReorg(anyhow::anyhow!(
-                "Ethereum node returned an error when calling function \"{}\" of contract \"{}\": {}",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
-                e
-            ))),
+            Err(EthereumContractCallError::Web3Error(e)) => {
+                Err(EthereumCallError::PossibleReorg(anyhow::anyhow!(
+                    "Ethereum node returned an error when calling {}: {}",
+                    description,
+                    e
+                )))
+            }
 
             // Also retry on timeouts.
-            Err(EthereumContractCallError::Timeout) => Err(EthereumCallError::PossibleReorg(anyhow::anyhow!(
-                "Ethereum node did not respond when calling function \"{}\" of contract \"{}\"",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
-            ))),
+            Err(EthereumContractCallError::Timeout) => Err(EthereumCallError::PossibleReorg(
+                anyhow::anyhow!("Ethereum node did not respond when calling {}", description),
+            )),
 
             Err(e) => Err(EthereumCallError::Unknown(anyhow::anyhow!(
-                "Failed to call function \"{}\" of contract \"{}\": {}",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
+                "Failed to call {}: {}",
+                description,
                 e
             ))),
         };
 
-        trace!(logger, "Contract call finished";
-              "address" => &unresolved_call.contract_address.to_string(),
-              "contract" => &unresolved_call.contract_name,
-              "function" => &unresolved_call.function_name,
-              "function_signature" => &unresolved_call.function_signature,
-              "time" => format!("{}ms", start_time.elapsed().as_millis()));
+        // Node errors are retried because they might be caused by a reorg,
+        // but a node that keeps failing is more likely broken or
+        // misconfigured, and retrying it forever would look like an endless
+        // series of reorgs
+        let result = match result {
+            Err(EthereumCallError::PossibleReorg(e)) => {
+                let failures = self.transient_call_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                if failures > self.max_transient_call_failures {
+                    Err(EthereumCallError::Unknown(e.context(format!(
+                        "Ethereum node failed {} calls in a row, not retrying as a possible reorg",
+                        failures
+                    ))))
+                } else {
+                    Err(EthereumCallError::PossibleReorg(e))
+                }
+            }
+            Ok(output) => {
+                self.transient_call_failures.store(0, Ordering::SeqCst);
+                Ok(output)
+            }
+            Err(e) => Err(e),
+        };
 
-        result.map_err(Into::into)
+        let source = match probe.hit.load(Ordering::SeqCst) {
+            true => "call_cache",
+            false => "node",
+        };
+        host_metrics.observe_eth_call_execution_time(start_time.elapsed().as_secs_f64(), source);
+
+        result
     }
 
     /// Prints the module of `n` in hex.
@@ -403,105 +1813,209 @@ impl<C: Blockchain> HostExports<C> {
         ))
     }
 
-    pub(crate) fn ipfs_cat(&self, logger: &Logger, link: String) -> Result<Vec<u8>, anyhow::Error> {
-        block_on03(self.link_resolver.cat(logger, &Link { link }))
+    /// The contents of the IPFS file `link`. If `gateway` is the URL of one
+    /// of the configured IPFS nodes, only that node is asked for the file;
+    /// an unknown gateway is ignored.
+    ///
+    /// Fails with `DeadlineExceeded` if the file has not been fetched by
+    /// `deadline`, which is when the calling handler times out.
+    pub(crate) fn ipfs_cat(
+        &self,
+        logger: &Logger,
+        link: String,
+        gateway: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        block_on03_until(
+            self.link_resolver
+                .cat_from_gateway(logger, &Link { link }, gateway),
+            deadline,
+        )?
     }
 
-    // Read the IPFS file `link`, split it into JSON objects, and invoke the
-    // exported function `callback` on each JSON object. The successful return
-    // value contains the block state produced by each callback invocation. Each
-    // invocation of `callback` happens in its own instance of a WASM module,
-    // which is identical to `module` when it was first started. The signature
-    // of the callback must be `callback(JSONValue, Value)`, and the `userData`
-    // parameter is passed to the callback without any changes
-    pub(crate) fn ipfs_map(
-        link_resolver: &Arc<dyn LinkResolver>,
-        module: &mut WasmInstanceContext<C>,
+    /// The entries of the IPFS directory behind `link`, as pairs of name and
+    /// CID.
+    pub(crate) fn ipfs_ls(
+        &self,
+        logger: &Logger,
         link: String,
-        callback: &str,
-        user_data: store::Value,
-        flags: Vec<String>,
-    ) -> Result<Vec<BlockState<C>>, anyhow::Error> {
-        const JSON_FLAG: &str = "json";
-        ensure!(
-            flags.contains(&JSON_FLAG.to_string()),
-            "Flags must contain 'json'"
-        );
+        deadline: Option<Instant>,
+    ) -> Result<Vec<(String, String)>, anyhow::Error> {
+        block_on03_until(self.link_resolver.ls(logger, &Link { link }), deadline)?
+    }
+
+    /// Like `ipfs_cat`, but meant for mappings that can not do without the
+    /// file. If the link resolver reports that the content does not exist,
+    /// that is a deterministic error: the content behind a CID never changes,
+    /// so every node comes to the same conclusion. Any other failure, e.g., a
+    /// timeout or an unreachable gateway, is transient and the block is
+    /// processed again later; that includes running into `deadline`.
+    pub(crate) fn ipfs_cat_required(
+        &self,
+        logger: &Logger,
+        link: String,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>, HostExportError> {
+        self.ipfs_cat(logger, link, None, deadline).map_err(|e| {
+            if e.chain().any(|cause| cause.is::<LinkNotFound>()) {
+                HostExportError::Deterministic(e)
+            } else {
+                HostExportError::Unknown(e)
+            }
+        })
+    }
+
+    /// Like `ipfs_cat`, but also returns the MIME type of the file if the
+    /// link resolver reports or detects one.
+    pub(crate) fn ipfs_cat_with_content_type(
+        &self,
+        logger: &Logger,
+        link: String,
+        deadline: Option<Instant>,
+    ) -> Result<(Vec<u8>, Option<String>), anyhow::Error> {
+        block_on03_until(
+            self.link_resolver
+                .cat_with_content_type(logger, &Link { link }),
+            deadline,
+        )?
+    }
+
+    /// Reads the IPFS file `link`, splits it into JSON values, and invokes the
+    /// exported function `callback` on each of them. The signature of the
+    /// callback must be `callback(JSONValue, Value)`, and `user_data` is
+    /// passed to it without any changes. Each invocation happens in its own
+    /// instance of the module, which is identical to `module` when it was
+    /// first started. The successful return value contains the block state
+    /// produced by each invocation.
+    ///
+    /// Up to `concurrency` callbacks run in parallel; the returned states are
+    /// in the order of the values in the file.
+    ///
+    /// To resume an interrupted job, values on lines before `start_line` are
+    /// skipped without invoking `callback` for them and produce no block
+    /// state. Lines count from 1, so a `start_line` of 0 or 1 skips nothing.
+    pub(crate) fn ipfs_map(
+        link_resolver: &Arc<dyn LinkResolver>,
+        module: &mut WasmInstanceContext<C>,
+        link: String,
+        callback: &str,
+        user_data: store::Value,
+        flags: Vec<String>,
+        concurrency: usize,
+        start_line: usize,
+    ) -> Result<Vec<BlockState<C>>, anyhow::Error> {
+        const JSON_FLAG: &str = "json";
+        ensure!(
+            flags.contains(&JSON_FLAG.to_string()),
+            "Flags must contain 'json'"
+        );
 
-        let host_metrics = module.host_metrics.clone();
-        let valid_module = module.valid_module.clone();
-        let ctx = module.ctx.derive_with_empty_block_state();
-        let callback = callback.to_owned();
+        let concurrency = concurrency.max(1).min(MAX_IPFS_MAP_CONCURRENCY);
+        let runner = IpfsMapCallback {
+            valid_module: module.valid_module.clone(),
+            ctx: module.ctx.derive_with_empty_block_state(),
+            host_metrics: module.host_metrics.clone(),
+            timeout: module.timeout,
+            experimental_features: module.experimental_features,
+            callback: callback.to_owned(),
+            user_data,
+        };
         // Create a base error message to avoid borrowing headaches
         let errmsg = format!(
             "ipfs_map: callback '{}' failed when processing file '{}'",
-            &*callback, &link
+            callback, &link
         );
 
         let start = Instant::now();
         let mut last_log = start;
-        let logger = ctx.logger.new(o!("ipfs_map" => link.clone()));
+        let logger = runner.ctx.logger.new(o!("ipfs_map" => link.clone()));
 
         let result = {
             let mut stream: JsonValueStream =
                 block_on03(link_resolver.json_stream(&logger, &Link { link }))?;
             let mut v = Vec::new();
-            while let Some(sv) = block_on03(stream.next()) {
-                let sv = sv?;
-                let module = WasmInstance::from_valid_module_with_ctx(
-                    valid_module.clone(),
-                    ctx.derive_with_empty_block_state(),
-                    host_metrics.clone(),
-                    module.timeout,
-                    module.experimental_features.clone(),
-                )?;
-                let result = module.handle_json_callback(&callback, &sv.value, &user_data)?;
-                // Log progress every 15s
-                if last_log.elapsed() > Duration::from_secs(15) {
-                    debug!(
-                        logger,
-                        "Processed {} lines in {}s so far",
-                        sv.line,
-                        start.elapsed().as_secs()
-                    );
-                    last_log = Instant::now();
+            let mut batch = Vec::with_capacity(concurrency);
+            loop {
+                let next = block_on03(stream.next()).transpose()?;
+                if next.as_ref().map_or(false, |sv| sv.line < start_line) {
+                    continue;
+                }
+                let done = next.is_none();
+                batch.extend(next);
+                if batch.len() == concurrency || (done && !batch.is_empty()) {
+                    let line = batch[batch.len() - 1].line;
+                    v.extend(runner.call_all(std::mem::take(&mut batch))?);
+                    // Log progress every 15s
+                    if last_log.elapsed() > Duration::from_secs(15) {
+                        debug!(
+                            logger,
+                            "Processed {} lines in {}s so far",
+                            line,
+                            start.elapsed().as_secs()
+                        );
+                        last_log = Instant::now();
+                    }
+                }
+                if done {
+                    break;
                 }
-                v.push(result)
             }
             Ok(v)
         };
         result.map_err(move |e: Error| anyhow::anyhow!("{}: {}", errmsg, e.to_string()))
     }
 
-    /// Expects a decimal string.
+    /// Expects a decimal string or a `0x`-prefixed hexadecimal string.
     pub(crate) fn json_to_i64(&self, json: String) -> Result<i64, DeterministicHostError> {
-        i64::from_str(&json)
+        parse_json_int(&json, i64::from_str_radix)
             .with_context(|| format!("JSON `{}` cannot be parsed as i64", json))
             .map_err(DeterministicHostError)
     }
 
-    /// Expects a decimal string.
+    /// Expects a decimal string or a `0x`-prefixed hexadecimal string.
     pub(crate) fn json_to_u64(&self, json: String) -> Result<u64, DeterministicHostError> {
-        u64::from_str(&json)
+        parse_json_int(&json, u64::from_str_radix)
             .with_context(|| format!("JSON `{}` cannot be parsed as u64", json))
             .map_err(DeterministicHostError)
     }
 
-    /// Expects a decimal string.
+    /// Expects a decimal string. Values that are not finite, like `NaN`,
+    /// `inf`, or `1e400`, which overflows, are rejected since they can not
+    /// be stored and break any arithmetic they are used in.
     pub(crate) fn json_to_f64(&self, json: String) -> Result<f64, DeterministicHostError> {
-        f64::from_str(&json)
+        let x = f64::from_str(&json)
             .with_context(|| format!("JSON `{}` cannot be parsed as f64", json))
-            .map_err(DeterministicHostError)
+            .map_err(DeterministicHostError)?;
+        if !x.is_finite() {
+            return Err(DeterministicHostError(anyhow!(
+                "JSON `{}` is not a finite f64",
+                json
+            )));
+        }
+        Ok(x)
     }
 
-    /// Expects a decimal string.
+    /// Expects a decimal string. Integers in scientific notation, like
+    /// `1e18`, are accepted as well, see `big_int_from_scientific`.
     pub(crate) fn json_to_big_int(&self, json: String) -> Result<Vec<u8>, DeterministicHostError> {
-        let big_int = BigInt::from_str(&json)
-            .with_context(|| format!("JSON `{}` is not a decimal string", json))
-            .map_err(DeterministicHostError)?;
+        let big_int = match BigInt::from_str(&json) {
+            Ok(big_int) => big_int,
+            Err(_) => big_int_from_scientific(&json)?,
+        };
         Ok(big_int.to_signed_bytes_le())
     }
 
+    /// Expects a decimal string. Unlike `json_to_f64`, this keeps every
+    /// digit that `BigDecimal` can represent.
+    pub(crate) fn json_to_big_decimal(
+        &self,
+        json: String,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        BigDecimal::from_str(&json)
+            .with_context(|| format!("JSON `{}` is not a decimal string", json))
+            .map_err(DeterministicHostError)
+    }
+
     pub(crate) fn crypto_keccak_256(
         &self,
         input: Vec<u8>,
@@ -509,6 +2023,260 @@ impl<C: Blockchain> HostExports<C> {
         Ok(tiny_keccak::keccak256(&input))
     }
 
+    /// Starts hashing input that is passed in chunks with `keccak_update`,
+    /// so that large inputs do not have to be held in memory all at once.
+    /// Returns the handle of the new hasher.
+    pub(crate) fn keccak_init(
+        &self,
+        hashers: &mut KeccakHashers,
+    ) -> Result<u32, DeterministicHostError> {
+        let handle = u32::try_from(hashers.len()).map_err(|e| DeterministicHostError(e.into()))?;
+        hashers.push(Some(tiny_keccak::Keccak::new_keccak256()));
+        Ok(handle)
+    }
+
+    pub(crate) fn keccak_update(
+        &self,
+        hashers: &mut KeccakHashers,
+        handle: u32,
+        chunk: Vec<u8>,
+    ) -> Result<(), DeterministicHostError> {
+        hashers
+            .get_mut(handle as usize)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| unknown_keccak_hasher(handle))?
+            .update(&chunk);
+        Ok(())
+    }
+
+    /// The keccak256 hash of all chunks passed to the hasher `handle`, which
+    /// can not be used afterwards.
+    pub(crate) fn keccak_finalize(
+        &self,
+        hashers: &mut KeccakHashers,
+        handle: u32,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        let hasher = hashers
+            .get_mut(handle as usize)
+            .and_then(Option::take)
+            .ok_or_else(|| unknown_keccak_hasher(handle))?;
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        Ok(hash)
+    }
+
+    /// Pseudo-random bytes for sampling that every indexer can reproduce.
+    /// They are NOT cryptographically secure: anyone who knows the block hash
+    /// can predict them, and miners can influence them.
+    ///
+    /// The bytes are the keccak256 hash of the block hash, the data source,
+    /// the `trigger_seed` and `counter`, so the n-th value a handler gets is
+    /// the same on every indexer and on every replay. The `trigger_seed`
+    /// tells the triggers of a block apart: it is the transaction hash and
+    /// log index for events, the transaction hash for calls, and `block` for
+    /// block handlers.
+    pub(crate) fn deterministic_random(
+        &self,
+        block_ptr: &BlockPtr,
+        trigger_seed: &[u8],
+        counter: u64,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        let mut input = block_ptr.hash_slice().to_vec();
+        input.extend_from_slice(&(self.data_source_name.len() as u64).to_be_bytes());
+        input.extend_from_slice(self.data_source_name.as_bytes());
+        if let Some(address) = self.data_source_address {
+            input.extend_from_slice(address.as_bytes());
+        }
+        input.extend_from_slice(&(trigger_seed.len() as u64).to_be_bytes());
+        input.extend_from_slice(trigger_seed);
+        input.extend_from_slice(&counter.to_be_bytes());
+        Ok(tiny_keccak::keccak256(&input))
+    }
+
+    /// A `BigInt` drawn uniformly from `[0, n)`. Its bits come from
+    /// `deterministic_random`, starting at `*counter` and advancing it past
+    /// every value used, so it shares one stream with `random.deterministic`
+    /// and is just as reproducible, and just as predictable. Candidates that
+    /// are not below `n` are drawn again rather than reduced modulo `n`,
+    /// which would favour small values.
+    pub(crate) fn big_int_random_below(
+        &self,
+        block_ptr: &BlockPtr,
+        trigger_seed: &[u8],
+        counter: &mut u64,
+        n: BigInt,
+    ) -> Result<BigInt, DeterministicHostError> {
+        if n <= BigInt::from(0) {
+            return Err(DeterministicHostError(anyhow!(
+                "the bound for a random BigInt must be positive, got {}",
+                n
+            )));
+        }
+        let bits = n.bits();
+        let len = (bits + 7) / 8;
+        loop {
+            let mut bytes = Vec::with_capacity(len + 32);
+            while bytes.len() < len {
+                bytes.extend_from_slice(&self.deterministic_random(
+                    block_ptr,
+                    trigger_seed,
+                    *counter,
+                )?);
+                *counter += 1;
+            }
+            bytes.truncate(len);
+            // Clear the bits above the highest bit of `n`, so that at least
+            // half of the candidates are below it
+            if bits % 8 != 0 {
+                bytes[len - 1] &= (1u8 << (bits % 8)) - 1;
+            }
+            let candidate = BigInt::from_unsigned_bytes_le(&bytes);
+            if candidate < n {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    /// Hashes the UTF-8 bytes of `s`, like Solidity's `keccak256(bytes(s))`.
+    pub(crate) fn crypto_keccak_256_string(
+        &self,
+        s: String,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        Ok(tiny_keccak::keccak256(s.as_bytes()))
+    }
+
+    /// An entity id made from several `parts`, as the `0x`-prefixed hex of
+    /// the keccak256 hash of the parts, each prefixed with its length as 8
+    /// big-endian bytes. The length prefixes keep parts from running into
+    /// each other, so that `["ab", "c"]` and `["a", "bc"]` get different ids.
+    pub(crate) fn make_entity_id(
+        &self,
+        parts: Vec<Vec<u8>>,
+    ) -> Result<String, DeterministicHostError> {
+        let mut input = Vec::with_capacity(parts.iter().map(|part| 8 + part.len()).sum());
+        for part in parts {
+            input.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            input.extend(part);
+        }
+        Ok(format!(
+            "0x{}",
+            ::hex::encode(tiny_keccak::keccak256(&input))
+        ))
+    }
+
+    /// The first four bytes of the keccak256 hash of `signature`, which
+    /// callers must give in canonical form, e.g. `transfer(address,uint256)`
+    /// without spaces or parameter names.
+    pub(crate) fn ethereum_function_selector(
+        &self,
+        signature: String,
+    ) -> Result<[u8; 4], DeterministicHostError> {
+        let hash = tiny_keccak::keccak256(signature.as_bytes());
+        Ok([hash[0], hash[1], hash[2], hash[3]])
+    }
+
+    /// The canonical signature, e.g. `Transfer(address,address,uint256)`,
+    /// of the event in the ABIs of the data source whose topic0 is `topic0`,
+    /// or `None` if there is no such event. The ABIs are searched in manifest
+    /// order.
+    pub(crate) fn ethereum_event_by_topic0(
+        &self,
+        topic0: Vec<u8>,
+    ) -> Result<Option<String>, DeterministicHostError> {
+        if topic0.len() != 32 {
+            return Err(DeterministicHostError(anyhow!(
+                "topic0 must be 32 bytes long, but is {} bytes long",
+                topic0.len()
+            )));
+        }
+        Ok(self
+            .abis
+            .iter()
+            .flat_map(|abi| abi.contract.events())
+            .map(|event| {
+                let inputs: Vec<_> = event
+                    .inputs
+                    .iter()
+                    .map(|input| input.kind.to_string())
+                    .collect();
+                format!("{}({})", event.name, inputs.join(","))
+            })
+            .find(|signature| tiny_keccak::keccak256(signature.as_bytes())[..] == topic0[..]))
+    }
+
+    /// Checks that `leaf` is part of the Merkle tree with the given `root`.
+    /// Following OpenZeppelin's `MerkleProof`, each pair of nodes is sorted
+    /// before it is hashed, so that `proof` only needs the sibling hashes
+    /// and not on which side they are. All hashes are keccak256 and must be
+    /// 32 bytes long.
+    pub(crate) fn ethereum_verify_merkle_proof(
+        &self,
+        leaf: Vec<u8>,
+        proof: Vec<Vec<u8>>,
+        root: Vec<u8>,
+    ) -> Result<bool, DeterministicHostError> {
+        fn check_len(what: &str, hash: &[u8]) -> Result<(), DeterministicHostError> {
+            if hash.len() != 32 {
+                return Err(DeterministicHostError(anyhow!(
+                    "Merkle proof {} must be 32 bytes long, but is {} bytes long",
+                    what,
+                    hash.len()
+                )));
+            }
+            Ok(())
+        }
+
+        check_len("leaf", &leaf)?;
+        check_len("root", &root)?;
+        let mut hash = leaf;
+        for sibling in proof {
+            check_len("node", &sibling)?;
+            let pair = match hash <= sibling {
+                true => [hash, sibling].concat(),
+                false => [sibling, hash].concat(),
+            };
+            hash = tiny_keccak::keccak256(&pair).to_vec();
+        }
+        Ok(hash == root)
+    }
+
+    /// Checks an Ed25519 `signature` of `message` by `public_key`. An invalid
+    /// signature is reported as `false`; only malformed inputs are an error.
+    pub(crate) fn crypto_verify_ed25519(
+        &self,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        public_key: Vec<u8>,
+    ) -> Result<bool, DeterministicHostError> {
+        if public_key.len() != 32 {
+            return Err(DeterministicHostError(anyhow!(
+                "Ed25519 public key must be 32 bytes long, got {}",
+                public_key.len()
+            )));
+        }
+        if signature.len() != 64 {
+            return Err(DeterministicHostError(anyhow!(
+                "Ed25519 signature must be 64 bytes long, got {}",
+                signature.len()
+            )));
+        }
+
+        let public_key = UnparsedPublicKey::new(&ED25519, public_key);
+        Ok(public_key.verify(&message, &signature).is_ok())
+    }
+
+    /// HMAC-SHA256 of `message` with `key`, as specified in RFC 2104.
+    pub(crate) fn crypto_hmac_sha256(
+        &self,
+        key: Vec<u8>,
+        message: Vec<u8>,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &key);
+        let mut output = [0u8; 32];
+        output.copy_from_slice(hmac::sign(&key, &message).as_ref());
+        Ok(output)
+    }
+
     pub(crate) fn big_int_plus(
         &self,
         x: BigInt,
@@ -570,12 +2338,87 @@ impl<C: Blockchain> HostExports<C> {
         Ok(x.pow(exponent))
     }
 
+    /// `base ^ exponent mod modulus`, in `[0, modulus)`.
+    pub(crate) fn big_int_pow_mod(
+        &self,
+        base: BigInt,
+        exponent: BigInt,
+        modulus: BigInt,
+    ) -> Result<BigInt, DeterministicHostError> {
+        if exponent < 0.into() {
+            return Err(DeterministicHostError(anyhow!(
+                "BigInt exponent `{}` of modular exponentiation is negative",
+                exponent
+            )));
+        }
+        if modulus <= 0.into() {
+            return Err(DeterministicHostError(anyhow!(
+                "BigInt modulus `{}` of modular exponentiation is not positive",
+                modulus
+            )));
+        }
+        Ok(base.mod_pow(&exponent, &modulus))
+    }
+
     pub(crate) fn big_int_from_string(&self, s: String) -> Result<BigInt, DeterministicHostError> {
         BigInt::from_str(&s)
             .with_context(|| format!("string is not a BigInt: `{}`", s))
             .map_err(DeterministicHostError)
     }
 
+    /// Parses `bytes` as ASCII decimal digits, without a sign.
+    pub(crate) fn big_int_from_ascii_bytes(
+        &self,
+        bytes: Vec<u8>,
+    ) -> Result<BigInt, DeterministicHostError> {
+        if bytes.is_empty() {
+            return Err(DeterministicHostError(anyhow!(
+                "can not parse an empty byte array as a BigInt"
+            )));
+        }
+        if let Some(i) = bytes.iter().position(|b| !b.is_ascii_digit()) {
+            return Err(DeterministicHostError(anyhow!(
+                "byte {} is 0x{:02x}, which is not an ASCII decimal digit",
+                i,
+                bytes[i]
+            )));
+        }
+        // Only ASCII digits, so this is valid UTF-8 and a valid number
+        let s = std::str::from_utf8(&bytes).unwrap();
+        Ok(BigInt::from_str(s).unwrap())
+    }
+
+    /// Parses `s` as a number in base `radix`, which must be between 2 and 36.
+    pub(crate) fn big_int_from_string_radix(
+        &self,
+        s: String,
+        radix: u32,
+    ) -> Result<BigInt, DeterministicHostError> {
+        check_radix(radix)?;
+        BigInt::from_str_radix(&s, radix)
+            .with_context(|| format!("string is not a base {} BigInt: `{}`", radix, s))
+            .map_err(DeterministicHostError)
+    }
+
+    /// Formats `x` in base `radix`, which must be between 2 and 36. Digits
+    /// above 9 are lowercase letters.
+    pub(crate) fn big_int_to_string_radix(
+        &self,
+        x: BigInt,
+        radix: u32,
+    ) -> Result<String, DeterministicHostError> {
+        check_radix(radix)?;
+        Ok(x.to_str_radix(radix))
+    }
+
+    pub(crate) fn big_int_from_u64(&self, n: u64) -> Result<BigInt, DeterministicHostError> {
+        Ok(BigInt::from(n))
+    }
+
+    pub(crate) fn big_int_from_i64(&self, n: i64) -> Result<BigInt, DeterministicHostError> {
+        Ok(BigInt::from(n))
+    }
+
     pub(crate) fn big_int_bit_or(
         &self,
         x: BigInt,
@@ -608,11 +2451,237 @@ impl<C: Blockchain> HostExports<C> {
         Ok(x >> bits)
     }
 
+    /// The number of significant bits of the magnitude of `x`, so that the
+    /// result is the same for `x` and `-x`. This is `floor(log2(|x|)) + 1`
+    /// for `x != 0`, and `0` for `x == 0`.
+    pub(crate) fn big_int_bit_length(&self, x: BigInt) -> Result<u32, DeterministicHostError> {
+        u32::try_from(x.bits()).map_err(|e| DeterministicHostError(e.into()))
+    }
+
+    /// The number of bits that are set in the magnitude of `x`. Like for
+    /// `big_int_bit_length`, the result is the same for `x` and `-x`, rather
+    /// than counting the bits of a two's complement representation, which
+    /// has infinitely many set bits for negative numbers.
+    pub(crate) fn big_int_count_ones(&self, x: BigInt) -> Result<u32, DeterministicHostError> {
+        let (_, bytes) = x.to_bytes_be();
+        Ok(bytes.iter().map(|byte| byte.count_ones()).sum())
+    }
+
+    /// The nearest `f64` to `x`. This is lossy: integers above 2^53 can not
+    /// all be represented and are rounded, and values that do not fit into
+    /// an `f64` at all are an error.
+    pub(crate) fn big_int_to_f64(&self, x: BigInt) -> Result<f64, DeterministicHostError> {
+        x.to_f64().ok_or_else(|| {
+            DeterministicHostError(anyhow!("BigInt `{}` is too large for an f64", x))
+        })
+    }
+
+    /// The smaller of `x` and `y`, or `x` if they are equal.
+    pub(crate) fn big_int_min(
+        &self,
+        x: BigInt,
+        y: BigInt,
+    ) -> Result<BigInt, DeterministicHostError> {
+        Ok(if y < x { y } else { x })
+    }
+
+    /// The larger of `x` and `y`, or `x` if they are equal.
+    pub(crate) fn big_int_max(
+        &self,
+        x: BigInt,
+        y: BigInt,
+    ) -> Result<BigInt, DeterministicHostError> {
+        Ok(if y > x { y } else { x })
+    }
+
     /// Useful for IPFS hashes stored as bytes
     pub(crate) fn bytes_to_base58(&self, bytes: Vec<u8>) -> Result<String, DeterministicHostError> {
         Ok(::bs58::encode(&bytes).into_string())
     }
 
+    /// Decodes `bytes` as UTF-16, little endian if `little_endian` is set and
+    /// big endian otherwise. Unlike `bytes_to_string`, invalid input is an
+    /// error instead of being replaced.
+    pub(crate) fn bytes_to_string_utf16(
+        &self,
+        bytes: Vec<u8>,
+        little_endian: bool,
+    ) -> Result<String, DeterministicHostError> {
+        if bytes.len() % 2 != 0 {
+            return Err(DeterministicHostError(anyhow!(
+                "UTF-16 input must have an even number of bytes, got {}",
+                bytes.len()
+            )));
+        }
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if little_endian {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            })
+            .collect();
+        String::from_utf16(&units).map_err(|e| DeterministicHostError(e.into()))
+    }
+
+    /// Encodes `bytes` as RFC 4648 base32 with the lowercase alphabet and
+    /// without padding. This is the encoding multibase uses for the `b`
+    /// prefix, e.g. for CIDv1 strings, but the prefix itself is not added.
+    pub(crate) fn bytes_to_base32(&self, bytes: Vec<u8>) -> Result<String, DeterministicHostError> {
+        let mut result = String::with_capacity((bytes.len() * 8 + 4) / 5);
+        let mut buffer: u32 = 0;
+        let mut bits: u32 = 0;
+        for byte in bytes {
+            buffer = (buffer << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+            buffer &= (1 << bits) - 1;
+        }
+        if bits > 0 {
+            result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+        Ok(result)
+    }
+
+    /// Decodes a string produced by `bytes_to_base32`. Uppercase letters,
+    /// padding and non-zero trailing bits are all rejected so that every
+    /// byte string has exactly one valid encoding.
+    pub(crate) fn base32_to_bytes(&self, s: String) -> Result<Vec<u8>, DeterministicHostError> {
+        let mut result = Vec::with_capacity(s.len() * 5 / 8);
+        let mut buffer: u32 = 0;
+        let mut bits: u32 = 0;
+        for (i, c) in s.bytes().enumerate() {
+            let value = match c {
+                b'a'..=b'z' => c - b'a',
+                b'2'..=b'7' => c - b'2' + 26,
+                _ => {
+                    return Err(DeterministicHostError(anyhow!(
+                        "Invalid base32 character `{}` at position {} in `{}`",
+                        s[i..].chars().next().unwrap(),
+                        i,
+                        s
+                    )))
+                }
+            };
+            buffer = (buffer << 5) | value as u32;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                result.push((buffer >> bits) as u8);
+                buffer &= (1 << bits) - 1;
+            }
+        }
+        // Whatever is left over is padding of less than a byte. A whole
+        // character of padding means the length was invalid.
+        if bits >= 5 || buffer != 0 {
+            return Err(DeterministicHostError(anyhow!(
+                "Invalid base32 string `{}`: trailing bits do not form a byte",
+                s
+            )));
+        }
+        Ok(result)
+    }
+
+    /// Reverses the order of the bytes, e.g. to switch between little and
+    /// big endian encodings.
+    pub(crate) fn bytes_reverse(
+        &self,
+        mut bytes: Vec<u8>,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        bytes.reverse();
+        Ok(bytes)
+    }
+
+    /// Prepends zero bytes to `bytes` until it is `length` bytes long, e.g. to
+    /// turn an address into a `bytes32`.
+    pub(crate) fn bytes_pad_left(
+        &self,
+        bytes: Vec<u8>,
+        length: u32,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        let padding = padding_for(&bytes, length)?;
+        let mut result = vec![0; padding];
+        result.extend(bytes);
+        Ok(result)
+    }
+
+    /// Appends zero bytes to `bytes` until it is `length` bytes long.
+    pub(crate) fn bytes_pad_right(
+        &self,
+        mut bytes: Vec<u8>,
+        length: u32,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        let padding = padding_for(&bytes, length)?;
+        bytes.resize(bytes.len() + padding, 0);
+        Ok(bytes)
+    }
+
+    /// Splits `s` at every occurrence of `delimiter`. Consecutive delimiters,
+    /// or one at the start or end of `s`, produce empty parts, so splitting
+    /// `"a--b"` on `"-"` gives `["a", "", "b"]`. An empty delimiter is an
+    /// error.
+    pub(crate) fn string_split(
+        &self,
+        s: String,
+        delimiter: String,
+    ) -> Result<Vec<String>, DeterministicHostError> {
+        if delimiter.is_empty() {
+            return Err(DeterministicHostError(anyhow!(
+                "can not split a string on an empty delimiter"
+            )));
+        }
+        Ok(s.split(delimiter.as_str()).map(str::to_owned).collect())
+    }
+
+    /// Converts the ASCII letters `A` to `Z` in `s` to lowercase and leaves
+    /// every other character alone. Unicode case mappings change between
+    /// Unicode versions, and with them between Rust versions, so they are
+    /// deliberately not applied to keep the result the same on every node.
+    pub(crate) fn string_to_lower(&self, s: String) -> Result<String, DeterministicHostError> {
+        Ok(s.to_ascii_lowercase())
+    }
+
+    /// Like `string_to_lower`, but converts `a` to `z` to uppercase.
+    pub(crate) fn string_to_upper(&self, s: String) -> Result<String, DeterministicHostError> {
+        Ok(s.to_ascii_uppercase())
+    }
+
+    /// The bytes from index `start`, inclusive, up to index `end`, exclusive.
+    pub(crate) fn bytes_slice(
+        &self,
+        bytes: Vec<u8>,
+        start: u32,
+        end: u32,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        let (start, end) = (start as usize, end as usize);
+        if start > end || end > bytes.len() {
+            return Err(DeterministicHostError(anyhow!(
+                "slice {}..{} is out of range for {} bytes",
+                start,
+                end,
+                bytes.len()
+            )));
+        }
+        Ok(bytes[start..end].to_vec())
+    }
+
+    /// Whether `a` and `b` are equal. For bytes of the same length, this
+    /// takes the same time no matter where they differ, so it is safe to use
+    /// for comparing signatures and MACs; only the length can leak.
+    pub(crate) fn bytes_equal(
+        &self,
+        a: Vec<u8>,
+        b: Vec<u8>,
+    ) -> Result<bool, DeterministicHostError> {
+        Ok(verify_slices_are_equal(&a, &b).is_ok())
+    }
+
     pub(crate) fn big_decimal_plus(
         &self,
         x: BigDecimal,
@@ -652,31 +2721,202 @@ impl<C: Blockchain> HostExports<C> {
         Ok(x / y)
     }
 
-    pub(crate) fn big_decimal_equals(
+    /// Like `big_decimal_divided_by`, but rounds the quotient to `precision`
+    /// significant digits. `BigDecimal` values never have more than
+    /// `BigDecimal::MAX_SIGNFICANT_DIGITS` digits, which bounds `precision`.
+    pub(crate) fn big_decimal_divided_by_with_precision(
         &self,
         x: BigDecimal,
         y: BigDecimal,
-    ) -> Result<bool, DeterministicHostError> {
-        Ok(x == y)
+        precision: u32,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        if precision == 0 || precision > BigDecimal::MAX_SIGNFICANT_DIGITS as u32 {
+            return Err(DeterministicHostError(anyhow!(
+                "BigDecimal division precision must be between 1 and {}, got {}",
+                BigDecimal::MAX_SIGNFICANT_DIGITS,
+                precision
+            )));
+        }
+        if y == 0.into() {
+            return Err(DeterministicHostError(anyhow!(
+                "attempted to divide BigDecimal `{}` by zero",
+                x
+            )));
+        }
+        Ok(x.div_with_prec(y, precision as u64))
     }
 
-    pub(crate) fn big_decimal_to_string(
+    /// Converts `x` to a `BigInt`, rounding any fractional part according
+    /// to `mode`.
+    pub(crate) fn big_decimal_to_big_int(
         &self,
         x: BigDecimal,
-    ) -> Result<String, DeterministicHostError> {
-        Ok(x.to_string())
+        mode: RoundingMode,
+    ) -> Result<BigInt, DeterministicHostError> {
+        let (int, fraction) = x.split_fraction();
+        let zero = BigDecimal::zero();
+        let away_from_zero = match mode {
+            RoundingMode::Truncate => false,
+            RoundingMode::Floor => fraction < zero,
+            RoundingMode::Ceil => fraction > zero,
+            RoundingMode::HalfUp => fraction.abs() >= BigDecimal::new(5.into(), -1),
+        };
+        Ok(match (away_from_zero, fraction < zero) {
+            (false, _) => int,
+            (true, false) => int + BigInt::from(1),
+            (true, true) => int - BigInt::from(1),
+        })
     }
 
-    pub(crate) fn big_decimal_from_string(
-        &self,
-        s: String,
-    ) -> Result<BigDecimal, DeterministicHostError> {
-        BigDecimal::from_str(&s)
-            .with_context(|| format!("string  is not a BigDecimal: '{}'", s))
-            .map_err(DeterministicHostError)
+    /// The number of digits after the decimal point. `BigDecimal` values are
+    /// always normalized, so trailing zeros do not count: the scale of `1.230`
+    /// is 2, and integers like `5` or `500` have a scale of 0.
+    pub(crate) fn big_decimal_scale(&self, x: BigDecimal) -> Result<i64, DeterministicHostError> {
+        let (_, scale) = x.as_bigint_and_exponent();
+        Ok(scale.max(0))
     }
 
-    pub(crate) fn data_source_create(
+    /// The nearest `f64` to `x`. This is lossy: an `f64` has about 16
+    /// significant digits and most decimal fractions, like `0.1`, can not be
+    /// represented exactly. Values that do not fit into an `f64` are an error.
+    pub(crate) fn big_decimal_to_f64(&self, x: BigDecimal) -> Result<f64, DeterministicHostError> {
+        // Parsing the decimal representation rounds correctly no matter how
+        // many digits `x` has, which scaling the digits by a power of ten
+        // does not.
+        match f64::from_str(&x.to_string()) {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(DeterministicHostError(anyhow!(
+                "BigDecimal `{}` is too large for an f64",
+                x
+            ))),
+        }
+    }
+
+    /// Rounds `x` to `scale` digits after the decimal point, to the nearest
+    /// value and away from zero if both are equally near, like
+    /// `RoundingMode::HalfUp`. `BigDecimal` values are always normalized, so
+    /// the result has no trailing zeros: rescaling `1.5` to 4 digits gives a
+    /// value equal to `1.5000` whose `scale` is 1.
+    pub(crate) fn big_decimal_with_scale(
+        &self,
+        x: BigDecimal,
+        scale: i64,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        if scale < 0 || scale > -(BigDecimal::MIN_EXP as i64) {
+            return Err(DeterministicHostError(anyhow!(
+                "BigDecimal scale must be between 0 and {}, got {}",
+                -BigDecimal::MIN_EXP,
+                scale
+            )));
+        }
+        let shifted = x * BigDecimal::new(BigInt::from(1), scale);
+        let digits = self.big_decimal_to_big_int(shifted, RoundingMode::HalfUp)?;
+        Ok(BigDecimal::new(digits, -scale))
+    }
+
+    /// The square root of `x`, correct to the 34 significant digits that
+    /// `BigDecimal` values keep.
+    pub(crate) fn big_decimal_sqrt(
+        &self,
+        x: BigDecimal,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        let zero = BigDecimal::zero();
+        if x < zero {
+            return Err(DeterministicHostError(anyhow!(
+                "attempted to take the square root of negative BigDecimal `{}`",
+                x
+            )));
+        }
+        if x == zero {
+            return Ok(zero);
+        }
+
+        // With `x = digits * 10^-scale`, take the integer square root of
+        // `digits * 10^shift`, which has at least 40 digits since `digits`
+        // is at least 1, and shift the decimal point back by half of
+        // `shift + scale`, which must therefore be even.
+        let (digits, scale) = x.as_bigint_and_exponent();
+        let shift = if (80 + scale) % 2 == 0 { 80 } else { 81 };
+        let root = isqrt(BigInt::from(digits) * BigInt::from(10).pow(shift as u8));
+        Ok(BigDecimal::new(root, -(shift + scale) / 2))
+    }
+
+    /// `BigDecimal` has no negative zero, so the result for `0` is `0`.
+    pub(crate) fn big_decimal_abs(
+        &self,
+        x: BigDecimal,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        Ok(x.abs())
+    }
+
+    pub(crate) fn big_decimal_neg(
+        &self,
+        x: BigDecimal,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        Ok(-x)
+    }
+
+    /// The smaller of `x` and `y`, or `x` if they are equal.
+    pub(crate) fn big_decimal_min(
+        &self,
+        x: BigDecimal,
+        y: BigDecimal,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        Ok(if y < x { y } else { x })
+    }
+
+    /// The larger of `x` and `y`, or `x` if they are equal.
+    pub(crate) fn big_decimal_max(
+        &self,
+        x: BigDecimal,
+        y: BigDecimal,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        Ok(if y > x { y } else { x })
+    }
+
+    pub(crate) fn big_decimal_equals(
+        &self,
+        x: BigDecimal,
+        y: BigDecimal,
+    ) -> Result<bool, DeterministicHostError> {
+        Ok(x == y)
+    }
+
+    pub(crate) fn big_decimal_to_string(
+        &self,
+        x: BigDecimal,
+    ) -> Result<String, DeterministicHostError> {
+        Ok(x.to_string())
+    }
+
+    pub(crate) fn big_decimal_from_string(
+        &self,
+        s: String,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        BigDecimal::from_str(&s)
+            .with_context(|| format!("string  is not a BigDecimal: '{}'", s))
+            .map_err(DeterministicHostError)
+    }
+
+    /// Builds `digits * 10^exp` directly, e.g. a raw token amount and
+    /// `-decimals`, without going through a string.
+    pub(crate) fn big_decimal_from_big_int(
+        &self,
+        digits: BigInt,
+        exp: i32,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        if exp < BigDecimal::MIN_EXP || exp > BigDecimal::MAX_EXP {
+            return Err(DeterministicHostError(anyhow!(
+                "BigDecimal exponent {} is out of range [{}, {}]",
+                exp,
+                BigDecimal::MIN_EXP,
+                BigDecimal::MAX_EXP
+            )));
+        }
+        Ok(BigDecimal::new(digits, exp as i64))
+    }
+
+    pub(crate) fn data_source_create(
         &self,
         logger: &Logger,
         state: &mut BlockState<C>,
@@ -694,9 +2934,7 @@ impl<C: Blockchain> HostExports<C> {
 
         // Resolve the name into the right template
         let template = self
-            .templates
-            .iter()
-            .find(|template| template.name() == name)
+            .template(&name)
             .with_context(|| {
                 format!(
                     "Failed to create data source from name `{}`: \
@@ -713,6 +2951,32 @@ impl<C: Blockchain> HostExports<C> {
             })
             .map_err(DeterministicHostError)?
             .clone();
+        debug!(logger, "Resolved data source template"; "kind" => template.kind());
+
+        // Data sources are considered the same if they have the same
+        // template and params, regardless of their context
+        if self.deduplicate_data_sources && state.has_created_data_source(&name, &params) {
+            debug!(
+                logger,
+                "Skip creating data source that already exists";
+                "name" => &name,
+                "params" => params.join(",")
+            );
+            return Ok(());
+        }
+
+        // A template that keeps instantiating itself would otherwise create
+        // data sources without bound, since triggers of data sources created
+        // in a block are processed in that same block
+        if state.created_data_sources_count() >= self.max_data_sources_per_block {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "Failed to create data source from name `{}`: \
+                 {} data sources have already been created in this block, \
+                 which is the limit",
+                name,
+                self.max_data_sources_per_block
+            )));
+        }
 
         // Remember that we need to create this data source
         state.push_created_data_source(DataSourceTemplateInfo {
@@ -725,16 +2989,166 @@ impl<C: Blockchain> HostExports<C> {
         Ok(())
     }
 
-    pub(crate) fn ens_name_by_hash(&self, hash: &str) -> Result<Option<String>, anyhow::Error> {
-        Ok(self.store.find_ens_name(hash)?)
+    /// Like `data_source_create`, but also sets the entity `entity_type` with
+    /// `entity_id` to `data`, e.g., the entity that tracks the new data
+    /// source. The entity is validated against the schema before anything is
+    /// recorded, so that an invalid entity can not leave a data source behind
+    /// without it. If the data source is not created because it already
+    /// exists, the entity is not set either.
+    pub(crate) fn data_source_create_with_entity(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        data_origin: DataOrigin,
+        name: String,
+        params: Vec<String>,
+        context: Option<DataSourceContext>,
+        creation_block: BlockNumber,
+        entity_type: String,
+        entity_id: String,
+        data: HashMap<String, Value>,
+        block_timestamp: Option<&BigInt>,
+        stopwatch: &StopwatchMetrics,
+    ) -> Result<(), HostExportError> {
+        let schema = self
+            .store
+            .input_schema(&self.subgraph_id)
+            .map_err(|e| HostExportError::Unknown(e.into()))?;
+        check_entity_type(&schema, &entity_type)?;
+        let mut seed = data.clone();
+        self.add_implicit_fields(
+            &schema,
+            &entity_type,
+            &entity_id,
+            &mut seed,
+            block_timestamp,
+        )?;
+        self.check_entity_size(&entity_type, &entity_id, &seed)?;
+        let key = EntityKey {
+            subgraph_id: self.subgraph_id.clone(),
+            entity_type: EntityType::new(entity_type.clone()),
+            entity_id: entity_id.clone(),
+        };
+        let seed = self.entity_after_set(state, &key, Entity::from(seed))?;
+        validate_entity(&schema.document, &key, &seed)?;
+
+        let created_count = state.created_data_sources_count();
+        self.data_source_create(logger, state, name, params, context, creation_block)?;
+        if state.created_data_sources_count() == created_count {
+            debug!(logger, "Skip setting entity for data source that already exists";
+                   "entity_type" => &entity_type,
+                   "id" => &entity_id);
+            return Ok(());
+        }
+
+        self.store_set(
+            logger,
+            state,
+            proof_of_indexing,
+            data_origin,
+            entity_type,
+            entity_id,
+            data,
+            block_timestamp,
+            stopwatch,
+        )
+    }
+
+    /// One of `event`, `call`, `block` or `ipfs` for an `ipfs.map` callback.
+    /// `kind` is `None` outside of handlers, e.g., while the module is
+    /// initialized.
+    pub(crate) fn trigger_kind(
+        &self,
+        kind: Option<TriggerKind>,
+    ) -> Result<String, DeterministicHostError> {
+        kind.map(|kind| kind.as_str().to_owned()).ok_or_else(|| {
+            DeterministicHostError(anyhow!(
+                "`dataSource.triggerKind` can only be called from a handler"
+            ))
+        })
+    }
+
+    fn template(&self, name: &str) -> Option<&C::DataSourceTemplate> {
+        self.templates
+            .iter()
+            .find(|template| template.name() == name)
+    }
+
+    /// The kind of the template `name` as declared in the manifest, e.g.
+    /// `ethereum/contract`, so that mappings can tell what kind of data
+    /// source `data_source_create` would create from it.
+    pub(crate) fn data_source_template_kind(
+        &self,
+        name: String,
+    ) -> Result<String, DeterministicHostError> {
+        self.template(&name)
+            .map(|template| template.kind().to_string())
+            .ok_or_else(|| {
+                DeterministicHostError(anyhow!(
+                    "No template with name `{}` in data source `{}`",
+                    name,
+                    self.data_source_name
+                ))
+            })
+    }
+
+    /// The names that `data_source_create` accepts, in manifest order.
+    pub(crate) fn data_source_template_names(&self) -> Result<Vec<String>, DeterministicHostError> {
+        Ok(self
+            .templates
+            .iter()
+            .map(|template| template.name().to_string())
+            .collect())
+    }
+
+    /// Hashes that were already looked up by the same handler are answered
+    /// from `memo` without querying the store again.
+    pub(crate) fn ens_name_by_hash(
+        &self,
+        hash: &str,
+        memo: &mut EnsNameMemo,
+    ) -> Result<Option<String>, anyhow::Error> {
+        if let Some(name) = memo.get(hash) {
+            return Ok(name.clone());
+        }
+        let name = self.store.find_ens_name(hash)?;
+        memo.insert(hash.to_string(), name.clone());
+        Ok(name)
     }
 
+    /// `log_count` is the number of messages logged so far by the current
+    /// handler. Once it reaches `max_logs_per_handler`, further messages are
+    /// dropped. Critical messages are never dropped since they fail the handler.
     pub(crate) fn log_log(
         &self,
         logger: &Logger,
         level: slog::Level,
         msg: String,
+        log_count: &mut usize,
     ) -> Result<(), DeterministicHostError> {
+        if level == slog::Level::Critical {
+            self.log_record(logger, level, msg);
+            return Err(DeterministicHostError(anyhow!(
+                "Critical error logged in mapping"
+            )));
+        }
+
+        *log_count += 1;
+        match self.max_logs_per_handler {
+            Some(max_logs) if *log_count == max_logs + 1 => warn!(
+                logger,
+                "Handler reached the limit of {} log messages, dropping further messages",
+                max_logs;
+                "data_source" => &self.data_source_name
+            ),
+            Some(max_logs) if *log_count > max_logs => (),
+            _ => self.log_record(logger, level, msg),
+        }
+        Ok(())
+    }
+
+    fn log_record(&self, logger: &Logger, level: slog::Level, msg: String) {
         let rs = record_static!(level, self.data_source_name.as_str());
 
         logger.log(&slog::Record::new(
@@ -742,23 +3156,38 @@ impl<C: Blockchain> HostExports<C> {
             &format_args!("{}", msg),
             b!("data_source" => &self.data_source_name),
         ));
-
-        if level == slog::Level::Critical {
-            return Err(DeterministicHostError(anyhow!(
-                "Critical error logged in mapping"
-            )));
-        }
-        Ok(())
     }
 
     pub(crate) fn data_source_address(&self) -> H160 {
         self.data_source_address.clone().unwrap_or_default()
     }
 
+    /// Like `data_source_address`, but `None` for data sources without an
+    /// address instead of the zero address.
+    pub(crate) fn data_source_address_opt(&self) -> Result<Option<H160>, DeterministicHostError> {
+        Ok(self.data_source_address.clone())
+    }
+
     pub(crate) fn data_source_network(&self) -> String {
         self.data_source_network.clone()
     }
 
+    /// The deployment hash of the subgraph, e.g. to build ids that are
+    /// unique across all deployments that share mapping code.
+    pub(crate) fn subgraph_id(&self) -> Result<String, DeterministicHostError> {
+        Ok(self.subgraph_id.to_string())
+    }
+
+    /// The EVM chain id of the data source's network, e.g. `1` for `mainnet`.
+    pub(crate) fn data_source_chain_id(&self) -> Result<u64, DeterministicHostError> {
+        network_chain_id(&self.data_source_network).ok_or_else(|| {
+            DeterministicHostError(anyhow!(
+                "no chain id is known for network `{}`",
+                self.data_source_network
+            ))
+        })
+    }
+
     pub(crate) fn data_source_context(&self) -> Entity {
         self.data_source_context
             .as_ref()
@@ -766,8 +3195,105 @@ impl<C: Blockchain> HostExports<C> {
             .unwrap_or_default()
     }
 
+    /// The `field` of the data source context, or `None` if the field is not
+    /// set or is null.
+    fn data_source_context_field(&self, field: &str) -> Option<&Value> {
+        self.data_source_context
+            .as_ref()
+            .as_ref()
+            .and_then(|context| context.get(field))
+            .filter(|value| !matches!(value, Value::Null))
+    }
+
+    fn data_source_context_type_error(
+        &self,
+        field: &str,
+        expected: &str,
+    ) -> DeterministicHostError {
+        DeterministicHostError(anyhow!(
+            "Field `{}` of the data source context is not of type {}",
+            field,
+            expected
+        ))
+    }
+
+    pub(crate) fn data_source_context_string(
+        &self,
+        field: &str,
+    ) -> Result<Option<String>, DeterministicHostError> {
+        match self.data_source_context_field(field) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(self.data_source_context_type_error(field, "String")),
+        }
+    }
+
+    pub(crate) fn data_source_context_bytes(
+        &self,
+        field: &str,
+    ) -> Result<Option<Vec<u8>>, DeterministicHostError> {
+        match self.data_source_context_field(field) {
+            None => Ok(None),
+            Some(Value::Bytes(bytes)) => Ok(Some(bytes.as_slice().to_vec())),
+            Some(_) => Err(self.data_source_context_type_error(field, "Bytes")),
+        }
+    }
+
+    pub(crate) fn data_source_context_big_int(
+        &self,
+        field: &str,
+    ) -> Result<Option<BigInt>, DeterministicHostError> {
+        match self.data_source_context_field(field) {
+            None => Ok(None),
+            Some(Value::BigInt(n)) => Ok(Some(n.clone())),
+            Some(_) => Err(self.data_source_context_type_error(field, "BigInt")),
+        }
+    }
+
+    /// The number and hash of the block that is currently being processed.
+    pub(crate) fn current_block_ptr(
+        &self,
+        block_ptr: &BlockPtr,
+    ) -> Result<(BlockNumber, H256), DeterministicHostError> {
+        Ok((block_ptr.number, block_ptr.hash_as_h256()))
+    }
+
+    /// A counter that starts at 0 for every block and goes up by one with
+    /// each call, e.g. to number entities in the order they were created
+    /// within the block. See `BlockState::next_sequence`.
+    pub(crate) fn next_sequence(
+        &self,
+        state: &mut BlockState<C>,
+    ) -> Result<u64, DeterministicHostError> {
+        Ok(state.next_sequence())
+    }
+
+    /// The timestamp of the block being handled, in seconds since the epoch.
+    /// Mappings must not use the wall clock, since every indexer would see a
+    /// different time; this is the time to use instead. `timestamp` is `None`
+    /// outside of handlers and in `ipfs.map` callbacks, which do not belong
+    /// to a block.
+    pub(crate) fn block_timestamp(
+        &self,
+        timestamp: Option<&BigInt>,
+    ) -> Result<BigInt, DeterministicHostError> {
+        timestamp.cloned().ok_or_else(|| {
+            DeterministicHostError(anyhow!(
+                "`block.timestamp` can only be called from a handler"
+            ))
+        })
+    }
+
+    /// The first fetch of a transaction still goes to the network, so this
+    /// remains non-deterministic. Later fetches are served from the cache.
     pub(crate) fn arweave_transaction_data(&self, tx_id: &str) -> Option<Bytes> {
-        block_on03(self.arweave_adapter.tx_data(tx_id)).ok()
+        if let Some(data) = self.arweave_cache.get(tx_id) {
+            return Some(data);
+        }
+
+        let data = block_on03(self.arweave_adapter.tx_data(tx_id)).ok()?;
+        self.arweave_cache.insert(tx_id.to_owned(), data.clone());
+        Some(data)
     }
 
     pub(crate) fn box_profile(
@@ -778,12 +3304,375 @@ impl<C: Blockchain> HostExports<C> {
     }
 }
 
+/// Maps the network names used in subgraph manifests to EVM chain ids.
+fn network_chain_id(network: &str) -> Option<u64> {
+    Some(match network {
+        "mainnet" => 1,
+        "ropsten" => 3,
+        "rinkeby" => 4,
+        "goerli" => 5,
+        "optimism" => 10,
+        "kovan" => 42,
+        "bsc" => 56,
+        "optimism-kovan" => 69,
+        "poa-sokol" => 77,
+        "chapel" => 97,
+        "poa-core" => 99,
+        "xdai" => 100,
+        "fuse" => 122,
+        "matic" => 137,
+        "fantom" => 250,
+        "moonriver" => 1285,
+        "mbase" => 1287,
+        "arbitrum-one" => 42161,
+        "celo" => 42220,
+        "fuji" => 43113,
+        "avalanche" => 43114,
+        "celo-alfajores" => 44787,
+        "mumbai" => 80001,
+        "arbitrum-rinkeby" => 421611,
+        "aurora" => 1313161554,
+        "aurora-testnet" => 1313161555,
+        _ => return None,
+    })
+}
+
 pub(crate) fn json_from_bytes(
     bytes: &Vec<u8>,
 ) -> Result<serde_json::Value, DeterministicHostError> {
     serde_json::from_reader(bytes.as_slice()).map_err(|e| DeterministicHostError(e.into()))
 }
 
+/// Looks up the value at a dotted `path` such as `data.items` in nested JSON
+/// objects. Returns `None` if a field is missing or if the path leads into
+/// something that is not an object.
+pub(crate) fn json_get_field(
+    value: &serde_json::Value,
+    path: &str,
+) -> Result<Option<serde_json::Value>, DeterministicHostError> {
+    let mut current = value;
+    for field in path.split('.') {
+        if field.is_empty() {
+            return Err(DeterministicHostError(anyhow!(
+                "invalid JSON field path `{}`",
+                path
+            )));
+        }
+        current = match current.as_object().and_then(|object| object.get(field)) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(current.clone()))
+}
+
+/// Merges `overlay` into `base`. Where both have an object, the two objects
+/// are merged field by field; otherwise the value from `overlay` wins. Arrays
+/// are replaced as a whole, not merged.
+pub(crate) fn json_merge(
+    base: &serde_json::Value,
+    overlay: &serde_json::Value,
+) -> Result<serde_json::Value, DeterministicHostError> {
+    use serde_json::Value;
+
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            let mut merged = base.clone();
+            for (key, value) in overlay {
+                let value = match base.get(key) {
+                    Some(base_value) => json_merge(base_value, value)?,
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Ok(Value::Object(merged))
+        }
+        (_, overlay) => Ok(overlay.clone()),
+    }
+}
+
+/// The name of the JSON type of `value`: one of `string`, `number`, `bool`,
+/// `array`, `object` or `null`.
+pub(crate) fn json_type_of(value: &serde_json::Value) -> Result<String, DeterministicHostError> {
+    use serde_json::Value;
+
+    let name = match value {
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "bool",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Null => "null",
+    };
+    Ok(name.to_string())
+}
+
+/// Serializes `value` without any whitespace, or indented if `pretty` is set.
+/// Object keys come out sorted and numbers keep the form they were parsed
+/// from; use `json_canonicalize` when logically equal values need to produce
+/// the same output.
+pub(crate) fn json_stringify(
+    value: &serde_json::Value,
+    pretty: bool,
+) -> Result<String, DeterministicHostError> {
+    let result = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    result.map_err(|e| DeterministicHostError(e.into()))
+}
+
+/// Serializes `value` in a canonical form modelled on RFC 8785: object keys are
+/// sorted by their UTF-16 code units, there is no insignificant whitespace and
+/// numbers are written in their shortest exact decimal form. Logically equal
+/// values therefore always produce the same bytes.
+pub(crate) fn json_canonicalize(
+    value: &serde_json::Value,
+) -> Result<Vec<u8>, DeterministicHostError> {
+    let mut out = String::new();
+    write_canonical_json(value, &mut out)?;
+    Ok(out.into_bytes())
+}
+
+fn write_canonical_json(
+    value: &serde_json::Value,
+    out: &mut String,
+) -> Result<(), DeterministicHostError> {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&canonical_json_number(&n.to_string())?),
+        Value::String(s) => write_canonical_json_string(s, out)?,
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(value, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json_string(key, out)?;
+                out.push(':');
+                write_canonical_json(value, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_canonical_json_string(s: &str, out: &mut String) -> Result<(), DeterministicHostError> {
+    // serde_json only escapes `"`, `\` and control characters, which is what
+    // RFC 8785 asks for.
+    out.push_str(&serde_json::to_string(s).map_err(|e| DeterministicHostError(e.into()))?);
+    Ok(())
+}
+
+/// Rewrites a JSON number literal such as `1.50E+2` into the shortest decimal
+/// representation of the same value (`150`), using the layout of ECMAScript's
+/// `Number.prototype.toString`. Unlike RFC 8785 the value is not rounded to an
+/// IEEE 754 double, so no precision is lost.
+fn canonical_json_number(number: &str) -> Result<String, DeterministicHostError> {
+    let out_of_range =
+        || DeterministicHostError(anyhow!("JSON number `{}` is out of range", number));
+
+    let (negative, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, number),
+    };
+    let (mantissa, mut exp) = match unsigned.find(|c| c == 'e' || c == 'E') {
+        Some(i) => (
+            &unsigned[..i],
+            i64::from_str(&unsigned[i + 1..]).map_err(|_| out_of_range())?,
+        ),
+        None => (unsigned, 0),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    exp = exp
+        .checked_sub(frac.len() as i64)
+        .ok_or_else(out_of_range)?;
+
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok("0".to_string());
+    }
+    let significant = digits.trim_end_matches('0');
+    exp = exp
+        .checked_add((digits.len() - significant.len()) as i64)
+        .ok_or_else(out_of_range)?;
+
+    // `k` significant digits with the decimal point after the first `n` of them.
+    let k = significant.len() as i64;
+    let n = k.checked_add(exp).ok_or_else(out_of_range)?;
+
+    let mut out = String::new();
+    if negative {
+        out.push('-');
+    }
+    if k <= n && n <= 21 {
+        out.push_str(significant);
+        out.extend(std::iter::repeat('0').take((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&significant[..n as usize]);
+        out.push('.');
+        out.push_str(&significant[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat('0').take(-n as usize));
+        out.push_str(significant);
+    } else {
+        out.push_str(&significant[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&significant[1..]);
+        }
+        out.push_str(&format!(
+            "e{}{}",
+            if n > 0 { "+" } else { "-" },
+            (n - 1).abs()
+        ));
+    }
+    Ok(out)
+}
+
+fn unknown_keccak_hasher(handle: u32) -> DeterministicHostError {
+    DeterministicHostError(anyhow!(
+        "No keccak256 hasher with handle {}, it was either never started or already finalized",
+        handle
+    ))
+}
+
+/// Parses integers in scientific notation, like `1e18` or `-2.5e3`, which some
+/// JSON serializers emit for large integers. Numbers with a fractional part,
+/// like `1.5e0`, are an error, and so are exponents above
+/// `BigDecimal::MAX_EXP`, which would make for absurdly large numbers.
+fn big_int_from_scientific(s: &str) -> Result<BigInt, DeterministicHostError> {
+    let not_decimal = || DeterministicHostError(anyhow!("JSON `{}` is not a decimal string", s));
+
+    let (mantissa, exp) = match s.find(|c| c == 'e' || c == 'E') {
+        Some(i) => (
+            &s[..i],
+            i64::from_str(&s[i + 1..]).map_err(|_| not_decimal())?,
+        ),
+        None => (s, 0),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    if (int.is_empty() && frac.is_empty())
+        || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(not_decimal());
+    }
+
+    let mut digits = format!("{}{}", int, frac);
+    if digits.chars().all(|c| c == '0') {
+        return Ok(BigInt::from(0));
+    }
+    let mut exp = exp.checked_sub(frac.len() as i64).ok_or_else(not_decimal)?;
+    // Trailing zeros only scale the value, e.g. `1.50e1` is `15`
+    while exp < 0 && digits.ends_with('0') {
+        digits.pop();
+        exp += 1;
+    }
+    if exp < 0 {
+        return Err(DeterministicHostError(anyhow!(
+            "JSON `{}` is not an integer",
+            s
+        )));
+    }
+    if exp > BigDecimal::MAX_EXP as i64 {
+        return Err(DeterministicHostError(anyhow!(
+            "JSON `{}` has an exponent larger than {}",
+            s,
+            BigDecimal::MAX_EXP
+        )));
+    }
+    digits.extend(std::iter::repeat('0').take(exp as usize));
+    BigInt::from_str(&format!("{}{}", sign, digits)).map_err(|_| not_decimal())
+}
+
+/// The fields of `entity_type` that are marked with `@unique`.
+fn unique_fields<'a>(schema: &'a Schema, entity_type: &str) -> impl Iterator<Item = String> + 'a {
+    schema
+        .document
+        .get_object_type_definition(entity_type)
+        .into_iter()
+        .flat_map(|object_type| object_type.fields.iter())
+        .filter(|field| field.find_directive("unique").is_some())
+        .map(|field| field.name.clone())
+}
+
+/// Unknown entity types would otherwise only be reported by the store, with
+/// a much less helpful message.
+fn check_entity_type(schema: &Schema, entity_type: &str) -> Result<(), HostExportError> {
+    match schema.document.get_object_type_definition(entity_type) {
+        Some(_) => Ok(()),
+        None => Err(HostExportError::Deterministic(anyhow!(
+            "Entity type '{}' is not defined in the subgraph schema",
+            entity_type
+        ))),
+    }
+}
+
+/// Parses `json` as hexadecimal if it starts with `0x` and as decimal
+/// otherwise. A sign is only accepted in front of decimal digits.
+fn parse_json_int<T>(
+    json: &str,
+    from_str_radix: fn(&str, u32) -> Result<T, std::num::ParseIntError>,
+) -> Result<T, anyhow::Error> {
+    match json.strip_prefix("0x") {
+        Some(hex) if hex.starts_with(|c| c == '+' || c == '-') => {
+            Err(anyhow!("sign after the `0x` prefix"))
+        }
+        Some(hex) => Ok(from_str_radix(hex, 16)?),
+        None => Ok(from_str_radix(json, 10)?),
+    }
+}
+
+fn check_radix(radix: u32) -> Result<(), DeterministicHostError> {
+    if !(2..=36).contains(&radix) {
+        return Err(DeterministicHostError(anyhow!(
+            "radix must be between 2 and 36, got {}",
+            radix
+        )));
+    }
+    Ok(())
+}
+
+/// The number of bytes needed to pad `bytes` to `length`.
+fn padding_for(bytes: &[u8], length: u32) -> Result<usize, DeterministicHostError> {
+    (length as usize).checked_sub(bytes.len()).ok_or_else(|| {
+        DeterministicHostError(anyhow!(
+            "Can not pad {} bytes to a length of {}",
+            bytes.len(),
+            length
+        ))
+    })
+}
+
 pub(crate) fn string_to_h160(string: &str) -> Result<H160, DeterministicHostError> {
     // `H160::from_str` takes a hex string with no leading `0x`.
     let s = string.trim_start_matches("0x");
@@ -792,7 +3681,15 @@ pub(crate) fn string_to_h160(string: &str) -> Result<H160, DeterministicHostErro
         .map_err(DeterministicHostError)
 }
 
-pub(crate) fn bytes_to_string(logger: &Logger, bytes: Vec<u8>) -> String {
+/// Decodes `bytes` as UTF-8, replacing invalid sequences. If there are any,
+/// a warning with the first `warning_chars` characters of the string is
+/// logged. Trailing nulls are removed if `trim_nulls` is set.
+pub(crate) fn bytes_to_string(
+    logger: &Logger,
+    bytes: Vec<u8>,
+    warning_chars: usize,
+    trim_nulls: bool,
+) -> String {
     let s = String::from_utf8_lossy(&bytes);
 
     // If the string was re-allocated, that means it was not UTF8.
@@ -801,24 +3698,96 @@ pub(crate) fn bytes_to_string(logger: &Logger, bytes: Vec<u8>) -> String {
             logger,
             "Bytes contain invalid UTF8. This may be caused by attempting \
             to convert a value such as an address that cannot be parsed to a unicode string. \
-            You may want to use 'toHexString()' instead. String (truncated to 1024 chars): '{}'",
-            &s.chars().take(1024).collect::<String>(),
+            You may want to use 'toHexString()' instead. String (truncated to {} chars): '{}'",
+            warning_chars,
+            &s.chars().take(warning_chars).collect::<String>(),
         )
     }
 
     // The string may have been encoded in a fixed length buffer and padded with null
     // characters, so trim trailing nulls.
-    s.trim_end_matches('\u{0000}').to_string()
+    if trim_nulls {
+        s.trim_end_matches('\u{0000}').to_string()
+    } else {
+        s.into_owned()
+    }
 }
 
 pub(crate) fn ethereum_encode(token: Token) -> Result<Vec<u8>, anyhow::Error> {
     Ok(encode(&[token]))
 }
 
-pub(crate) fn ethereum_decode(types: String, data: Vec<u8>) -> Result<Token, anyhow::Error> {
+/// Encodes `tokens` as a dynamic array whose elements are of type
+/// `element_type`, e.g. `uint256` to encode a `uint256[]`. Every token must
+/// be of that type.
+pub(crate) fn ethereum_encode_array(
+    element_type: String,
+    tokens: Vec<Token>,
+) -> Result<Vec<u8>, DeterministicHostError> {
+    let param_type = Reader::read(&element_type).map_err(|e| {
+        DeterministicHostError(anyhow!(
+            "Failed to read array element type `{}`: {}",
+            element_type,
+            e
+        ))
+    })?;
+    if let Some((i, token)) = tokens
+        .iter()
+        .enumerate()
+        .find(|(_, token)| !token.type_check(&param_type))
+    {
+        return Err(DeterministicHostError(anyhow!(
+            "Array element {} is `{}`, which is not of type `{}`",
+            i,
+            token,
+            element_type
+        )));
+    }
+    Ok(encode(&[Token::Array(tokens)]))
+}
+
+/// The keccak256 hash of `tokens` encoded like Solidity's `abi.encode`, i.e.,
+/// with every value padded to 32 bytes rather than packed.
+pub(crate) fn ethereum_hash_encoded(
+    tokens: Vec<Token>,
+) -> Result<[u8; 32], DeterministicHostError> {
+    Ok(tiny_keccak::keccak256(&encode(&tokens)))
+}
+
+/// Decodes `data` as a single value of type `types`, which is the inverse of
+/// `ethereum_encode`. Structs are passed using tuple syntax, e.g.
+/// `(uint256,(address,bool))`, and decode to a nested `Token::Tuple`.
+///
+/// Fails without decoding if the result would be larger than `max_bytes`,
+/// since a few bytes of data can describe a huge array. Also fails for a
+/// `bytesN` value whose word has anything but zeros after the first `N`
+/// bytes, which `decode` would silently cut off.
+pub(crate) fn ethereum_decode(
+    types: String,
+    data: Vec<u8>,
+    max_bytes: usize,
+) -> Result<Token, anyhow::Error> {
     let param_types =
         Reader::read(&types).or_else(|e| Err(anyhow::anyhow!("Failed to read types: {}", e)))?;
 
+    let mut budget = max_bytes;
+    match decoded_size(&param_types, &data, 0, &mut budget) {
+        Err(DecodedSizeError::TooLarge) => {
+            return Err(anyhow::anyhow!(
+                "Failed to decode: the decoded value would be larger than {} bytes",
+                max_bytes
+            ))
+        }
+        Err(DecodedSizeError::FixedBytesPadding(len)) => {
+            return Err(anyhow::anyhow!(
+                "Failed to decode: a bytes{} value has more than {} bytes",
+                len,
+                len
+            ))
+        }
+        Err(DecodedSizeError::Malformed) | Ok(_) => {}
+    }
+
     decode(&[param_types], &data)
         // The `.pop().unwrap()` here is ok because we're always only passing one
         // `param_types` to `decode`, so the returned `Vec` has always size of one.
@@ -827,6 +3796,159 @@ pub(crate) fn ethereum_decode(types: String, data: Vec<u8>) -> Result<Token, any
         .context("Failed to decode")
 }
 
+/// Logs a warning if encoding `token`, which `ethereum_decode` produced from
+/// `data` and `types`, does not give back `data`. Decoding ignores bytes it
+/// does not need and accepts some values with the wrong type, so a mismatch
+/// points to `types` not describing `data`.
+pub(crate) fn check_ethereum_decode(logger: &Logger, types: &str, data: &[u8], token: &Token) {
+    if encode(&[token.clone()]) != data {
+        warn!(
+            logger,
+            "Value decoded as `{}` does not encode to the bytes it was decoded from, \
+             the types may be wrong",
+            types;
+            "data" => format!("0x{}", ::hex::encode(data))
+        );
+    }
+}
+
+/// The largest integer whose square is at most `n`, which must be positive.
+fn isqrt(n: BigInt) -> BigInt {
+    // Newton's method, starting from a power of two that is at least the
+    // root, decreases until it reaches the root. `n` has at most a few
+    // hundred bits for the callers here, so the shift fits into a `u8`.
+    let mut root = BigInt::from(1) << ((n.bits() + 1) / 2) as u8;
+    loop {
+        let next = (root.clone() + n.clone() / root.clone()) / BigInt::from(2);
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+enum DecodedSizeError {
+    /// `decode` will reject the data, so there is no need to look further.
+    Malformed,
+    TooLarge,
+    /// A `bytesN` value for this `N` is not padded with zeros.
+    FixedBytesPadding(usize),
+}
+
+/// Walks `data` the way `decode` does and takes the size of the values it
+/// would produce out of `budget`, failing once that runs out. Each value
+/// counts as 32 bytes, plus the length of bytes and strings. Returns the
+/// offset after the head of the value at `offset`. Checks the padding of
+/// `bytesN` values on the way, since `decode` does not.
+fn decoded_size(
+    param_type: &ParamType,
+    data: &[u8],
+    offset: usize,
+    budget: &mut usize,
+) -> Result<usize, DecodedSizeError> {
+    fn charge(budget: &mut usize, bytes: usize) -> Result<(), DecodedSizeError> {
+        *budget = budget
+            .checked_sub(bytes)
+            .ok_or(DecodedSizeError::TooLarge)?;
+        Ok(())
+    }
+
+    fn read_usize(data: &[u8], offset: usize) -> Result<usize, DecodedSizeError> {
+        let word = offset
+            .checked_add(32)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(DecodedSizeError::Malformed)?;
+        if word[..24].iter().any(|byte| *byte != 0) {
+            return Err(DecodedSizeError::Malformed);
+        }
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&word[24..]);
+        usize::try_from(u64::from_be_bytes(bytes)).map_err(|_| DecodedSizeError::Malformed)
+    }
+
+    fn is_dynamic(param_type: &ParamType) -> bool {
+        match param_type {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(param_type, _) => is_dynamic(param_type),
+            ParamType::Tuple(param_types) => param_types.iter().any(|t| is_dynamic(t)),
+            _ => false,
+        }
+    }
+
+    // Dynamic values are stored at the offset found in their head.
+    fn tail(data: &[u8], offset: usize) -> Result<&[u8], DecodedSizeError> {
+        let start = read_usize(data, offset)?;
+        data.get(start..).ok_or(DecodedSizeError::Malformed)
+    }
+
+    charge(budget, 32)?;
+    match param_type {
+        ParamType::Bytes | ParamType::String => {
+            let len = read_usize(tail(data, offset)?, 0)?;
+            charge(budget, len)?;
+            Ok(offset + 32)
+        }
+        ParamType::Array(param_type) => {
+            let tail = tail(data, offset)?;
+            let len = read_usize(tail, 0)?;
+            // Check the claimed length before looking at any element.
+            charge(budget, len.saturating_mul(32))?;
+            let elements = &tail[32..];
+            let mut element_offset = 0;
+            for _ in 0..len {
+                element_offset = decoded_size(param_type, elements, element_offset, budget)?;
+            }
+            Ok(offset + 32)
+        }
+        ParamType::FixedArray(element_type, len) if is_dynamic(param_type) => {
+            let tail = tail(data, offset)?;
+            let mut element_offset = 0;
+            for _ in 0..*len {
+                element_offset = decoded_size(element_type, tail, element_offset, budget)?;
+            }
+            Ok(offset + 32)
+        }
+        ParamType::FixedArray(element_type, len) => {
+            let mut offset = offset;
+            for _ in 0..*len {
+                offset = decoded_size(element_type, data, offset, budget)?;
+            }
+            Ok(offset)
+        }
+        ParamType::Tuple(param_types) if is_dynamic(param_type) => {
+            let tail = tail(data, offset)?;
+            let mut element_offset = 0;
+            for param_type in param_types {
+                element_offset = decoded_size(param_type, tail, element_offset, budget)?;
+            }
+            Ok(offset + 32)
+        }
+        ParamType::Tuple(param_types) => {
+            let mut offset = offset;
+            for param_type in param_types {
+                offset = decoded_size(param_type, data, offset, budget)?;
+            }
+            Ok(offset)
+        }
+        ParamType::FixedBytes(len) => {
+            let word = offset
+                .checked_add(32)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(DecodedSizeError::Malformed)?;
+            if word
+                .get(*len..)
+                .unwrap_or(&[])
+                .iter()
+                .any(|byte| *byte != 0)
+            {
+                return Err(DecodedSizeError::FixedBytesPadding(*len));
+            }
+            Ok(offset + 32)
+        }
+        _ => Ok(offset + 32),
+    }
+}
+
 #[test]
 fn test_string_to_h160_with_0x() {
     assert_eq!(
@@ -843,13 +3965,57 @@ fn block_on03<T>(future: impl futures03::Future<Output = T> + Send) -> T {
     graph::block_on(future)
 }
 
+/// Returned by host exports that gave up waiting on a future because the
+/// handler that called them ran out of time.
+#[derive(Debug)]
+pub(crate) struct DeadlineExceeded;
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "the handler timed out while waiting for the operation")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl DeadlineExceeded {
+    pub(crate) fn caused(e: &anyhow::Error) -> bool {
+        e.chain().any(|cause| cause.is::<DeadlineExceeded>())
+    }
+}
+
+fn block_on_until<I, ER>(
+    future: impl Future<Item = I, Error = ER> + Send,
+    deadline: Option<Instant>,
+) -> Result<Result<I, ER>, DeadlineExceeded> {
+    block_on03_until(future.compat(), deadline)
+}
+
+/// Like `block_on03`, but stops waiting at `deadline`. The future is dropped
+/// at that point, which cancels whatever request it was making.
+fn block_on03_until<T>(
+    future: impl futures03::Future<Output = T> + Send,
+    deadline: Option<Instant>,
+) -> Result<T, DeadlineExceeded> {
+    match deadline {
+        None => Ok(block_on03(future)),
+        Some(deadline) => block_on03(tokio::time::timeout_at(
+            tokio::time::Instant::from_std(deadline),
+            future,
+        ))
+        .map_err(|_| DeadlineExceeded),
+    }
+}
+
 #[test]
 fn bytes_to_string_is_lossy() {
     assert_eq!(
         "Downcoin WETH-USDT",
         bytes_to_string(
             &graph::log::logger(true),
-            vec![68, 111, 119, 110, 99, 111, 105, 110, 32, 87, 69, 84, 72, 45, 85, 83, 68, 84]
+            vec![68, 111, 119, 110, 99, 111, 105, 110, 32, 87, 69, 84, 72, 45, 85, 83, 68, 84],
+            1024,
+            true
         )
     );
 
@@ -860,7 +4026,276 @@ fn bytes_to_string_is_lossy() {
             vec![
                 68, 111, 119, 110, 99, 111, 105, 110, 32, 87, 69, 84, 72, 45, 85, 83, 68, 84, 160,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-            ]
+            ],
+            1024,
+            true
         )
     )
 }
+
+#[test]
+fn bytes_to_string_trim_nulls() {
+    let logger = graph::log::logger(true);
+    let bytes = b"a\0b\xff\0\0".to_vec();
+    assert_eq!(
+        "a\0b\u{fffd}",
+        bytes_to_string(&logger, bytes.clone(), 1024, true)
+    );
+    assert_eq!(
+        "a\0b\u{fffd}\0\0",
+        bytes_to_string(&logger, bytes, 1024, false)
+    );
+}
+
+#[test]
+fn ethereum_decode_nested_static_tuple() {
+    let token = Token::Tuple(vec![
+        Token::Uint(42.into()),
+        Token::Tuple(vec![
+            Token::Address(Address::repeat_byte(0x11)),
+            Token::Bool(true),
+        ]),
+    ]);
+    let data = ethereum_encode(token.clone()).unwrap();
+    assert_eq!(
+        token,
+        ethereum_decode("(uint256,(address,bool))".to_string(), data, 1024).unwrap()
+    );
+}
+
+#[test]
+fn ethereum_encode_array_round_trip() {
+    let tokens = vec![
+        Token::Uint(1.into()),
+        Token::Uint(2.into()),
+        Token::Uint(3.into()),
+    ];
+    let data = ethereum_encode_array("uint256".to_string(), tokens.clone()).unwrap();
+    assert_eq!(
+        Token::Array(tokens),
+        ethereum_decode("uint256[]".to_string(), data, 1024).unwrap()
+    );
+
+    assert!(ethereum_encode_array(
+        "uint256".to_string(),
+        vec![Token::Uint(1.into()), Token::Bool(true)]
+    )
+    .is_err());
+    assert!(ethereum_encode_array("uint257".to_string(), vec![]).is_err());
+}
+
+#[test]
+fn ethereum_hash_encoded_matches_solidity() {
+    // keccak256(abi.encode(uint256(1), address(0x1111111111111111111111111111111111111111)))
+    let hash = ethereum_hash_encoded(vec![
+        Token::Uint(1.into()),
+        Token::Address(Address::repeat_byte(0x11)),
+    ])
+    .unwrap();
+    assert_eq!(
+        "e0c7a9983a810c24cb2fe92669f4f7e99cdccb534b2d47678b3ca9b9c903bb11",
+        ::hex::encode(hash)
+    );
+}
+
+#[test]
+fn ethereum_decode_nested_dynamic_tuple() {
+    let token = Token::Tuple(vec![
+        Token::Uint(42.into()),
+        Token::Tuple(vec![
+            Token::String("graph".to_string()),
+            Token::Array(vec![
+                Token::Address(Address::repeat_byte(0x11)),
+                Token::Address(Address::repeat_byte(0x22)),
+            ]),
+        ]),
+    ]);
+    let data = ethereum_encode(token.clone()).unwrap();
+    assert_eq!(
+        token,
+        ethereum_decode("(uint256,(string,address[]))".to_string(), data, 1024).unwrap()
+    );
+}
+
+#[test]
+fn isqrt_rounds_down() {
+    for (n, root) in &[(1, 1), (2, 1), (3, 1), (4, 2), (15, 3), (16, 4), (17, 4)] {
+        assert_eq!(BigInt::from(*root), isqrt(BigInt::from(*n)));
+    }
+    let square = BigInt::from(10).pow(40) + BigInt::from(7);
+    assert_eq!(BigInt::from(10).pow(20), isqrt(square));
+}
+
+#[test]
+fn ethereum_decode_fixed_bytes_padding() {
+    let mut data = vec![0xde, 0xad, 0xbe, 0xef];
+    data.extend(vec![0u8; 28]);
+    assert_eq!(
+        Token::FixedBytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        ethereum_decode("bytes4".to_string(), data.clone(), 1024).unwrap()
+    );
+
+    // Five bytes where there should be four
+    data[4] = 0x01;
+    let err = ethereum_decode("bytes4".to_string(), data.clone(), 1024).unwrap_err();
+    assert!(err.to_string().contains("bytes4"));
+    let mut tuple = vec![0u8; 32];
+    tuple.extend(data);
+    assert!(ethereum_decode("(uint256,bytes4)".to_string(), tuple, 1024).is_err());
+}
+
+#[test]
+fn ethereum_decode_size_limit() {
+    fn word(n: usize) -> Vec<u8> {
+        let mut word = vec![0u8; 24];
+        word.extend_from_slice(&(n as u64).to_be_bytes());
+        word
+    }
+
+    // An array that claims to have 2^32 - 1 elements
+    let mut data = word(32);
+    data.extend(word(u32::MAX as usize));
+    let err = ethereum_decode("uint256[]".to_string(), data, 1 << 20).unwrap_err();
+    assert!(err.to_string().contains("larger than 1048576 bytes"));
+
+    // A thousand elements that all point to the same 1000 bytes
+    let mut data = word(32);
+    data.extend(word(1000));
+    for _ in 0..1000 {
+        data.extend(word(32 * 1000));
+    }
+    data.extend(word(1000));
+    data.extend(vec![7u8; 1024]);
+    assert!(ethereum_decode("bytes[]".to_string(), data.clone(), 2 << 20).is_ok());
+    let err = ethereum_decode("bytes[]".to_string(), data, 100_000).unwrap_err();
+    assert!(err.to_string().contains("larger than 100000 bytes"));
+}
+
+#[test]
+fn json_stringify_round_trip() {
+    let json = br#"{"b": [1, 2.50, {"c": null}], "a": "x\"y"}"#.to_vec();
+    let value = json_from_bytes(&json).unwrap();
+
+    let compact = json_stringify(&value, false).unwrap();
+    assert_eq!(r#"{"a":"x\"y","b":[1,2.50,{"c":null}]}"#, compact);
+    assert_eq!(value, json_from_bytes(&compact.into_bytes()).unwrap());
+
+    let pretty = json_stringify(&value, true).unwrap();
+    assert!(pretty.contains("\n  \"a\": \"x\\\"y\","));
+    assert_eq!(value, json_from_bytes(&pretty.into_bytes()).unwrap());
+}
+
+#[test]
+fn json_canonicalize_sorts_keys() {
+    let a = json_from_bytes(&br#"{"b": [1, {"y": null, "x": true}], "a": "s"}"#.to_vec()).unwrap();
+    let b = json_from_bytes(&br#"{"a":"s","b":[1,{"x":true,"y":null}]}"#.to_vec()).unwrap();
+    assert_eq!(
+        json_canonicalize(&a).unwrap(),
+        json_canonicalize(&b).unwrap()
+    );
+    assert_eq!(
+        br#"{"a":"s","b":[1,{"x":true,"y":null}]}"#.to_vec(),
+        json_canonicalize(&a).unwrap()
+    );
+}
+
+#[test]
+fn json_canonicalize_numbers() {
+    for (number, canonical) in &[
+        ("0", "0"),
+        ("-0.0", "0"),
+        ("1.50E+2", "150"),
+        ("-12.3400", "-12.34"),
+        ("0.000001", "0.000001"),
+        ("0.0000001", "1e-7"),
+        ("1e20", "100000000000000000000"),
+        ("1e21", "1e+21"),
+        ("1e22", "1e+22"),
+        (
+            "123456789012345678901234567890",
+            "1.2345678901234567890123456789e+29",
+        ),
+    ] {
+        assert_eq!(
+            canonical.to_string(),
+            canonical_json_number(number).unwrap()
+        );
+    }
+}
+
+#[test]
+fn json_get_field_paths() {
+    let value = json_from_bytes(&br#"{"data": {"items": [1, 2], "count": 2}}"#.to_vec()).unwrap();
+
+    assert_eq!(
+        Some(serde_json::json!([1, 2])),
+        json_get_field(&value, "data.items").unwrap()
+    );
+    assert_eq!(None, json_get_field(&value, "data.missing").unwrap());
+    assert_eq!(None, json_get_field(&value, "data.count.value").unwrap());
+    assert!(json_get_field(&value, "data..items").is_err());
+}
+
+#[test]
+fn json_merge_nested() {
+    let base = serde_json::json!({
+        "name": "base",
+        "tags": ["a", "b"],
+        "meta": {"version": 1, "author": {"name": "x", "email": "x@example.com"}}
+    });
+    let overlay = serde_json::json!({
+        "tags": ["c"],
+        "meta": {"version": 2, "author": {"name": "y"}, "license": null}
+    });
+
+    assert_eq!(
+        serde_json::json!({
+            "name": "base",
+            "tags": ["c"],
+            "meta": {
+                "version": 2,
+                "author": {"name": "y", "email": "x@example.com"},
+                "license": null
+            }
+        }),
+        json_merge(&base, &overlay).unwrap()
+    );
+    // An overlay that is not an object replaces the base
+    assert_eq!(
+        serde_json::json!([1]),
+        json_merge(&base, &serde_json::json!([1])).unwrap()
+    );
+}
+
+#[test]
+fn json_type_of_values() {
+    let value = json_from_bytes(
+        &br#"{"s": "a", "n": 1.5, "b": true, "a": [], "o": {}, "z": null}"#.to_vec(),
+    )
+    .unwrap();
+
+    for (field, expected) in &[
+        ("s", "string"),
+        ("n", "number"),
+        ("b", "bool"),
+        ("a", "array"),
+        ("o", "object"),
+        ("z", "null"),
+    ] {
+        assert_eq!(*expected, json_type_of(&value[field]).unwrap());
+    }
+    assert_eq!("object", json_type_of(&value).unwrap());
+}
+
+#[test]
+fn parse_json_int_hex() {
+    assert_eq!(parse_json_int("26", u64::from_str_radix).unwrap(), 26);
+    assert_eq!(parse_json_int("0x1a", u64::from_str_radix).unwrap(), 26);
+    assert_eq!(parse_json_int("0x1A", i64::from_str_radix).unwrap(), 26);
+    assert_eq!(parse_json_int("-26", i64::from_str_radix).unwrap(), -26);
+    assert!(parse_json_int("0x", u64::from_str_radix).is_err());
+    assert!(parse_json_int("0x-1a", i64::from_str_radix).is_err());
+    assert!(parse_json_int("0xzz", u64::from_str_radix).is_err());
+    assert!(parse_json_int("1a", u64::from_str_radix).is_err());
+    assert!(parse_json_int("garbage", u64::from_str_radix).is_err());
+}
@@ -18,6 +18,7 @@ mod error;
 
 use graph::prelude::web3::types::Address;
 use graph::prelude::SubgraphStore;
+use graph_chain_ethereum::EthereumStateOverrides;
 
 #[derive(Clone, Debug)]
 pub(crate) struct UnresolvedContractCall {
@@ -26,6 +27,11 @@ pub(crate) struct UnresolvedContractCall {
     pub function_name: String,
     pub function_signature: Option<String>,
     pub function_args: Vec<ethabi::Token>,
+    pub from: Option<Address>,
+    pub gas_limit: Option<u64>,
+    /// Accounts to change for the call. Since this makes the result depend
+    /// on more than the chain, the overrides go into the proof of indexing.
+    pub state_overrides: Option<EthereumStateOverrides>,
 }
 
 trait RuntimeStore: SubgraphStore {}
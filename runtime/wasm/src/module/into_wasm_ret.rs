@@ -44,6 +44,13 @@ impl IntoWasmRet for f64 {
     }
 }
 
+impl IntoWasmRet for u32 {
+    type Ret = u32;
+    fn into_wasm_ret(self) -> u32 {
+        self
+    }
+}
+
 impl IntoWasmRet for u64 {
     type Ret = u64;
     fn into_wasm_ret(self) -> u64 {
@@ -26,7 +26,10 @@ use host_exports::HostExportError;
 use web3::types::{Log, Transaction, U256};
 
 use crate::asc_abi::class::*;
-use crate::host_exports::{EthereumCallError, HostExports};
+use crate::host_exports::{
+    DataOrigin, DeadlineExceeded, EnsNameMemo, EthereumCallError, EthereumCallMemo, HostExports,
+    KeccakHashers, RoundingMode, TriggerKind,
+};
 use crate::mapping::ValidModule;
 use crate::UnresolvedContractCall;
 
@@ -147,6 +150,17 @@ impl<C: Blockchain> WasmInstance<C> {
             .erase()
         };
 
+        let mut random_seed = transaction.hash.as_bytes().to_vec();
+        let mut log_index = [0u8; 32];
+        log.log_index
+            .unwrap_or(U256::zero())
+            .to_big_endian(&mut log_index);
+        random_seed.extend_from_slice(&log_index);
+
+        self.instance_ctx_mut().trigger_kind = Some(TriggerKind::Event);
+        self.instance_ctx_mut().random_seed = random_seed;
+        self.instance_ctx_mut().block_timestamp =
+            Some(BigInt::from_unsigned_u256(&block.timestamp));
         self.invoke_handler(handler_name, event)
     }
 
@@ -173,6 +187,10 @@ impl<C: Blockchain> WasmInstance<C> {
             asc_new::<AscEthereumCall, _, _>(&mut self, &call)?.erase()
         };
 
+        self.instance_ctx_mut().trigger_kind = Some(TriggerKind::Call);
+        self.instance_ctx_mut().random_seed = transaction.hash.as_bytes().to_vec();
+        self.instance_ctx_mut().block_timestamp =
+            Some(BigInt::from_unsigned_u256(&block.timestamp));
         self.invoke_handler(handler_name, arg)
     }
 
@@ -181,11 +199,15 @@ impl<C: Blockchain> WasmInstance<C> {
         block: Arc<LightEthereumBlock>,
         handler_name: &str,
     ) -> Result<BlockState<C>, MappingError> {
+        self.instance_ctx_mut().block_timestamp =
+            Some(BigInt::from_unsigned_u256(&block.timestamp));
         let block = EthereumBlockData::from(block.as_ref());
 
         // Prepare an EthereumBlock for the WASM runtime
         let arg = asc_new(&mut self, &block)?;
 
+        self.instance_ctx_mut().trigger_kind = Some(TriggerKind::Block);
+        self.instance_ctx_mut().random_seed = b"block".to_vec();
         self.invoke_handler(handler_name, arg)
     }
 
@@ -290,6 +312,10 @@ pub struct ExperimentalFeatures {
     pub allow_non_deterministic_ipfs: bool,
     pub allow_non_deterministic_arweave: bool,
     pub allow_non_deterministic_3box: bool,
+    pub allow_custom_poi_events: bool,
+    /// Warn about `ethereum.decode` results that do not encode to the bytes
+    /// they were decoded from, which usually means the types were wrong.
+    pub check_ethereum_decode: bool,
 }
 
 pub(crate) struct WasmInstanceContext<C: Blockchain> {
@@ -322,6 +348,37 @@ pub(crate) struct WasmInstanceContext<C: Blockchain> {
     // A host export trap ocurred for a deterministic reason.
     deterministic_host_trap: bool,
 
+    // Number of messages logged by the current handler.
+    log_count: usize,
+
+    // Number of values `random.deterministic` returned to the current handler.
+    random_count: u64,
+
+    // Results of the `ethereum.call`s made by the current handler.
+    ethereum_call_memo: EthereumCallMemo,
+
+    // Results of the `ens.nameByHash` lookups made by the current handler.
+    ens_name_memo: EnsNameMemo,
+
+    // The `crypto.keccak256Init` hashers of the current handler.
+    keccak_hashers: KeccakHashers,
+
+    // Where the data being processed comes from. Set to `Ipfs` for the instances that run
+    // `ipfs.map` callbacks.
+    pub(crate) data_origin: DataOrigin,
+
+    // The kind of trigger being handled. `None` until a handler is invoked.
+    pub(crate) trigger_kind: Option<TriggerKind>,
+
+    // Identifies the trigger being handled among the triggers of its block, so that
+    // `random.deterministic` gives each of them a different sequence. Empty until a handler is
+    // invoked, and in the instances that run `ipfs.map` callbacks.
+    pub(crate) random_seed: Vec<u8>,
+
+    // The timestamp of the block being handled. `None` until a handler is invoked, and in the
+    // instances that run `ipfs.map` callbacks.
+    pub(crate) block_timestamp: Option<BigInt>,
+
     pub(crate) experimental_features: ExperimentalFeatures,
 }
 
@@ -367,12 +424,9 @@ impl<C: Blockchain> WasmInstance<C> {
             });
         }
 
+        // Parameters are `u32`s, usually pointers, unless a type is given.
         macro_rules! link {
-            ($wasm_name:expr, $rust_name:ident, $($param:ident),*) => {
-                link!($wasm_name, $rust_name, "host_export_other", $($param),*)
-            };
-
-            ($wasm_name:expr, $rust_name:ident, $section:expr, $($param:ident),*) => {
+            ($wasm_name:expr, $rust_name:ident, $section:literal, $($param:ident: $ty:ty),*) => {
                 let modules = valid_module
                     .import_name_to_modules
                     .get($wasm_name)
@@ -389,7 +443,7 @@ impl<C: Blockchain> WasmInstance<C> {
                     linker.func(
                         module,
                         $wasm_name,
-                        move |caller: wasmtime::Caller, $($param: u32),*| {
+                        move |caller: wasmtime::Caller, $($param: $ty),*| {
                             let instance = func_shared_ctx.upgrade().unwrap();
                             let mut instance = instance.borrow_mut();
 
@@ -428,6 +482,18 @@ impl<C: Blockchain> WasmInstance<C> {
                     )?;
                 }
             };
+
+            ($wasm_name:expr, $rust_name:ident, $section:literal, $($param:ident),*) => {
+                link!($wasm_name, $rust_name, $section, $($param: u32),*)
+            };
+
+            ($wasm_name:expr, $rust_name:ident, $($param:ident: $ty:ty),*) => {
+                link!($wasm_name, $rust_name, "host_export_other", $($param: $ty),*)
+            };
+
+            ($wasm_name:expr, $rust_name:ident, $($param:ident),*) => {
+                link!($wasm_name, $rust_name, "host_export_other", $($param: u32),*)
+            };
         }
 
         let modules = valid_module
@@ -487,8 +553,30 @@ impl<C: Blockchain> WasmInstance<C> {
             })?;
         }
 
+        link!(
+            "ethereum.callBySelector",
+            ethereum_call_by_selector,
+            "host_export_ethereum_call",
+            address_ptr,
+            selector_ptr,
+            args_ptr,
+            return_types_ptr
+        );
         link!("ethereum.encode", ethereum_encode, params_ptr);
+        link!(
+            "ethereum.encodeArray",
+            ethereum_encode_array,
+            element_type_ptr,
+            tokens_ptr
+        );
         link!("ethereum.decode", ethereum_decode, params_ptr, data_ptr);
+        link!("ethereum.hashEncoded", ethereum_hash_encoded, tokens_ptr);
+        link!("ethereum.functionSelector", ethereum_function_selector, ptr);
+        link!(
+            "ethereum.eventByTopic0",
+            ethereum_event_by_topic0,
+            topic0_ptr
+        );
 
         link!("abort", abort, message_ptr, file_name_ptr, line, column);
 
@@ -501,8 +589,36 @@ impl<C: Blockchain> WasmInstance<C> {
             id,
             data
         );
+        link!(
+            "store.setReturning",
+            store_set_returning,
+            "host_export_store_set",
+            entity,
+            id,
+            data
+        );
 
         link!("ipfs.cat", ipfs_cat, "host_export_ipfs_cat", hash_ptr);
+        link!(
+            "ipfs.catFromGateway",
+            ipfs_cat_from_gateway,
+            "host_export_ipfs_cat",
+            hash_ptr,
+            gateway_ptr
+        );
+        link!(
+            "ipfs.catRequired",
+            ipfs_cat_required,
+            "host_export_ipfs_cat",
+            hash_ptr
+        );
+        link!(
+            "ipfs.catWithContentType",
+            ipfs_cat_with_content_type,
+            "host_export_ipfs_cat",
+            hash_ptr
+        );
+        link!("ipfs.ls", ipfs_ls, "host_export_ipfs_ls", hash_ptr);
         link!(
             "ipfs.map",
             ipfs_map,
@@ -512,15 +628,96 @@ impl<C: Blockchain> WasmInstance<C> {
             user_data,
             flags
         );
+        link!(
+            "ipfs.mapFrom",
+            ipfs_map_from,
+            "host_export_ipfs_map",
+            link_ptr,
+            callback,
+            user_data,
+            flags,
+            start_line
+        );
 
+        link!(
+            "store.getWithVersion",
+            store_get_with_version,
+            entity_ptr,
+            id_ptr
+        );
+        link!(
+            "store.getWithStatus",
+            store_get_with_status,
+            entity_ptr,
+            id_ptr
+        );
+        link!(
+            "store.getByField",
+            store_get_by_field,
+            entity_ptr,
+            field_ptr,
+            value_ptr
+        );
         link!("store.remove", store_remove, entity_ptr, id_ptr);
+        link!(
+            "store.removeCascade",
+            store_remove_cascade,
+            entity_ptr,
+            id_ptr,
+            recursive
+        );
+        link!(
+            "store.removeIf",
+            store_remove_if,
+            entity_ptr,
+            id_ptr,
+            field_ptr,
+            expected_ptr
+        );
+        link!(
+            "store.increment",
+            store_increment,
+            entity_ptr,
+            id_ptr,
+            field_ptr,
+            delta_ptr
+        );
+        link!(
+            "proofOfIndexing.write",
+            proof_of_indexing_write,
+            name_ptr,
+            data_ptr
+        );
 
         link!("typeConversion.bytesToString", bytes_to_string, ptr);
+        link!(
+            "typeConversion.bytesToStringWithNulls",
+            bytes_to_string_with_nulls,
+            ptr
+        );
+        link!(
+            "typeConversion.bytesToStringUtf16",
+            bytes_to_string_utf16,
+            ptr,
+            little_endian
+        );
         link!("typeConversion.bytesToHex", bytes_to_hex, ptr);
         link!("typeConversion.bigIntToString", big_int_to_string, ptr);
         link!("typeConversion.bigIntToHex", big_int_to_hex, ptr);
         link!("typeConversion.stringToH160", string_to_h160, ptr);
         link!("typeConversion.bytesToBase58", bytes_to_base58, ptr);
+        link!("typeConversion.bytesToBase32", bytes_to_base32, ptr);
+        link!("typeConversion.base32ToBytes", base32_to_bytes, ptr);
+
+        link!("bytes.reverse", bytes_reverse, ptr);
+        link!("bytes.slice", bytes_slice, bytes_ptr, start, end);
+        link!("bytes.padLeft", bytes_pad_left, bytes_ptr, length);
+        link!("bytes.padRight", bytes_pad_right, bytes_ptr, length);
+        link!("bytes.equals", bytes_equal, a_ptr, b_ptr);
+
+        link!("string.split", string_split, s_ptr, delimiter_ptr);
+        link!("string.toLower", string_to_lower, s_ptr);
+        link!("string.toUpper", string_to_upper, s_ptr);
 
         link!("json.fromBytes", json_from_bytes, ptr);
         link!("json.try_fromBytes", json_try_from_bytes, ptr);
@@ -528,8 +725,48 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("json.toU64", json_to_u64, ptr);
         link!("json.toF64", json_to_f64, ptr);
         link!("json.toBigInt", json_to_big_int, ptr);
+        link!("json.toBigDecimal", json_to_big_decimal, ptr);
+        link!("json.canonicalize", json_canonicalize, ptr);
+        link!("json.stringify", json_stringify, value_ptr, pretty);
+        link!("json.getField", json_get_field, value_ptr, path_ptr);
+        link!("json.typeOf", json_type_of, value_ptr);
+        link!("json.merge", json_merge, base_ptr, overlay_ptr);
 
         link!("crypto.keccak256", crypto_keccak_256, ptr);
+        link!("crypto.keccak256Init", crypto_keccak_256_init,);
+        link!(
+            "crypto.keccak256Update",
+            crypto_keccak_256_update,
+            handle,
+            chunk_ptr
+        );
+        link!(
+            "crypto.keccak256Finalize",
+            crypto_keccak_256_finalize,
+            handle
+        );
+        link!("crypto.keccak256String", crypto_keccak_256_string, ptr);
+        link!("crypto.makeEntityId", make_entity_id, parts_ptr);
+        link!(
+            "ethereum.verifyMerkleProof",
+            ethereum_verify_merkle_proof,
+            leaf_ptr,
+            proof_ptr,
+            root_ptr
+        );
+        link!(
+            "crypto.verifyEd25519",
+            crypto_verify_ed25519,
+            message_ptr,
+            signature_ptr,
+            public_key_ptr
+        );
+        link!(
+            "crypto.hmacSha256",
+            crypto_hmac_sha256,
+            key_ptr,
+            message_ptr
+        );
 
         link!("bigInt.plus", big_int_plus, x_ptr, y_ptr);
         link!("bigInt.minus", big_int_minus, x_ptr, y_ptr);
@@ -538,19 +775,73 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("bigInt.dividedByDecimal", big_int_divided_by_decimal, x, y);
         link!("bigInt.mod", big_int_mod, x_ptr, y_ptr);
         link!("bigInt.pow", big_int_pow, x_ptr, exp);
+        link!(
+            "bigInt.powMod",
+            big_int_pow_mod,
+            base_ptr,
+            exponent_ptr,
+            modulus_ptr
+        );
         link!("bigInt.fromString", big_int_from_string, ptr);
+        link!("bigInt.fromAsciiBytes", big_int_from_ascii_bytes, bytes_ptr);
+        link!(
+            "bigInt.fromStringRadix",
+            big_int_from_string_radix,
+            ptr,
+            radix
+        );
+        link!(
+            "bigInt.toStringRadix",
+            big_int_to_string_radix,
+            x_ptr,
+            radix
+        );
+        link!("bigInt.fromU64", big_int_from_u64, n: u64);
+        link!("bigInt.fromI64", big_int_from_i64, n: i64);
         link!("bigInt.bitOr", big_int_bit_or, x_ptr, y_ptr);
         link!("bigInt.bitAnd", big_int_bit_and, x_ptr, y_ptr);
         link!("bigInt.leftShift", big_int_left_shift, x_ptr, bits);
         link!("bigInt.rightShift", big_int_right_shift, x_ptr, bits);
+        link!("bigInt.bitLength", big_int_bit_length, x_ptr);
+        link!("bigInt.countOnes", big_int_count_ones, x_ptr);
+        link!("bigInt.toF64", big_int_to_f64, x_ptr);
+        link!("bigInt.min", big_int_min, x_ptr, y_ptr);
+        link!("bigInt.max", big_int_max, x_ptr, y_ptr);
 
         link!("bigDecimal.toString", big_decimal_to_string, ptr);
         link!("bigDecimal.fromString", big_decimal_from_string, ptr);
+        link!(
+            "bigDecimal.fromBigInt",
+            big_decimal_from_big_int,
+            digits_ptr,
+            exp
+        );
         link!("bigDecimal.plus", big_decimal_plus, x_ptr, y_ptr);
         link!("bigDecimal.minus", big_decimal_minus, x_ptr, y_ptr);
         link!("bigDecimal.times", big_decimal_times, x_ptr, y_ptr);
         link!("bigDecimal.dividedBy", big_decimal_divided_by, x, y);
+        link!(
+            "bigDecimal.dividedByWithPrecision",
+            big_decimal_divided_by_with_precision,
+            x_ptr,
+            y_ptr,
+            precision
+        );
         link!("bigDecimal.equals", big_decimal_equals, x_ptr, y_ptr);
+        link!("bigDecimal.toBigInt", big_decimal_to_big_int, x_ptr, mode);
+        link!("bigDecimal.scale", big_decimal_scale, x_ptr);
+        link!("bigDecimal.toF64", big_decimal_to_f64, x_ptr);
+        link!(
+            "bigDecimal.withScale",
+            big_decimal_with_scale,
+            x_ptr: u32,
+            scale: i64
+        );
+        link!("bigDecimal.sqrt", big_decimal_sqrt, x_ptr);
+        link!("bigDecimal.abs", big_decimal_abs, x_ptr);
+        link!("bigDecimal.neg", big_decimal_neg, x_ptr);
+        link!("bigDecimal.min", big_decimal_min, x_ptr, y_ptr);
+        link!("bigDecimal.max", big_decimal_max, x_ptr, y_ptr);
 
         link!("dataSource.create", data_source_create, name, params);
         link!(
@@ -560,9 +851,39 @@ impl<C: Blockchain> WasmInstance<C> {
             params,
             context
         );
+        link!(
+            "dataSource.createWithEntity",
+            data_source_create_with_entity,
+            name,
+            params,
+            entity_ptr,
+            id_ptr,
+            data_ptr
+        );
         link!("dataSource.address", data_source_address,);
+        link!("dataSource.addressOrNull", data_source_address_or_null,);
         link!("dataSource.network", data_source_network,);
+        link!("dataSource.subgraphId", data_source_subgraph_id,);
+        link!("dataSource.chainId", data_source_chain_id,);
         link!("dataSource.context", data_source_context,);
+        link!("dataSource.contextString", data_source_context_string, ptr);
+        link!("dataSource.contextBytes", data_source_context_bytes, ptr);
+        link!("dataSource.contextBigInt", data_source_context_big_int, ptr);
+        link!("dataSource.templateNames", data_source_template_names,);
+        link!(
+            "dataSource.templateKind",
+            data_source_template_kind,
+            name_ptr
+        );
+        link!("dataSource.triggerKind", data_source_trigger_kind,);
+
+        link!("random.deterministic", deterministic_random,);
+        link!("random.bigIntBelow", big_int_random_below, n_ptr);
+
+        link!("block.number", block_number,);
+        link!("block.hash", block_hash,);
+        link!("block.nextSequence", next_sequence,);
+        link!("block.timestamp", block_timestamp,);
 
         link!("ens.nameByHash", ens_name_by_hash, ptr);
 
@@ -679,6 +1000,15 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             arena_start_ptr: 0,
             possible_reorg: false,
             deterministic_host_trap: false,
+            log_count: 0,
+            random_count: 0,
+            ethereum_call_memo: EthereumCallMemo::new(),
+            ens_name_memo: EnsNameMemo::new(),
+            keccak_hashers: KeccakHashers::new(),
+            data_origin: DataOrigin::Chain,
+            trigger_kind: None,
+            random_seed: Vec::new(),
+            block_timestamp: None,
             experimental_features,
         })
     }
@@ -716,9 +1046,27 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             arena_start_ptr: 0,
             possible_reorg: false,
             deterministic_host_trap: false,
+            log_count: 0,
+            random_count: 0,
+            ethereum_call_memo: EthereumCallMemo::new(),
+            ens_name_memo: EnsNameMemo::new(),
+            keccak_hashers: KeccakHashers::new(),
+            data_origin: DataOrigin::Chain,
+            trigger_kind: None,
+            random_seed: Vec::new(),
+            block_timestamp: None,
             experimental_features,
         })
     }
+
+    /// The point in time at which the current handler times out, if it has a
+    /// timeout. Host exports that block on the network stop waiting then, since
+    /// the watchdog can only interrupt the handler while wasm code is running.
+    fn handler_deadline(&self) -> Option<Instant> {
+        let timeout = self.timeout?;
+        let elapsed = self.timeout_stopwatch.lock().unwrap().elapsed();
+        Some(Instant::now() + timeout.checked_sub(elapsed).unwrap_or_default())
+    }
 }
 
 // Implementation of externals.
@@ -749,9 +1097,19 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             _ => Some(column_number),
         };
 
+        // Creating a trap captures the wasm frames that are on the stack.
+        let backtrace = Trap::new("abort")
+            .trace()
+            .iter()
+            .map(|frame| match frame.func_name() {
+                Some(name) => name.to_string(),
+                None => format!("wasm-function[{}]", frame.func_index()),
+            })
+            .collect();
+
         self.ctx
             .host_exports
-            .abort(message, file_name, line_number, column_number)
+            .abort(message, file_name, line_number, column_number, backtrace)
     }
 
     /// function store.set(entity: string, id: string, data: Entity): void
@@ -772,14 +1130,90 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             &self.ctx.logger,
             &mut self.ctx.state,
             &self.ctx.proof_of_indexing,
+            self.data_origin,
             entity,
             id,
             data,
+            self.block_timestamp.as_ref(),
             stopwatch,
         )?;
         Ok(())
     }
 
+    /// function store.setReturning(entity: string, id: string, data: Entity): Entity
+    fn store_set_returning(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+        data_ptr: AscPtr<AscEntity>,
+    ) -> Result<AscPtr<AscEntity>, HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let data = try_asc_get(self, data_ptr)?;
+
+        let entity = self.ctx.host_exports.store_set_returning(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            entity,
+            id,
+            data,
+            self.block_timestamp.as_ref(),
+            &self.host_metrics.stopwatch,
+        )?;
+        Ok(asc_new(self, &entity.sorted())?)
+    }
+
+    /// function proofOfIndexing.write(name: string, data: Entity): void
+    fn proof_of_indexing_write(
+        &mut self,
+        name_ptr: AscPtr<AscString>,
+        data_ptr: AscPtr<AscEntity>,
+    ) -> Result<(), HostExportError> {
+        if !self.experimental_features.allow_custom_poi_events {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "`proofOfIndexing.write` is experimental and not enabled on this node"
+            )));
+        }
+        let name = asc_get(self, name_ptr)?;
+        let data = try_asc_get(self, data_ptr)?;
+        self.ctx.host_exports.proof_of_indexing_write(
+            &self.ctx.logger,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            name,
+            data,
+        )?;
+        Ok(())
+    }
+
+    /// function store.increment(entity: string, id: string, field: string, delta: BigInt): void
+    fn store_increment(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+        field_ptr: AscPtr<AscString>,
+        delta_ptr: AscPtr<AscBigInt>,
+    ) -> Result<(), HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let field = asc_get(self, field_ptr)?;
+        let delta = asc_get(self, delta_ptr)?;
+        self.ctx.host_exports.store_increment(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            entity,
+            id,
+            field,
+            delta,
+            self.block_timestamp.as_ref(),
+            &self.host_metrics.stopwatch,
+        )
+    }
+
     /// function store.remove(entity: string, id: string): void
     fn store_remove(
         &mut self,
@@ -792,8 +1226,53 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             &self.ctx.logger,
             &mut self.ctx.state,
             &self.ctx.proof_of_indexing,
+            self.data_origin,
+            entity,
+            id,
+        )
+    }
+
+    /// function store.removeCascade(entity: string, id: string, recursive: bool): void
+    fn store_remove_cascade(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+        recursive: u32,
+    ) -> Result<(), HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        self.ctx.host_exports.store_remove_cascade(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            entity,
+            id,
+            recursive != 0,
+        )
+    }
+
+    /// function store.removeIf(entity: string, id: string, field: string, expected: Value): bool
+    fn store_remove_if(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+        field_ptr: AscPtr<AscString>,
+        expected_ptr: AscPtr<AscEnum<StoreValueKind>>,
+    ) -> Result<bool, HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let field = asc_get(self, field_ptr)?;
+        let expected = try_asc_get(self, expected_ptr)?;
+        self.ctx.host_exports.store_remove_if(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
             entity,
             id,
+            field,
+            expected,
         )
     }
 
@@ -828,19 +1307,114 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         Ok(ret)
     }
 
+    /// function store.getByField(entity: string, field: string, value: Value): Entity | null
+    fn store_get_by_field(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        field_ptr: AscPtr<AscString>,
+        value_ptr: AscPtr<AscEnum<StoreValueKind>>,
+    ) -> Result<AscPtr<AscEntity>, HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let field = asc_get(self, field_ptr)?;
+        let value = try_asc_get(self, value_ptr)?;
+        let entity_option =
+            self.ctx
+                .host_exports
+                .store_get_by_field(&mut self.ctx.state, entity, field, value)?;
+        match entity_option {
+            Some(entity) => Ok(asc_new(self, &entity.sorted())?),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function store.getWithVersion(entity: string, id: string): EntityWithVersion | null
+    fn store_get_with_version(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscEntityWithVersion>, HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let block_number = self.ctx.block_ptr.number;
+        let result = self.ctx.host_exports.store_get_with_version(
+            &mut self.ctx.state,
+            block_number,
+            entity,
+            id,
+        )?;
+
+        let ret = match result {
+            Some((entity, version)) => asc_new(self, &(entity.sorted(), version))?,
+            None => AscPtr::null(),
+        };
+        Ok(ret)
+    }
+
+    /// function store.getWithStatus(entity: string, id: string): EntityWithStatus
+    fn store_get_with_status(
+        &mut self,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscEntityWithStatus>, HostExportError> {
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let status =
+            self.ctx
+                .host_exports
+                .store_get_with_status(&mut self.ctx.state, entity, id)?;
+        Ok(asc_new(self, &status)?)
+    }
+
     /// function ethereum.call(call: SmartContractCall): Array<Token> | null
     fn ethereum_call(
         &mut self,
         call: UnresolvedContractCall,
     ) -> Result<AscEnumArray<EthereumValueKind>, HostExportError> {
-        let result =
-            self.ctx
-                .host_exports
-                .ethereum_call(&self.ctx.logger, &self.ctx.block_ptr, call);
+        let result = self.ctx.host_exports.ethereum_call(
+            &self.ctx.logger,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            &self.ctx.block_ptr,
+            call,
+            &mut self.ethereum_call_memo,
+            &self.host_metrics,
+            self.handler_deadline(),
+        );
+        match result {
+            Ok(Some(tokens)) => Ok(asc_new(self, tokens.as_slice())?),
+            Ok(None) => Ok(AscPtr::null()),
+            Err(EthereumCallError::Unknown(e)) => Err(HostExportError::Unknown(e.into())),
+            Err(EthereumCallError::Deterministic(e)) => Err(HostExportError::Deterministic(e)),
+            Err(EthereumCallError::PossibleReorg(e)) => {
+                self.possible_reorg = true;
+                Err(HostExportError::Unknown(e))
+            }
+        }
+    }
+
+    /// function ethereum.callBySelector(address: Address, selector: Bytes, args: Array<ethereum.Value>, returnTypes: string): Array<ethereum.Value> | null
+    fn ethereum_call_by_selector(
+        &mut self,
+        address_ptr: AscPtr<AscH160>,
+        selector_ptr: AscPtr<Uint8Array>,
+        args_ptr: AscPtr<Array<AscPtr<AscEnum<EthereumValueKind>>>>,
+        return_types_ptr: AscPtr<AscString>,
+    ) -> Result<AscEnumArray<EthereumValueKind>, HostExportError> {
+        let result = self.ctx.host_exports.ethereum_call_by_selector(
+            &self.ctx.logger,
+            &self.ctx.block_ptr,
+            asc_get(self, address_ptr)?,
+            asc_get(self, selector_ptr)?,
+            asc_get(self, args_ptr)?,
+            asc_get(self, return_types_ptr)?,
+            &self.host_metrics,
+            self.handler_deadline(),
+        );
         match result {
             Ok(Some(tokens)) => Ok(asc_new(self, tokens.as_slice())?),
             Ok(None) => Ok(AscPtr::null()),
             Err(EthereumCallError::Unknown(e)) => Err(HostExportError::Unknown(e.into())),
+            Err(EthereumCallError::Deterministic(e)) => Err(HostExportError::Deterministic(e)),
             Err(EthereumCallError::PossibleReorg(e)) => {
                 self.possible_reorg = true;
                 Err(HostExportError::Unknown(e))
@@ -853,7 +1427,39 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         &mut self,
         bytes_ptr: AscPtr<Uint8Array>,
     ) -> Result<AscPtr<AscString>, DeterministicHostError> {
-        let string = host_exports::bytes_to_string(&self.ctx.logger, asc_get(self, bytes_ptr)?);
+        let string = host_exports::bytes_to_string(
+            &self.ctx.logger,
+            asc_get(self, bytes_ptr)?,
+            self.ctx.host_exports.bytes_to_string_warning_chars,
+            true,
+        );
+        asc_new(self, &string)
+    }
+
+    /// function typeConversion.bytesToStringWithNulls(bytes: Bytes): string
+    fn bytes_to_string_with_nulls(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let string = host_exports::bytes_to_string(
+            &self.ctx.logger,
+            asc_get(self, bytes_ptr)?,
+            self.ctx.host_exports.bytes_to_string_warning_chars,
+            false,
+        );
+        asc_new(self, &string)
+    }
+
+    /// function typeConversion.bytesToStringUtf16(bytes: Bytes, littleEndian: bool): string
+    fn bytes_to_string_utf16(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+        little_endian: u32,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let string = self
+            .ctx
+            .host_exports
+            .bytes_to_string_utf16(asc_get(self, bytes_ptr)?, little_endian != 0)?;
         asc_new(self, &string)
     }
 
@@ -894,17 +1500,55 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &result)
     }
 
-    /// function typeConversion.bigIntToHex(n: Uint8Array): string
-    fn big_int_to_hex(
+    /// function bigInt.fromAsciiBytes(bytes: Bytes): BigInt
+    fn big_int_from_ascii_bytes(
         &mut self,
-        big_int_ptr: AscPtr<AscBigInt>,
-    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
-        let n: BigInt = asc_get(self, big_int_ptr)?;
-        let hex = self.ctx.host_exports.big_int_to_hex(n)?;
-        asc_new(self, &hex)
-    }
-
-    /// function typeConversion.stringToH160(s: String): H160
+        bytes_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_from_ascii_bytes(asc_get(self, bytes_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigInt.fromStringRadix(x: string, radix: u32): BigInt
+    fn big_int_from_string_radix(
+        &mut self,
+        string_ptr: AscPtr<AscString>,
+        radix: u32,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_from_string_radix(asc_get(self, string_ptr)?, radix)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigInt.toStringRadix(x: BigInt, radix: u32): string
+    fn big_int_to_string_radix(
+        &mut self,
+        x_ptr: AscPtr<AscBigInt>,
+        radix: u32,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_to_string_radix(asc_get(self, x_ptr)?, radix)?;
+        asc_new(self, &result)
+    }
+
+    /// function typeConversion.bigIntToHex(n: Uint8Array): string
+    fn big_int_to_hex(
+        &mut self,
+        big_int_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let n: BigInt = asc_get(self, big_int_ptr)?;
+        let hex = self.ctx.host_exports.big_int_to_hex(n)?;
+        asc_new(self, &hex)
+    }
+
+    /// function typeConversion.stringToH160(s: String): H160
     fn string_to_h160(
         &mut self,
         str_ptr: AscPtr<AscString>,
@@ -966,10 +1610,17 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         }
 
         let link = asc_get(self, link_ptr)?;
-        let ipfs_res = self.ctx.host_exports.ipfs_cat(&self.ctx.logger, link);
+        let deadline = self.handler_deadline();
+        let ipfs_res = self
+            .ctx
+            .host_exports
+            .ipfs_cat(&self.ctx.logger, link, None, deadline);
         match ipfs_res {
             Ok(bytes) => asc_new(self, &*bytes).map_err(Into::into),
 
+            // The handler is out of time, returning `null` would only hide that.
+            Err(e) if DeadlineExceeded::caused(&e) => Err(HostExportError::Unknown(e)),
+
             // Return null in case of error.
             Err(e) => {
                 info!(&self.ctx.logger, "Failed ipfs.cat, returning `null`";
@@ -980,6 +1631,128 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         }
     }
 
+    /// function ipfs.catFromGateway(link: String, gateway: String): Bytes | null
+    fn ipfs_cat_from_gateway(
+        &mut self,
+        link_ptr: AscPtr<AscString>,
+        gateway_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        if !self.experimental_features.allow_non_deterministic_ipfs {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "`ipfs.catFromGateway` is not supported. Improved support for IPFS will be added in the future"
+            )));
+        }
+
+        let link = asc_get(self, link_ptr)?;
+        let gateway: String = asc_get(self, gateway_ptr)?;
+        let deadline = self.handler_deadline();
+        let ipfs_res =
+            self.ctx
+                .host_exports
+                .ipfs_cat(&self.ctx.logger, link, Some(&gateway), deadline);
+        match ipfs_res {
+            Ok(bytes) => asc_new(self, &*bytes).map_err(Into::into),
+
+            // The handler is out of time, returning `null` would only hide that.
+            Err(e) if DeadlineExceeded::caused(&e) => Err(HostExportError::Unknown(e)),
+
+            // Return null in case of error.
+            Err(e) => {
+                info!(&self.ctx.logger, "Failed ipfs.catFromGateway, returning `null`";
+                                    "link" => asc_get::<String, _, _>(self, link_ptr)?,
+                                    "gateway" => gateway,
+                                    "error" => e.to_string());
+                Ok(AscPtr::null())
+            }
+        }
+    }
+
+    /// function ipfs.catRequired(link: String): Bytes
+    fn ipfs_cat_required(
+        &mut self,
+        link_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        if !self.experimental_features.allow_non_deterministic_ipfs {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "`ipfs.catRequired` is not supported. Improved support for IPFS will be added in the future"
+            )));
+        }
+
+        let link = asc_get(self, link_ptr)?;
+        let deadline = self.handler_deadline();
+        let bytes = self
+            .ctx
+            .host_exports
+            .ipfs_cat_required(&self.ctx.logger, link, deadline)?;
+        asc_new(self, &*bytes).map_err(Into::into)
+    }
+
+    /// function ipfs.catWithContentType(link: String): IpfsFile | null
+    fn ipfs_cat_with_content_type(
+        &mut self,
+        link_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscIpfsFile>, HostExportError> {
+        if !self.experimental_features.allow_non_deterministic_ipfs {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "`ipfs.catWithContentType` is not supported. Improved support for IPFS will be added in the future"
+            )));
+        }
+
+        let link = asc_get(self, link_ptr)?;
+        let deadline = self.handler_deadline();
+        let ipfs_res =
+            self.ctx
+                .host_exports
+                .ipfs_cat_with_content_type(&self.ctx.logger, link, deadline);
+        match ipfs_res {
+            Ok(file) => asc_new(self, &file).map_err(Into::into),
+
+            // The handler is out of time, returning `null` would only hide that.
+            Err(e) if DeadlineExceeded::caused(&e) => Err(HostExportError::Unknown(e)),
+
+            // Return null in case of error.
+            Err(e) => {
+                info!(&self.ctx.logger, "Failed ipfs.catWithContentType, returning `null`";
+                                    "link" => asc_get::<String, _, _>(self, link_ptr)?,
+                                    "error" => e.to_string());
+                Ok(AscPtr::null())
+            }
+        }
+    }
+
+    /// function ipfs.ls(link: String): Array<IpfsLink> | null
+    fn ipfs_ls(
+        &mut self,
+        link_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Array<AscPtr<AscIpfsLink>>>, HostExportError> {
+        if !self.experimental_features.allow_non_deterministic_ipfs {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "`ipfs.ls` is not supported. Improved support for IPFS will be added in the future"
+            )));
+        }
+
+        let link = asc_get(self, link_ptr)?;
+        let deadline = self.handler_deadline();
+        let ipfs_res = self
+            .ctx
+            .host_exports
+            .ipfs_ls(&self.ctx.logger, link, deadline);
+        match ipfs_res {
+            Ok(entries) => asc_new(self, entries.as_slice()).map_err(Into::into),
+
+            // The handler is out of time, returning `null` would only hide that.
+            Err(e) if DeadlineExceeded::caused(&e) => Err(HostExportError::Unknown(e)),
+
+            // Return null in case of error.
+            Err(e) => {
+                info!(&self.ctx.logger, "Failed ipfs.ls, returning `null`";
+                                    "link" => asc_get::<String, _, _>(self, link_ptr)?,
+                                    "error" => e.to_string());
+                Ok(AscPtr::null())
+            }
+        }
+    }
+
     /// function ipfs.map(link: String, callback: String, flags: String[]): void
     fn ipfs_map(
         &mut self,
@@ -987,6 +1760,18 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         callback: AscPtr<AscString>,
         user_data: AscPtr<AscEnum<StoreValueKind>>,
         flags: AscPtr<Array<AscPtr<AscString>>>,
+    ) -> Result<(), HostExportError> {
+        self.ipfs_map_from(link_ptr, callback, user_data, flags, 0)
+    }
+
+    /// function ipfs.mapFrom(link: String, callback: String, userData: Value, flags: String[], startLine: u32): void
+    fn ipfs_map_from(
+        &mut self,
+        link_ptr: AscPtr<AscString>,
+        callback: AscPtr<AscString>,
+        user_data: AscPtr<AscEnum<StoreValueKind>>,
+        flags: AscPtr<Array<AscPtr<AscString>>>,
+        start_line: u32,
     ) -> Result<(), HostExportError> {
         if !self.experimental_features.allow_non_deterministic_ipfs {
             return Err(HostExportError::Deterministic(anyhow!(
@@ -1013,6 +1798,8 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             &*callback,
             user_data,
             flags,
+            self.ctx.host_exports.ipfs_map_concurrency,
+            start_line as usize,
         )?;
 
         debug!(
@@ -1030,6 +1817,63 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         Ok(())
     }
 
+    /// function json.canonicalize(value: JSONValue): Bytes
+    fn json_canonicalize(
+        &mut self,
+        value_ptr: AscPtr<AscEnum<JsonValueKind>>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let value: serde_json::Value = try_asc_get(self, value_ptr)?;
+        let bytes = host_exports::json_canonicalize(&value)?;
+        asc_new(self, bytes.as_slice())
+    }
+
+    /// function json.stringify(value: JSONValue, pretty: bool): string
+    fn json_stringify(
+        &mut self,
+        value_ptr: AscPtr<AscEnum<JsonValueKind>>,
+        pretty: u32,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let value: serde_json::Value = try_asc_get(self, value_ptr)?;
+        let string = host_exports::json_stringify(&value, pretty != 0)?;
+        asc_new(self, &string)
+    }
+
+    /// function json.getField(value: JSONValue, path: string): JSONValue | null
+    fn json_get_field(
+        &mut self,
+        value_ptr: AscPtr<AscEnum<JsonValueKind>>,
+        path_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscEnum<JsonValueKind>>, DeterministicHostError> {
+        let value: serde_json::Value = try_asc_get(self, value_ptr)?;
+        let path: String = asc_get(self, path_ptr)?;
+        match host_exports::json_get_field(&value, &path)? {
+            Some(field) => asc_new(self, &field),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function json.merge(base: JSONValue, overlay: JSONValue): JSONValue
+    fn json_merge(
+        &mut self,
+        base_ptr: AscPtr<AscEnum<JsonValueKind>>,
+        overlay_ptr: AscPtr<AscEnum<JsonValueKind>>,
+    ) -> Result<AscPtr<AscEnum<JsonValueKind>>, DeterministicHostError> {
+        let base: serde_json::Value = try_asc_get(self, base_ptr)?;
+        let overlay: serde_json::Value = try_asc_get(self, overlay_ptr)?;
+        let merged = host_exports::json_merge(&base, &overlay)?;
+        asc_new(self, &merged)
+    }
+
+    /// function json.typeOf(value: JSONValue): string
+    fn json_type_of(
+        &mut self,
+        value_ptr: AscPtr<AscEnum<JsonValueKind>>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let value: serde_json::Value = try_asc_get(self, value_ptr)?;
+        let name = host_exports::json_type_of(&value)?;
+        asc_new(self, &name)
+    }
+
     /// Expects a decimal string.
     /// function json.toI64(json: String): i64
     fn json_to_i64(&mut self, json_ptr: AscPtr<AscString>) -> Result<i64, DeterministicHostError> {
@@ -1061,6 +1905,18 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &*big_int)
     }
 
+    /// function json.toBigDecimal(json: String): BigDecimal
+    fn json_to_big_decimal(
+        &mut self,
+        json_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let big_decimal = self
+            .ctx
+            .host_exports
+            .json_to_big_decimal(asc_get(self, json_ptr)?)?;
+        asc_new(self, &big_decimal)
+    }
+
     /// function crypto.keccak256(input: Bytes): Bytes
     fn crypto_keccak_256(
         &mut self,
@@ -1073,6 +1929,154 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, input.as_ref())
     }
 
+    /// function crypto.keccak256Init(): u32
+    fn crypto_keccak_256_init(&mut self) -> Result<u32, DeterministicHostError> {
+        self.ctx.host_exports.keccak_init(&mut self.keccak_hashers)
+    }
+
+    /// function crypto.keccak256Update(handle: u32, chunk: Bytes): void
+    fn crypto_keccak_256_update(
+        &mut self,
+        handle: u32,
+        chunk_ptr: AscPtr<Uint8Array>,
+    ) -> Result<(), DeterministicHostError> {
+        let chunk = asc_get(self, chunk_ptr)?;
+        self.ctx
+            .host_exports
+            .keccak_update(&mut self.keccak_hashers, handle, chunk)
+    }
+
+    /// function crypto.keccak256Finalize(handle: u32): Bytes
+    fn crypto_keccak_256_finalize(
+        &mut self,
+        handle: u32,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let hash = self
+            .ctx
+            .host_exports
+            .keccak_finalize(&mut self.keccak_hashers, handle)?;
+        asc_new(self, hash.as_ref())
+    }
+
+    /// function random.deterministic(): Bytes
+    fn deterministic_random(&mut self) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let bytes = self.ctx.host_exports.deterministic_random(
+            &self.ctx.block_ptr,
+            &self.random_seed,
+            self.random_count,
+        )?;
+        self.random_count += 1;
+        asc_new(self, bytes.as_ref())
+    }
+
+    /// function random.bigIntBelow(n: BigInt): BigInt
+    fn big_int_random_below(
+        &mut self,
+        n_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let n = asc_get(self, n_ptr)?;
+        let result = self.ctx.host_exports.big_int_random_below(
+            &self.ctx.block_ptr,
+            &self.random_seed,
+            &mut self.random_count,
+            n,
+        )?;
+        asc_new(self, &result)
+    }
+
+    /// function crypto.keccak256String(input: string): Bytes
+    fn crypto_keccak_256_string(
+        &mut self,
+        input_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let input = self
+            .ctx
+            .host_exports
+            .crypto_keccak_256_string(asc_get(self, input_ptr)?)?;
+        asc_new(self, input.as_ref())
+    }
+
+    /// function crypto.makeEntityId(parts: Array<Bytes>): string
+    fn make_entity_id(
+        &mut self,
+        parts_ptr: AscPtr<Array<AscPtr<Uint8Array>>>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let id = self
+            .ctx
+            .host_exports
+            .make_entity_id(asc_get(self, parts_ptr)?)?;
+        asc_new(self, &id)
+    }
+
+    /// function ethereum.verifyMerkleProof(leaf: Bytes, proof: Array<Bytes>, root: Bytes): bool
+    fn ethereum_verify_merkle_proof(
+        &mut self,
+        leaf_ptr: AscPtr<Uint8Array>,
+        proof_ptr: AscPtr<Array<AscPtr<Uint8Array>>>,
+        root_ptr: AscPtr<Uint8Array>,
+    ) -> Result<bool, DeterministicHostError> {
+        let leaf = asc_get(self, leaf_ptr)?;
+        let proof = asc_get(self, proof_ptr)?;
+        let root = asc_get(self, root_ptr)?;
+        self.ctx
+            .host_exports
+            .ethereum_verify_merkle_proof(leaf, proof, root)
+    }
+
+    /// function ethereum.eventByTopic0(topic0: Bytes): string | null
+    fn ethereum_event_by_topic0(
+        &mut self,
+        topic0_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        match self
+            .ctx
+            .host_exports
+            .ethereum_event_by_topic0(asc_get(self, topic0_ptr)?)?
+        {
+            Some(signature) => asc_new(self, &signature),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function ethereum.functionSelector(signature: string): Bytes
+    fn ethereum_function_selector(
+        &mut self,
+        signature_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let selector = self
+            .ctx
+            .host_exports
+            .ethereum_function_selector(asc_get(self, signature_ptr)?)?;
+        asc_new(self, selector.as_ref())
+    }
+
+    /// function crypto.verifyEd25519(message: Bytes, signature: Bytes, publicKey: Bytes): bool
+    fn crypto_verify_ed25519(
+        &mut self,
+        message_ptr: AscPtr<Uint8Array>,
+        signature_ptr: AscPtr<Uint8Array>,
+        public_key_ptr: AscPtr<Uint8Array>,
+    ) -> Result<bool, DeterministicHostError> {
+        self.ctx.host_exports.crypto_verify_ed25519(
+            asc_get(self, message_ptr)?,
+            asc_get(self, signature_ptr)?,
+            asc_get(self, public_key_ptr)?,
+        )
+    }
+
+    /// function crypto.hmacSha256(key: Bytes, message: Bytes): Bytes
+    fn crypto_hmac_sha256(
+        &mut self,
+        key_ptr: AscPtr<Uint8Array>,
+        message_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let output = self
+            .ctx
+            .host_exports
+            .crypto_hmac_sha256(asc_get(self, key_ptr)?, asc_get(self, message_ptr)?)?;
+        asc_new(self, output.as_ref())
+    }
+
     /// function bigInt.plus(x: BigInt, y: BigInt): BigInt
     fn big_int_plus(
         &mut self,
@@ -1166,6 +2170,23 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &result)
     }
 
+    /// function bigInt.powMod(base: BigInt, exponent: BigInt, modulus: BigInt): BigInt
+    fn big_int_pow_mod(
+        &mut self,
+        base_ptr: AscPtr<AscBigInt>,
+        exponent_ptr: AscPtr<AscBigInt>,
+        modulus_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let base = asc_get(self, base_ptr)?;
+        let exponent = asc_get(self, exponent_ptr)?;
+        let modulus = asc_get(self, modulus_ptr)?;
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_pow_mod(base, exponent, modulus)?;
+        asc_new(self, &result)
+    }
+
     /// function bigInt.bitOr(x: BigInt, y: BigInt): BigInt
     fn big_int_bit_or(
         &mut self,
@@ -1232,40 +2253,240 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &result)
     }
 
-    /// function bigDecimal.toString(x: BigDecimal): string
-    fn big_decimal_to_string(
+    /// function typeConversion.bytesToBase32(bytes: Bytes): string
+    fn bytes_to_base32(
         &mut self,
-        big_decimal_ptr: AscPtr<AscBigDecimal>,
+        bytes_ptr: AscPtr<Uint8Array>,
     ) -> Result<AscPtr<AscString>, DeterministicHostError> {
         let result = self
             .ctx
             .host_exports
-            .big_decimal_to_string(try_asc_get(self, big_decimal_ptr)?)?;
+            .bytes_to_base32(asc_get(self, bytes_ptr)?)?;
         asc_new(self, &result)
     }
 
-    /// function bigDecimal.fromString(x: string): BigDecimal
-    fn big_decimal_from_string(
+    /// function typeConversion.base32ToBytes(s: string): Bytes
+    fn base32_to_bytes(
         &mut self,
-        string_ptr: AscPtr<AscString>,
-    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        str_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
         let result = self
             .ctx
             .host_exports
-            .big_decimal_from_string(asc_get(self, string_ptr)?)?;
-        asc_new(self, &result)
+            .base32_to_bytes(asc_get(self, str_ptr)?)?;
+        asc_new(self, result.as_slice())
     }
 
-    /// function bigDecimal.plus(x: BigDecimal, y: BigDecimal): BigDecimal
-    fn big_decimal_plus(
-        &mut self,
-        x_ptr: AscPtr<AscBigDecimal>,
-        y_ptr: AscPtr<AscBigDecimal>,
-    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
-        let result = self
-            .ctx
-            .host_exports
-            .big_decimal_plus(try_asc_get(self, x_ptr)?, try_asc_get(self, y_ptr)?)?;
+    /// function bigInt.fromU64(n: u64): BigInt
+    fn big_int_from_u64(&mut self, n: u64) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self.ctx.host_exports.big_int_from_u64(n)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigInt.fromI64(n: i64): BigInt
+    fn big_int_from_i64(&mut self, n: i64) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self.ctx.host_exports.big_int_from_i64(n)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigInt.bitLength(x: BigInt): u32
+    fn big_int_bit_length(
+        &mut self,
+        x_ptr: AscPtr<AscBigInt>,
+    ) -> Result<u32, DeterministicHostError> {
+        self.ctx
+            .host_exports
+            .big_int_bit_length(asc_get(self, x_ptr)?)
+    }
+
+    /// function bigInt.countOnes(x: BigInt): u32
+    fn big_int_count_ones(
+        &mut self,
+        x_ptr: AscPtr<AscBigInt>,
+    ) -> Result<u32, DeterministicHostError> {
+        self.ctx
+            .host_exports
+            .big_int_count_ones(asc_get(self, x_ptr)?)
+    }
+
+    /// function bigInt.toF64(x: BigInt): f64
+    fn big_int_to_f64(&mut self, x_ptr: AscPtr<AscBigInt>) -> Result<f64, DeterministicHostError> {
+        self.ctx.host_exports.big_int_to_f64(asc_get(self, x_ptr)?)
+    }
+
+    /// function bigInt.min(x: BigInt, y: BigInt): BigInt
+    fn big_int_min(
+        &mut self,
+        x_ptr: AscPtr<AscBigInt>,
+        y_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_min(asc_get(self, x_ptr)?, asc_get(self, y_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigInt.max(x: BigInt, y: BigInt): BigInt
+    fn big_int_max(
+        &mut self,
+        x_ptr: AscPtr<AscBigInt>,
+        y_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_max(asc_get(self, x_ptr)?, asc_get(self, y_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bytes.reverse(bytes: Bytes): Bytes
+    fn bytes_reverse(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .bytes_reverse(asc_get(self, bytes_ptr)?)?;
+        asc_new(self, result.as_slice())
+    }
+
+    /// function bytes.slice(bytes: Bytes, start: u32, end: u32): Bytes
+    fn bytes_slice(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+        start: u32,
+        end: u32,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .bytes_slice(asc_get(self, bytes_ptr)?, start, end)?;
+        asc_new(self, result.as_slice())
+    }
+
+    /// function bytes.equals(a: Bytes, b: Bytes): bool
+    fn bytes_equal(
+        &mut self,
+        a_ptr: AscPtr<Uint8Array>,
+        b_ptr: AscPtr<Uint8Array>,
+    ) -> Result<bool, DeterministicHostError> {
+        self.ctx
+            .host_exports
+            .bytes_equal(asc_get(self, a_ptr)?, asc_get(self, b_ptr)?)
+    }
+
+    /// function bytes.padLeft(bytes: Bytes, length: u32): Bytes
+    fn bytes_pad_left(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+        length: u32,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .bytes_pad_left(asc_get(self, bytes_ptr)?, length)?;
+        asc_new(self, result.as_slice())
+    }
+
+    /// function bytes.padRight(bytes: Bytes, length: u32): Bytes
+    fn bytes_pad_right(
+        &mut self,
+        bytes_ptr: AscPtr<Uint8Array>,
+        length: u32,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .bytes_pad_right(asc_get(self, bytes_ptr)?, length)?;
+        asc_new(self, result.as_slice())
+    }
+
+    /// function string.split(s: string, delimiter: string): Array<string>
+    fn string_split(
+        &mut self,
+        s_ptr: AscPtr<AscString>,
+        delimiter_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Array<AscPtr<AscString>>>, DeterministicHostError> {
+        let parts = self
+            .ctx
+            .host_exports
+            .string_split(asc_get(self, s_ptr)?, asc_get(self, delimiter_ptr)?)?;
+        asc_new(self, parts.as_slice())
+    }
+
+    /// function string.toLower(s: string): string
+    fn string_to_lower(
+        &mut self,
+        s_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let s = self
+            .ctx
+            .host_exports
+            .string_to_lower(asc_get(self, s_ptr)?)?;
+        asc_new(self, &s)
+    }
+
+    /// function string.toUpper(s: string): string
+    fn string_to_upper(
+        &mut self,
+        s_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let s = self
+            .ctx
+            .host_exports
+            .string_to_upper(asc_get(self, s_ptr)?)?;
+        asc_new(self, &s)
+    }
+
+    /// function bigDecimal.toString(x: BigDecimal): string
+    fn big_decimal_to_string(
+        &mut self,
+        big_decimal_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_to_string(try_asc_get(self, big_decimal_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.fromString(x: string): BigDecimal
+    fn big_decimal_from_string(
+        &mut self,
+        string_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_from_string(asc_get(self, string_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.fromBigInt(digits: BigInt, exp: i32): BigDecimal
+    fn big_decimal_from_big_int(
+        &mut self,
+        digits_ptr: AscPtr<AscBigInt>,
+        exp: i32,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_from_big_int(asc_get(self, digits_ptr)?, exp)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.plus(x: BigDecimal, y: BigDecimal): BigDecimal
+    fn big_decimal_plus(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        y_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_plus(try_asc_get(self, x_ptr)?, try_asc_get(self, y_ptr)?)?;
         asc_new(self, &result)
     }
 
@@ -1308,6 +2529,34 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &result)
     }
 
+    /// function bigDecimal.dividedByWithPrecision(x: BigDecimal, y: BigDecimal, precision: u32): BigDecimal
+    fn big_decimal_divided_by_with_precision(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        y_ptr: AscPtr<AscBigDecimal>,
+        precision: u32,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let x = try_asc_get(self, x_ptr)?;
+        let y = try_asc_get(self, y_ptr)?;
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_divided_by_with_precision(x, y, precision)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.toBigInt(x: BigDecimal, mode: RoundingMode): BigInt
+    fn big_decimal_to_big_int(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        mode: u32,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let x = try_asc_get(self, x_ptr)?;
+        let mode = RoundingMode::try_from(mode)?;
+        let result = self.ctx.host_exports.big_decimal_to_big_int(x, mode)?;
+        asc_new(self, &result)
+    }
+
     /// function bigDecimal.equals(x: BigDecimal, y: BigDecimal): bool
     fn big_decimal_equals(
         &mut self,
@@ -1319,6 +2568,101 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             .big_decimal_equals(try_asc_get(self, x_ptr)?, try_asc_get(self, y_ptr)?)
     }
 
+    /// function bigDecimal.scale(x: BigDecimal): i64
+    fn big_decimal_scale(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<i64, DeterministicHostError> {
+        self.ctx
+            .host_exports
+            .big_decimal_scale(try_asc_get(self, x_ptr)?)
+    }
+
+    /// function bigDecimal.toF64(x: BigDecimal): f64
+    fn big_decimal_to_f64(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<f64, DeterministicHostError> {
+        self.ctx
+            .host_exports
+            .big_decimal_to_f64(try_asc_get(self, x_ptr)?)
+    }
+
+    /// function bigDecimal.withScale(x: BigDecimal, scale: i64): BigDecimal
+    fn big_decimal_with_scale(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        scale: i64,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_with_scale(try_asc_get(self, x_ptr)?, scale)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.sqrt(x: BigDecimal): BigDecimal
+    fn big_decimal_sqrt(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_sqrt(try_asc_get(self, x_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.abs(x: BigDecimal): BigDecimal
+    fn big_decimal_abs(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_abs(try_asc_get(self, x_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.neg(x: BigDecimal): BigDecimal
+    fn big_decimal_neg(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_neg(try_asc_get(self, x_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.min(x: BigDecimal, y: BigDecimal): BigDecimal
+    fn big_decimal_min(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        y_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_min(try_asc_get(self, x_ptr)?, try_asc_get(self, y_ptr)?)?;
+        asc_new(self, &result)
+    }
+
+    /// function bigDecimal.max(x: BigDecimal, y: BigDecimal): BigDecimal
+    fn big_decimal_max(
+        &mut self,
+        x_ptr: AscPtr<AscBigDecimal>,
+        y_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, DeterministicHostError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_max(try_asc_get(self, x_ptr)?, try_asc_get(self, y_ptr)?)?;
+        asc_new(self, &result)
+    }
+
     /// function dataSource.create(name: string, params: Array<string>): void
     fn data_source_create(
         &mut self,
@@ -1357,27 +2701,176 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         )
     }
 
+    /// function dataSource.createWithEntity(name: string, params: Array<string>, entity: string, id: string, data: Entity): void
+    fn data_source_create_with_entity(
+        &mut self,
+        name_ptr: AscPtr<AscString>,
+        params_ptr: AscPtr<Array<AscPtr<AscString>>>,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+        data_ptr: AscPtr<AscEntity>,
+    ) -> Result<(), HostExportError> {
+        let name: String = asc_get(self, name_ptr)?;
+        let params: Vec<String> = asc_get(self, params_ptr)?;
+        let entity = asc_get(self, entity_ptr)?;
+        let id = asc_get(self, id_ptr)?;
+        let data = try_asc_get(self, data_ptr)?;
+        self.ctx.host_exports.data_source_create_with_entity(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            self.data_origin,
+            name,
+            params,
+            None,
+            self.ctx.block_ptr.number,
+            entity,
+            id,
+            data,
+            self.block_timestamp.as_ref(),
+            &self.host_metrics.stopwatch,
+        )
+    }
+
     /// function dataSource.address(): Bytes
     fn data_source_address(&mut self) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
         asc_new(self, &self.ctx.host_exports.data_source_address())
     }
 
+    /// function dataSource.addressOrNull(): Bytes | null
+    fn data_source_address_or_null(
+        &mut self,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        match self.ctx.host_exports.data_source_address_opt()? {
+            Some(address) => asc_new(self, &address),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function dataSource.templateNames(): Array<string>
+    fn data_source_template_names(
+        &mut self,
+    ) -> Result<AscPtr<Array<AscPtr<AscString>>>, DeterministicHostError> {
+        let names = self.ctx.host_exports.data_source_template_names()?;
+        asc_new(self, names.as_slice())
+    }
+
+    /// function dataSource.templateKind(name: string): string
+    fn data_source_template_kind(
+        &mut self,
+        name_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let kind = self
+            .ctx
+            .host_exports
+            .data_source_template_kind(asc_get(self, name_ptr)?)?;
+        asc_new(self, &kind)
+    }
+
+    /// function dataSource.triggerKind(): string
+    fn data_source_trigger_kind(&mut self) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let kind = self.ctx.host_exports.trigger_kind(self.trigger_kind)?;
+        asc_new(self, &kind)
+    }
+
     /// function dataSource.network(): String
     fn data_source_network(&mut self) -> Result<AscPtr<AscString>, DeterministicHostError> {
         asc_new(self, &self.ctx.host_exports.data_source_network())
     }
 
+    /// function dataSource.subgraphId(): String
+    fn data_source_subgraph_id(&mut self) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let subgraph_id = self.ctx.host_exports.subgraph_id()?;
+        asc_new(self, &subgraph_id)
+    }
+
+    /// function dataSource.chainId(): u64
+    fn data_source_chain_id(&mut self) -> Result<u64, DeterministicHostError> {
+        self.ctx.host_exports.data_source_chain_id()
+    }
+
     /// function dataSource.context(): DataSourceContext
     fn data_source_context(&mut self) -> Result<AscPtr<AscEntity>, DeterministicHostError> {
         asc_new(self, &self.ctx.host_exports.data_source_context().sorted())
     }
 
+    /// function dataSource.contextString(field: string): string | null
+    fn data_source_context_string(
+        &mut self,
+        field_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscString>, DeterministicHostError> {
+        let field: String = asc_get(self, field_ptr)?;
+        match self.ctx.host_exports.data_source_context_string(&field)? {
+            Some(s) => asc_new(self, &s),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function dataSource.contextBytes(field: string): Bytes | null
+    fn data_source_context_bytes(
+        &mut self,
+        field_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let field: String = asc_get(self, field_ptr)?;
+        match self.ctx.host_exports.data_source_context_bytes(&field)? {
+            Some(bytes) => asc_new(self, bytes.as_slice()),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function dataSource.contextBigInt(field: string): BigInt | null
+    fn data_source_context_big_int(
+        &mut self,
+        field_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let field: String = asc_get(self, field_ptr)?;
+        match self.ctx.host_exports.data_source_context_big_int(&field)? {
+            Some(n) => asc_new(self, &n),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
+    /// function block.number(): i32
+    fn block_number(&mut self) -> Result<i32, DeterministicHostError> {
+        let (number, _) = self
+            .ctx
+            .host_exports
+            .current_block_ptr(&self.ctx.block_ptr)?;
+        Ok(number)
+    }
+
+    /// function block.hash(): Bytes
+    fn block_hash(&mut self) -> Result<AscPtr<AscH256>, DeterministicHostError> {
+        let (_, hash) = self
+            .ctx
+            .host_exports
+            .current_block_ptr(&self.ctx.block_ptr)?;
+        asc_new(self, &hash)
+    }
+
+    /// function block.nextSequence(): u64
+    fn next_sequence(&mut self) -> Result<u64, DeterministicHostError> {
+        self.ctx.host_exports.next_sequence(&mut self.ctx.state)
+    }
+
+    /// function block.timestamp(): BigInt
+    fn block_timestamp(&mut self) -> Result<AscPtr<AscBigInt>, DeterministicHostError> {
+        let timestamp = self
+            .ctx
+            .host_exports
+            .block_timestamp(self.block_timestamp.as_ref())?;
+        asc_new(self, &timestamp)
+    }
+
     fn ens_name_by_hash(
         &mut self,
         hash_ptr: AscPtr<AscString>,
     ) -> Result<AscPtr<AscString>, HostExportError> {
         let hash: String = asc_get(self, hash_ptr)?;
-        let name = self.ctx.host_exports.ens_name_by_hash(&*hash)?;
+        let name = self
+            .ctx
+            .host_exports
+            .ens_name_by_hash(&*hash, &mut self.ens_name_memo)?;
         // map `None` to `null`, and `Some(s)` to a runtime string
         name.map(|name| asc_new(self, &*name).map_err(Into::into))
             .unwrap_or(Ok(AscPtr::null()))
@@ -1390,7 +2883,9 @@ impl<C: Blockchain> WasmInstanceContext<C> {
     ) -> Result<(), DeterministicHostError> {
         let level = LogLevel::from(level).into();
         let msg: String = asc_get(self, msg)?;
-        self.ctx.host_exports.log_log(&self.ctx.logger, level, msg)
+        self.ctx
+            .host_exports
+            .log_log(&self.ctx.logger, level, msg, &mut self.log_count)
     }
 
     /// function encode(token: ethereum.Value): Bytes | null
@@ -1404,14 +2899,44 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             .unwrap_or(Ok(AscPtr::null()))
     }
 
+    /// function ethereum.encodeArray(elementType: string, tokens: Array<ethereum.Value>): Bytes
+    fn ethereum_encode_array(
+        &mut self,
+        element_type_ptr: AscPtr<AscString>,
+        tokens_ptr: AscPtr<Array<AscPtr<AscEnum<EthereumValueKind>>>>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let data = host_exports::ethereum_encode_array(
+            asc_get(self, element_type_ptr)?,
+            asc_get(self, tokens_ptr)?,
+        )?;
+        asc_new(self, data.as_slice())
+    }
+
+    /// function ethereum.hashEncoded(tokens: Array<ethereum.Value>): Bytes
+    fn ethereum_hash_encoded(
+        &mut self,
+        tokens_ptr: AscPtr<Array<AscPtr<AscEnum<EthereumValueKind>>>>,
+    ) -> Result<AscPtr<Uint8Array>, DeterministicHostError> {
+        let hash = host_exports::ethereum_hash_encoded(asc_get(self, tokens_ptr)?)?;
+        asc_new(self, hash.as_ref())
+    }
+
     /// function decode(types: String, data: Bytes): ethereum.Value | null
     fn ethereum_decode(
         &mut self,
         types_ptr: AscPtr<AscString>,
         data_ptr: AscPtr<Uint8Array>,
     ) -> Result<AscPtr<AscEnum<EthereumValueKind>>, DeterministicHostError> {
-        let result =
-            host_exports::ethereum_decode(asc_get(self, types_ptr)?, asc_get(self, data_ptr)?);
+        let types: String = asc_get(self, types_ptr)?;
+        let data: Vec<u8> = asc_get(self, data_ptr)?;
+        let result = host_exports::ethereum_decode(
+            types.clone(),
+            data.clone(),
+            self.ctx.host_exports.max_ethereum_decode_bytes,
+        );
+        if let (true, Ok(token)) = (self.experimental_features.check_ethereum_decode, &result) {
+            host_exports::check_ethereum_decode(&self.ctx.logger, &types, &data, token);
+        }
         // return `null` if it fails
         result
             .map(|param| asc_new(self, &param))
@@ -1,20 +1,32 @@
+use atomic_refcell::AtomicRefCell;
 use ethabi::{Contract, Token};
 use hex;
 use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
-
-use crate::host_exports::HostExports;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::host_exports::{
+    ArweaveCache, ContractCallFilter, DataOrigin, EntityStatus, EthereumCallError, HostExports,
+    HostExportsBuilder, IPFS_CAUSALITY_REGION,
+};
+use graph::bytes::Bytes;
+use graph::components::arweave::ArweaveAdapter as ArweaveAdapterTrait;
+use graph::components::subgraph::{ProofOfIndexing, ProofOfIndexingEvent};
 use graph::data::store::scalar;
 use graph::data::subgraph::*;
+use graph::prelude::futures03::{future::BoxFuture, FutureExt};
 use graph::{components::store::*, ipfs_client::IpfsClient};
 use graph_chain_arweave::adapter::ArweaveAdapter;
-use graph_chain_ethereum::{Chain, DataSource, DataSourceTemplate, MockEthereumAdapter};
+use graph_chain_ethereum::{
+    Chain, DataSource, DataSourceTemplate, EthereumAccountOverride, EthereumAdapterTrait,
+    EthereumContractCallError, EthereumStateOverrides, MockEthereumAdapter,
+};
 use graph_core;
 use graph_core::three_box::ThreeBoxAdapter;
 use graph_mock::MockMetricsRegistry;
 use test_store::{NETWORK_NAME, STORE};
 
-use web3::types::{Address, H160};
+use web3::types::{Address, H160, H256};
 
 use super::*;
 
@@ -45,7 +57,6 @@ fn test_valid_module_and_store_with_timeout(
         .block_store()
         .ethereum_call_cache(NETWORK_NAME)
         .expect("call cache for test network");
-    let metrics_registry = Arc::new(MockMetricsRegistry::new());
     let deployment_id = DeploymentHash::new(subgraph_id).unwrap();
     let deployment = test_store::create_test_subgraph(
         &deployment_id,
@@ -58,23 +69,45 @@ fn test_valid_module_and_store_with_timeout(
             id: ID!,
             value: String,
             extra: String
+        }
+
+        type Counter @entity {
+            id: ID!,
+            count: BigInt,
+            total: BigDecimal,
+            name: String,
+            updatedAt: BigInt
+        }
+
+        type Post @entity {
+            id: ID!,
+            comments: [Comment!]! @derivedFrom(field: \"post\")
+        }
+
+        type Comment @entity {
+            id: ID!,
+            post: Post!,
+            replies: [Reply!]! @derivedFrom(field: \"comment\")
+        }
+
+        type Reply @entity {
+            id: ID!,
+            comment: Comment!
+        }
+
+        type Token @entity {
+            id: ID!,
+            symbol: String @unique
         }",
     );
-    let stopwatch_metrics = StopwatchMetrics::new(
-        Logger::root(slog::Discard, o!()),
-        deployment_id.clone(),
-        metrics_registry.clone(),
-    );
-    let host_metrics = Arc::new(HostMetrics::new(
-        metrics_registry,
-        deployment_id.as_str(),
-        stopwatch_metrics,
-    ));
+    let host_metrics = Arc::new(test_host_metrics(&deployment_id));
 
     let experimental_features = ExperimentalFeatures {
         allow_non_deterministic_ipfs: true,
         allow_non_deterministic_arweave: true,
         allow_non_deterministic_3box: true,
+        allow_custom_poi_events: true,
+        check_ethereum_decode: true,
     };
 
     let module = WasmInstance::from_valid_module_with_ctx(
@@ -94,6 +127,16 @@ fn test_valid_module_and_store_with_timeout(
     (module, store.subgraph_store(), deployment)
 }
 
+fn test_host_metrics(deployment_id: &DeploymentHash) -> HostMetrics {
+    let metrics_registry = Arc::new(MockMetricsRegistry::new());
+    let stopwatch_metrics = StopwatchMetrics::new(
+        Logger::root(slog::Discard, o!()),
+        deployment_id.clone(),
+        metrics_registry.clone(),
+    );
+    HostMetrics::new(metrics_registry, deployment_id.as_str(), stopwatch_metrics)
+}
+
 fn test_module(subgraph_id: &str, data_source: DataSource) -> WasmInstance<Chain> {
     test_valid_module_and_store(subgraph_id, data_source).0
 }
@@ -157,8 +200,24 @@ fn mock_host_exports(
     store: Arc<impl SubgraphStore>,
     call_cache: Arc<impl EthereumCallCache>,
 ) -> HostExports<Chain> {
-    let mock_ethereum_adapter = Arc::new(MockEthereumAdapter::default());
-    let arweave_adapter = Arc::new(ArweaveAdapter::new("https://arweave.net".to_string()));
+    mock_host_exports_with_adapters(
+        subgraph_id,
+        data_source,
+        store,
+        call_cache,
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    )
+}
+
+fn mock_host_exports_with_adapters(
+    subgraph_id: DeploymentHash,
+    data_source: DataSource,
+    store: Arc<impl SubgraphStore>,
+    call_cache: Arc<impl EthereumCallCache>,
+    ethereum_adapter: Arc<dyn EthereumAdapterTrait>,
+    arweave_adapter: Arc<dyn ArweaveAdapterTrait>,
+) -> HostExports<Chain> {
     let three_box_adapter = Arc::new(ThreeBoxAdapter::new("https://ipfs.3box.io/".to_string()));
 
     let templates = vec![DataSourceTemplate {
@@ -190,11 +249,12 @@ fn mock_host_exports(
         &data_source,
         network,
         Arc::new(templates),
-        mock_ethereum_adapter,
+        ethereum_adapter,
         Arc::new(graph_core::LinkResolver::from(IpfsClient::localhost())),
         store,
         call_cache,
         arweave_adapter,
+        Arc::new(ArweaveCache::new(1_000_000)),
         three_box_adapter,
     )
 }
@@ -222,6 +282,88 @@ fn mock_context(
     }
 }
 
+fn test_host_exports(subgraph_id: &str) -> HostExports<Chain> {
+    test_host_exports_with_adapters(
+        subgraph_id,
+        mock_data_source("wasm_test/abort.wasm"),
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    )
+}
+
+fn test_host_exports_with_adapters(
+    subgraph_id: &str,
+    data_source: DataSource,
+    ethereum_adapter: Arc<dyn EthereumAdapterTrait>,
+    arweave_adapter: Arc<dyn ArweaveAdapterTrait>,
+) -> HostExports<Chain> {
+    let store = STORE.clone();
+    let call_cache = store
+        .block_store()
+        .ethereum_call_cache(NETWORK_NAME)
+        .expect("call cache for test network");
+    mock_host_exports_with_adapters(
+        DeploymentHash::new(subgraph_id).unwrap(),
+        data_source,
+        store.subgraph_store(),
+        call_cache,
+        ethereum_adapter,
+        arweave_adapter,
+    )
+}
+
+/// A data source with a `Token` ABI, for testing `ethereum.call`.
+fn contract_call_data_source() -> DataSource {
+    let mut data_source = mock_data_source("wasm_test/abort.wasm");
+    data_source.mapping.abis = vec![Arc::new(MappingABI {
+        name: "Token".to_string(),
+        contract: Contract::load(
+            r#"[
+            {
+                "constant": true,
+                "inputs": [
+                    {
+                        "name": "owner",
+                        "type": "address"
+                    }
+                ],
+                "name": "balanceOf",
+                "outputs": [
+                    {
+                        "name": "",
+                        "type": "uint256"
+                    }
+                ],
+                "type": "function"
+            }
+        ]"#
+            .as_bytes(),
+        )
+        .unwrap(),
+    })];
+    data_source
+}
+
+fn balance_of_call(contract_address: Address) -> UnresolvedContractCall {
+    UnresolvedContractCall {
+        contract_name: "Token".to_string(),
+        contract_address,
+        function_name: "balanceOf".to_string(),
+        function_signature: None,
+        function_args: vec![Token::Address(Address::repeat_byte(0x01))],
+        from: None,
+        gas_limit: None,
+        state_overrides: None,
+    }
+}
+
+/// Host functions use `block_on` which must be called from a sync context,
+/// so we replicate what we do `spawn_module`.
+fn run_in_sync_context<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let runtime = tokio::runtime::Handle::current();
+    std::thread::spawn(move || runtime.enter(f)).join().unwrap()
+}
+
 impl WasmInstance<Chain> {
     fn invoke_export<C, R>(&self, f: &str, arg: AscPtr<C>) -> AscPtr<R> {
         let func = self.get_func(f).typed().unwrap().clone();
@@ -466,6 +608,430 @@ async fn ipfs_map() {
     assert!(errmsg.contains("500 Internal Server Error"));
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_map_causality_region() {
+    let subgraph_id = "ipfsMapCausalityRegion";
+    let (json_string, _) = make_thing(subgraph_id, "one", "eins");
+    let hash = IpfsClient::localhost()
+        .add(json_string.into())
+        .await
+        .unwrap()
+        .hash;
+
+    let regions = run_in_sync_context(move || {
+        let (mut module, _, _) =
+            test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/ipfs_map.wasm"));
+        let proof_of_indexing = Arc::new(AtomicRefCell::new(ProofOfIndexing::new(0)));
+        module.instance_ctx_mut().ctx.proof_of_indexing = Some(proof_of_indexing.cheap_clone());
+
+        let value = asc_new(&mut module, &hash).unwrap();
+        let user_data = asc_new(&mut module, USER_DATA).unwrap();
+        let func = module.get_func("ipfsMap").typed().unwrap().clone();
+        let _: () = func.call((value.wasm_ptr(), user_data.wasm_ptr())).unwrap();
+
+        let proof_of_indexing = std::mem::take(&mut *proof_of_indexing.borrow_mut());
+        proof_of_indexing
+            .take()
+            .into_iter()
+            .map(|(region, _)| region)
+            .collect::<Vec<_>>()
+    });
+
+    // The entity is written by the `ipfs.map` callback, so the change must
+    // not show up under the region of the ethereum network.
+    assert_eq!(vec![IPFS_CAUSALITY_REGION.to_string()], regions);
+}
+
+#[tokio::test]
+async fn proof_of_indexing_write() {
+    let host_exports = test_host_exports("proofOfIndexingWrite");
+    let logger = &*test_store::LOGGER;
+    let mut data = HashMap::new();
+    data.insert("price".to_string(), Value::String("42".to_string()));
+
+    let digests = |proof_of_indexing: ProofOfIndexing| {
+        proof_of_indexing
+            .take()
+            .into_iter()
+            .map(|(region, stream)| (region, stream.pause(None)))
+            .collect::<Vec<_>>()
+    };
+
+    let recorded = Arc::new(AtomicRefCell::new(ProofOfIndexing::new(0)));
+    host_exports
+        .proof_of_indexing_write(
+            logger,
+            &Some(recorded.cheap_clone()),
+            DataOrigin::Chain,
+            "price".to_string(),
+            data.clone(),
+        )
+        .unwrap();
+    let recorded = digests(std::mem::take(&mut *recorded.borrow_mut()));
+
+    let mut expected = ProofOfIndexing::new(0);
+    expected.write(
+        logger,
+        host_exports.causality_region(DataOrigin::Chain),
+        &ProofOfIndexingEvent::Custom {
+            name: "price",
+            data: &data,
+        },
+    );
+    assert_eq!(1, recorded.len());
+    assert_eq!(digests(expected), recorded);
+}
+
+type MockResult<T> = BoxFuture<'static, Result<T, anyhow::Error>>;
+
+/// A `LinkResolver` that does what the closures it is given say, so that
+/// every test can set up just the behavior it needs. Calling a method that
+/// has no closure panics. `cat` is answered by the `cat_from_gateway`
+/// closure with no gateway.
+#[derive(Default)]
+struct MockLinkResolver {
+    cat: Option<Box<dyn Fn(&Link, Option<&str>) -> MockResult<Vec<u8>> + Send + Sync>>,
+    ls: Option<Box<dyn Fn(&Link) -> MockResult<Vec<(String, String)>> + Send + Sync>>,
+    json_stream: Option<Box<dyn Fn(&Link) -> MockResult<JsonValueStream> + Send + Sync>>,
+}
+
+impl MockLinkResolver {
+    fn with_cat(
+        mut self,
+        cat: impl Fn(&Link, Option<&str>) -> MockResult<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.cat = Some(Box::new(cat));
+        self
+    }
+
+    fn with_ls(
+        mut self,
+        ls: impl Fn(&Link) -> MockResult<Vec<(String, String)>> + Send + Sync + 'static,
+    ) -> Self {
+        self.ls = Some(Box::new(ls));
+        self
+    }
+
+    fn with_json_stream(
+        mut self,
+        json_stream: impl Fn(&Link) -> MockResult<JsonValueStream> + Send + Sync + 'static,
+    ) -> Self {
+        self.json_stream = Some(Box::new(json_stream));
+        self
+    }
+
+    /// Streams the same JSON values, one per line, for every link. Lines
+    /// count from 1, like for files on IPFS.
+    fn json_values(values: Vec<serde_json::Value>) -> Self {
+        Self::default().with_json_stream(move |_| {
+            let values = values.clone().into_iter().enumerate().map(|(line, value)| {
+                Ok(JsonStreamValue {
+                    value,
+                    line: line + 1,
+                })
+            });
+            let stream: JsonValueStream = Box::pin(futures03::stream::iter(values));
+            futures03::future::ok(stream).boxed()
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkResolver for MockLinkResolver {
+    fn with_timeout(self, _timeout: Duration) -> Self {
+        self
+    }
+
+    fn with_retries(self) -> Self {
+        self
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, anyhow::Error> {
+        self.cat_from_gateway(logger, link, None).await
+    }
+
+    async fn cat_from_gateway(
+        &self,
+        _logger: &Logger,
+        link: &Link,
+        gateway: Option<&str>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let cat = self.cat.as_ref().expect("MockLinkResolver has no `cat`");
+        cat(link, gateway).await
+    }
+
+    async fn ls(
+        &self,
+        _logger: &Logger,
+        link: &Link,
+    ) -> Result<Vec<(String, String)>, anyhow::Error> {
+        let ls = self.ls.as_ref().expect("MockLinkResolver has no `ls`");
+        ls(link).await
+    }
+
+    async fn json_stream(
+        &self,
+        _logger: &Logger,
+        link: &Link,
+    ) -> Result<JsonValueStream, anyhow::Error> {
+        let json_stream = self
+            .json_stream
+            .as_ref()
+            .expect("MockLinkResolver has no `json_stream`");
+        json_stream(link).await
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_map_concurrency() {
+    let subgraph_id = "ipfsMapConcurrency";
+    let ids: Vec<String> = (0..10).map(|i| format!("thing{}", i)).collect();
+    let values = ids
+        .iter()
+        .map(|id| serde_json::json!({ "id": id, "value": id }))
+        .collect();
+
+    let output = run_in_sync_context(move || {
+        let (module, _, _) =
+            test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/ipfs_map.wasm"));
+        let link_resolver: Arc<dyn LinkResolver> = Arc::new(MockLinkResolver::json_values(values));
+
+        // Ten values with a concurrency of four also exercises a partial
+        // last batch.
+        let states = HostExports::ipfs_map(
+            &link_resolver,
+            &mut module.instance_ctx_mut(),
+            "QmStream".to_string(),
+            "echoToStore",
+            Value::String(USER_DATA.to_string()),
+            vec!["json".to_string()],
+            4,
+            0,
+        )
+        .unwrap();
+
+        states
+            .into_iter()
+            .map(|state| {
+                let mods = state.entity_cache.as_modifications().unwrap().modifications;
+                assert_eq!(1, mods.len());
+                mods[0].entity_key().entity_id.clone()
+            })
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(ids, output);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_map_concurrency_proof_of_indexing() {
+    let values: Vec<serde_json::Value> = (0..10)
+        .map(|i| serde_json::json!({ "id": format!("thing{}", i), "value": "x" }))
+        .collect();
+
+    // Running the callbacks in parallel must not change the proof of indexing
+    let digests = run_in_sync_context(move || {
+        [1, 4]
+            .iter()
+            .map(|concurrency| {
+                let (module, _, _) = test_valid_module_and_store(
+                    &format!("ipfsMapConcurrencyPoi{}", concurrency),
+                    mock_data_source("wasm_test/ipfs_map.wasm"),
+                );
+                let proof_of_indexing = Arc::new(AtomicRefCell::new(ProofOfIndexing::new(0)));
+                module.instance_ctx_mut().ctx.proof_of_indexing =
+                    Some(proof_of_indexing.cheap_clone());
+                let link_resolver: Arc<dyn LinkResolver> =
+                    Arc::new(MockLinkResolver::json_values(values.clone()));
+
+                HostExports::ipfs_map(
+                    &link_resolver,
+                    &mut module.instance_ctx_mut(),
+                    "QmStream".to_string(),
+                    "echoToStore",
+                    Value::String(USER_DATA.to_string()),
+                    vec!["json".to_string()],
+                    *concurrency,
+                    0,
+                )
+                .unwrap();
+
+                std::mem::take(&mut *proof_of_indexing.borrow_mut())
+                    .take()
+                    .into_iter()
+                    .map(|(region, stream)| (region, stream.pause(None)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(1, digests[0].len());
+    assert_eq!(digests[0], digests[1]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_map_start_line() {
+    let subgraph_id = "ipfsMapStartLine";
+    let values = (1..=5)
+        .map(|i| serde_json::json!({ "id": format!("thing{}", i), "value": "x" }))
+        .collect();
+
+    let output = run_in_sync_context(move || {
+        let (module, _, _) =
+            test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/ipfs_map.wasm"));
+        let link_resolver: Arc<dyn LinkResolver> = Arc::new(MockLinkResolver::json_values(values));
+
+        let states = HostExports::ipfs_map(
+            &link_resolver,
+            &mut module.instance_ctx_mut(),
+            "QmStream".to_string(),
+            "echoToStore",
+            Value::String(USER_DATA.to_string()),
+            vec!["json".to_string()],
+            1,
+            3,
+        )
+        .unwrap();
+
+        states
+            .into_iter()
+            .map(|state| {
+                let mods = state.entity_cache.as_modifications().unwrap().modifications;
+                mods[0].entity_key().entity_id.clone()
+            })
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(vec!["thing3", "thing4", "thing5"], output);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_cat_with_content_type() {
+    let mut host_exports = test_host_exports("ipfsCatWithContentType");
+    host_exports.link_resolver = Arc::new(
+        MockLinkResolver::default()
+            .with_cat(|_, _| futures03::future::ok(br#"{"name": "graph"}"#.to_vec()).boxed()),
+    );
+
+    let (bytes, content_type) = run_in_sync_context(move || {
+        host_exports
+            .ipfs_cat_with_content_type(&*test_store::LOGGER, "QmJson".to_string(), None)
+            .unwrap()
+    });
+    assert_eq!(br#"{"name": "graph"}"#.to_vec(), bytes);
+    assert_eq!(Some("application/json".to_string()), content_type);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_ls() {
+    let mut host_exports = test_host_exports("ipfsLs");
+    host_exports.link_resolver = Arc::new(MockLinkResolver::default().with_ls(|_| {
+        futures03::future::ok(vec![
+            ("a.json".to_string(), "QmA".to_string()),
+            ("b.json".to_string(), "QmB".to_string()),
+        ])
+        .boxed()
+    }));
+
+    let entries = run_in_sync_context(move || {
+        host_exports
+            .ipfs_ls(&*test_store::LOGGER, "QmDirectory".to_string(), None)
+            .unwrap()
+    });
+    assert_eq!(
+        vec![
+            ("a.json".to_string(), "QmA".to_string()),
+            ("b.json".to_string(), "QmB".to_string()),
+        ],
+        entries
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_cat_required() {
+    let mut host_exports = test_host_exports("ipfsCatRequired");
+    // Knows that `QmMissing` does not exist and fails to fetch anything else
+    host_exports.link_resolver = Arc::new(MockLinkResolver::default().with_cat(|link, _| {
+        let result = match link.link.as_str() {
+            "QmMissing" => Err(LinkNotFound(link.link.clone()).into()),
+            _ => Err(anyhow!("IPFS request timed out")),
+        };
+        futures03::future::ready(result).boxed()
+    }));
+
+    run_in_sync_context(move || {
+        let logger = &*test_store::LOGGER;
+        assert!(matches!(
+            host_exports.ipfs_cat_required(logger, "QmMissing".to_string(), None),
+            Err(HostExportError::Deterministic(_))
+        ));
+        assert!(matches!(
+            host_exports.ipfs_cat_required(logger, "QmUnavailable".to_string(), None),
+            Err(HostExportError::Unknown(_))
+        ));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_cat_deadline() {
+    let mut host_exports = test_host_exports("ipfsCatDeadline");
+    // Never finishes fetching anything
+    host_exports.link_resolver = Arc::new(
+        MockLinkResolver::default()
+            .with_cat(|_, _| futures03::future::pending().boxed())
+            .with_ls(|_| futures03::future::pending().boxed()),
+    );
+
+    run_in_sync_context(move || {
+        let logger = &*test_store::LOGGER;
+        let start = Instant::now();
+        let deadline = Some(start + Duration::from_millis(100));
+
+        let err = host_exports
+            .ipfs_cat(logger, "QmStalled".to_string(), None, deadline)
+            .unwrap_err();
+        assert!(DeadlineExceeded::caused(&err));
+        let err = host_exports
+            .ipfs_ls(logger, "QmStalled".to_string(), deadline)
+            .unwrap_err();
+        assert!(DeadlineExceeded::caused(&err));
+        // Running out of time is not the file's fault
+        assert!(matches!(
+            host_exports.ipfs_cat_required(logger, "QmStalled".to_string(), deadline),
+            Err(HostExportError::Unknown(_))
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ipfs_cat_from_gateway() {
+    let gateways = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut host_exports = test_host_exports("ipfsCatFromGateway");
+    let recorded = gateways.clone();
+    host_exports.link_resolver =
+        Arc::new(MockLinkResolver::default().with_cat(move |_, gateway| {
+            recorded.lock().unwrap().push(gateway.map(str::to_owned));
+            futures03::future::ok(vec![]).boxed()
+        }));
+
+    run_in_sync_context(move || {
+        let logger = &*test_store::LOGGER;
+        host_exports
+            .ipfs_cat(logger, "QmA".to_string(), None, None)
+            .unwrap();
+        host_exports
+            .ipfs_cat(
+                logger,
+                "QmA".to_string(),
+                Some("http://ipfs.local:5001"),
+                None,
+            )
+            .unwrap();
+    });
+    assert_eq!(
+        vec![None, Some("http://ipfs.local:5001".to_string())],
+        *gateways.lock().unwrap()
+    );
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn ipfs_fail() {
     let runtime = tokio::runtime::Handle::current();
@@ -598,10 +1164,32 @@ async fn big_int_arithmetic() {
 async fn abort() {
     let module = test_module("abort", mock_data_source("wasm_test/abort.wasm"));
     let res: Result<(), _> = module.get_func("abort").typed().unwrap().call(());
-    assert!(res
-        .unwrap_err()
-        .to_string()
-        .contains("line 6, column 2, with message: not true"));
+    let err = res.unwrap_err().to_string();
+    assert!(err.contains("line 6, column 2, with message: not true"));
+    assert!(err.contains("wasm backtrace:"));
+}
+
+#[tokio::test]
+async fn abort_backtrace() {
+    let host_exports = test_host_exports("abortBacktrace");
+    let err = host_exports
+        .abort(
+            Some("not true".to_string()),
+            Some("lib.ts".to_string()),
+            Some(3),
+            Some(7),
+            vec![
+                "lib/check".to_string(),
+                "mapping/handleTransfer".to_string(),
+            ],
+        )
+        .err()
+        .unwrap();
+    assert_eq!(
+        "Mapping aborted at lib.ts, line 3, column 7, with message: not true\n\
+         wasm backtrace:\n  0: lib/check\n  1: mapping/handleTransfer",
+        err.0.to_string()
+    );
 }
 
 #[tokio::test]
@@ -618,6 +1206,82 @@ async fn bytes_to_base58() {
     assert_eq!(base58, "QmWmyoMoctfbAaiEs2G46gpeUmhqFRDW6KWo64y5r581Vz");
 }
 
+#[tokio::test]
+async fn bytes_to_string_utf16() {
+    let host_exports = test_host_exports("bytesToStringUtf16");
+
+    // "Hi €𝄞": an ASCII pair, a BMP code point and a surrogate pair
+    let le = vec![
+        0x48, 0x00, 0x69, 0x00, 0x20, 0x00, 0xac, 0x20, 0x34, 0xd8, 0x1e, 0xdd,
+    ];
+    let be: Vec<u8> = le
+        .chunks(2)
+        .flat_map(|pair| vec![pair[1], pair[0]])
+        .collect();
+    assert_eq!(
+        "Hi €𝄞",
+        host_exports
+            .bytes_to_string_utf16(le.clone(), true)
+            .unwrap()
+    );
+    assert_eq!(
+        "Hi €𝄞",
+        host_exports.bytes_to_string_utf16(be, false).unwrap()
+    );
+
+    // Odd length
+    assert!(host_exports
+        .bytes_to_string_utf16(vec![0x48, 0x00, 0x69], true)
+        .is_err());
+    // Unpaired high surrogate
+    assert!(host_exports
+        .bytes_to_string_utf16(vec![0x34, 0xd8, 0x48, 0x00], true)
+        .is_err());
+    // Unpaired low surrogate
+    assert!(host_exports
+        .bytes_to_string_utf16(vec![0x1e, 0xdd], true)
+        .is_err());
+}
+
+#[tokio::test]
+async fn base32() {
+    let host_exports = test_host_exports("base32");
+
+    // RFC 4648 test vectors, lowercased and without padding
+    let vectors = vec![
+        ("", ""),
+        ("f", "my"),
+        ("fo", "mzxq"),
+        ("foo", "mzxw6"),
+        ("foob", "mzxw6yq"),
+        ("fooba", "mzxw6ytb"),
+        ("foobar", "mzxw6ytboi"),
+    ];
+    for (bytes, encoded) in vectors {
+        let bytes = bytes.as_bytes().to_vec();
+        assert_eq!(
+            encoded,
+            host_exports.bytes_to_base32(bytes.clone()).unwrap()
+        );
+        assert_eq!(
+            bytes,
+            host_exports.base32_to_bytes(encoded.to_string()).unwrap()
+        );
+    }
+
+    let bytes: Vec<u8> = (0..=255).collect();
+    let encoded = host_exports.bytes_to_base32(bytes.clone()).unwrap();
+    assert_eq!(bytes, host_exports.base32_to_bytes(encoded).unwrap());
+
+    for invalid in &["MZXW6", "mzxw6===", "mzxw1", "m", "mzx", "mz", "mzxw6yä"] {
+        assert!(
+            host_exports.base32_to_bytes(invalid.to_string()).is_err(),
+            "`{}` should not decode",
+            invalid
+        );
+    }
+}
+
 #[tokio::test]
 async fn data_source_create() {
     let run_data_source_create =
@@ -658,6 +1322,154 @@ async fn data_source_create() {
     };
 }
 
+#[tokio::test]
+async fn data_source_create_limit() {
+    let subgraph_id = "dataSourceCreateLimit";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let mut host_exports = test_host_exports(subgraph_id);
+    host_exports.max_data_sources_per_block = 2;
+
+    let logger = ctx.ctx.logger.clone();
+    let state = &mut ctx.ctx.state;
+    let data_source_create = |state: &mut BlockState<Chain>| {
+        host_exports.data_source_create(
+            &logger,
+            state,
+            "example template".to_string(),
+            vec!["0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95".to_string()],
+            None,
+            0,
+        )
+    };
+
+    state.enter_handler();
+    data_source_create(state).unwrap();
+    state.exit_handler();
+    assert_eq!(1, state.drain_created_data_sources().len());
+
+    // Data sources that were already instantiated still count
+    state.enter_handler();
+    data_source_create(state).unwrap();
+    assert!(matches!(
+        data_source_create(state),
+        Err(HostExportError::Deterministic(_))
+    ));
+    state.exit_handler();
+    assert_eq!(2, state.created_data_sources_count());
+}
+
+#[tokio::test]
+async fn data_source_create_deduplication() {
+    let subgraph_id = "dataSourceCreateDeduplication";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let mut host_exports = test_host_exports(subgraph_id);
+    host_exports.deduplicate_data_sources = true;
+
+    let logger = ctx.ctx.logger.clone();
+    let state = &mut ctx.ctx.state;
+    let data_source_create = |state: &mut BlockState<Chain>, address: &str| {
+        host_exports.data_source_create(
+            &logger,
+            state,
+            "example template".to_string(),
+            vec![address.to_string()],
+            None,
+            0,
+        )
+    };
+
+    state.enter_handler();
+    data_source_create(state, "0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95").unwrap();
+    data_source_create(state, "0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95").unwrap();
+    state.exit_handler();
+    assert_eq!(1, state.drain_created_data_sources().len());
+
+    // Data sources that were already instantiated are remembered
+    state.enter_handler();
+    data_source_create(state, "0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95").unwrap();
+    data_source_create(state, "0x0000000000000000000000000000000000000001").unwrap();
+    state.exit_handler();
+    assert_eq!(1, state.drain_created_data_sources().len());
+}
+
+#[tokio::test]
+async fn data_source_create_with_entity() {
+    let subgraph_id = "dataSourceCreateWithEntity";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let stopwatch = ctx.host_metrics.stopwatch.clone();
+    let state = &mut ctx.ctx.state;
+
+    let mut create_with_entity = |entity_type: &str, id: &str| {
+        host_exports.data_source_create_with_entity(
+            &logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "example template".to_string(),
+            vec![id.to_string()],
+            None,
+            0,
+            entity_type.to_string(),
+            id.to_string(),
+            HashMap::new(),
+            None,
+            &stopwatch,
+        )
+    };
+
+    create_with_entity("Thing", "0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95").unwrap();
+    // A `Comment` needs a `post`, so neither the data source nor the entity
+    // are recorded
+    assert!(create_with_entity("Comment", "0x0000000000000000000000000000000000000001").is_err());
+
+    assert_eq!(1, state.drain_created_data_sources().len());
+    assert!(host_exports
+        .store_get(
+            state,
+            "Thing".to_string(),
+            "0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95".to_string()
+        )
+        .unwrap()
+        .is_some());
+    assert!(host_exports
+        .store_get(
+            state,
+            "Comment".to_string(),
+            "0x0000000000000000000000000000000000000001".to_string()
+        )
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn trigger_kind() {
+    let host_exports = test_host_exports("triggerKind");
+    for (kind, expected) in &[
+        (TriggerKind::Event, "event"),
+        (TriggerKind::Call, "call"),
+        (TriggerKind::Block, "block"),
+        (TriggerKind::Ipfs, "ipfs"),
+    ] {
+        assert_eq!(*expected, host_exports.trigger_kind(Some(*kind)).unwrap());
+    }
+
+    // Outside of a handler, there is no trigger
+    let module = test_module(
+        "triggerKindOutsideHandler",
+        mock_data_source("wasm_test/abort.wasm"),
+    );
+    assert_eq!(None, module.instance_ctx().trigger_kind);
+    assert!(host_exports.trigger_kind(None).is_err());
+}
+
 #[tokio::test]
 async fn ens_name_by_hash() {
     let mut module = test_module(
@@ -679,6 +1491,32 @@ async fn ens_name_by_hash() {
         .is_null());
 }
 
+#[tokio::test]
+async fn ens_name_by_hash_memo() {
+    let host_exports = test_host_exports("ensNameByHashMemo");
+    let hash = "0x4f3c2a9e1d4d2e0a6f9b1a3c5e7d9f0b2c4e6a8d0f1b3c5e7a9d2f4b6c8e0a1d";
+
+    let mut memo = EnsNameMemo::new();
+    assert_eq!(
+        None,
+        host_exports.ens_name_by_hash(hash, &mut memo).unwrap()
+    );
+
+    // The same handler does not see the name that was added in the meantime,
+    // but the next one does
+    test_store::insert_ens_name(hash, "memo");
+    assert_eq!(
+        None,
+        host_exports.ens_name_by_hash(hash, &mut memo).unwrap()
+    );
+    assert_eq!(
+        Some("memo".to_string()),
+        host_exports
+            .ens_name_by_hash(hash, &mut EnsNameMemo::new())
+            .unwrap()
+    );
+}
+
 #[tokio::test]
 async fn entity_store() {
     let (mut module, store, deployment) =
@@ -784,3 +1622,2248 @@ async fn detect_contract_calls() {
         true
     );
 }
+
+#[tokio::test]
+async fn current_block_ptr() {
+    let host_exports = test_host_exports("currentBlockPtr");
+    let hash = H256::repeat_byte(0xab);
+    let block_ptr = BlockPtr::from((hash, 42));
+
+    let (number, block_hash) = host_exports.current_block_ptr(&block_ptr).unwrap();
+    assert_eq!(number, 42);
+    assert_eq!(block_hash, hash);
+}
+
+#[derive(Default)]
+struct CountingArweaveAdapter {
+    calls: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl ArweaveAdapterTrait for CountingArweaveAdapter {
+    async fn tx_data(&self, tx_id: &str) -> Result<Bytes, anyhow::Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(Bytes::from(tx_id.as_bytes().to_vec()))
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn arweave_transaction_data_is_cached() {
+    let arweave_adapter = Arc::new(CountingArweaveAdapter::default());
+    let host_exports = test_host_exports_with_adapters(
+        "arweaveCache",
+        mock_data_source("wasm_test/abort.wasm"),
+        Arc::new(MockEthereumAdapter::default()),
+        arweave_adapter.clone(),
+    );
+
+    run_in_sync_context(move || {
+        let first = host_exports.arweave_transaction_data("tx").unwrap();
+        let second = host_exports.arweave_transaction_data("tx").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(arweave_adapter.calls.load(Ordering::SeqCst), 1);
+
+        host_exports.arweave_transaction_data("other").unwrap();
+        assert_eq!(arweave_adapter.calls.load(Ordering::SeqCst), 2);
+    });
+}
+
+struct CountingDrain(Arc<AtomicUsize>);
+
+impl slog::Drain for CountingDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, _: &slog::Record, _: &slog::OwnedKVList) -> Result<(), slog::Never> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Keeps the messages of all records.
+struct CapturingDrain(Arc<std::sync::Mutex<Vec<String>>>);
+
+impl slog::Drain for CapturingDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &slog::Record, _: &slog::OwnedKVList) -> Result<(), slog::Never> {
+        self.0.lock().unwrap().push(record.msg().to_string());
+        Ok(())
+    }
+}
+
+#[test]
+fn check_ethereum_decode_warns() {
+    let messages = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let logger = Logger::root(CapturingDrain(messages.clone()), o!());
+    let data = ethabi::encode(&[Token::Uint(5.into()), Token::Uint(6.into())]);
+
+    let check = |types: &str| {
+        let token =
+            host_exports::ethereum_decode(types.to_string(), data.clone(), 100_000).unwrap();
+        host_exports::check_ethereum_decode(&logger, types, &data, &token);
+    };
+    check("(uint256,uint256)");
+    // Decoding only looks at the first word, which a mapping would not notice
+    check("uint256");
+    // Types that encode the same way can not be told apart
+    check("(int256,uint256)");
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(1, messages.len());
+    assert!(messages[0].contains("does not encode to the bytes it was decoded from"));
+}
+
+#[tokio::test]
+async fn bytes_to_string_warning_chars() {
+    let messages = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let logger = Logger::root(CapturingDrain(messages.clone()), o!());
+    let bytes = b"abcdefgh\xff".to_vec();
+
+    assert_eq!(
+        "abcdefgh\u{fffd}",
+        host_exports::bytes_to_string(&logger, bytes.clone(), 4, true)
+    );
+    host_exports::bytes_to_string(&logger, bytes, 100, true);
+    // Valid UTF-8 is not logged
+    host_exports::bytes_to_string(&logger, b"abc".to_vec(), 4, true);
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(2, messages.len());
+    assert!(messages[0].ends_with("String (truncated to 4 chars): 'abcd'"));
+    assert!(messages[1].ends_with("String (truncated to 100 chars): 'abcdefgh\u{fffd}'"));
+}
+
+#[tokio::test]
+async fn log_log_drops_messages_over_limit() {
+    let mut host_exports = test_host_exports("logLogLimit");
+    host_exports.max_logs_per_handler = Some(2);
+    let records = Arc::new(AtomicUsize::new(0));
+    let logger = Logger::root(CountingDrain(records.clone()), o!());
+
+    let mut log_count = 0;
+    for i in 0..5 {
+        host_exports
+            .log_log(
+                &logger,
+                slog::Level::Info,
+                format!("msg {}", i),
+                &mut log_count,
+            )
+            .unwrap();
+    }
+    // Two messages plus a single warning about the dropped messages.
+    assert_eq!(records.load(Ordering::SeqCst), 3);
+
+    // Critical messages still fail the handler once the limit is reached.
+    let err = host_exports
+        .log_log(
+            &logger,
+            slog::Level::Critical,
+            "boom".to_string(),
+            &mut log_count,
+        )
+        .unwrap_err();
+    assert!(err
+        .0
+        .to_string()
+        .contains("Critical error logged in mapping"));
+    assert_eq!(records.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn crypto_keccak_256_string() {
+    let host_exports = test_host_exports("cryptoKeccak256String");
+    let s = "transfer(address,uint256)";
+    assert_eq!(
+        host_exports
+            .crypto_keccak_256_string(s.to_string())
+            .unwrap(),
+        host_exports
+            .crypto_keccak_256(s.as_bytes().to_vec())
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn make_entity_id() {
+    let host_exports = test_host_exports("makeEntityId");
+    let id = |parts: &[&str]| {
+        host_exports
+            .make_entity_id(parts.iter().map(|part| part.as_bytes().to_vec()).collect())
+            .unwrap()
+    };
+
+    assert_ne!(id(&["ab", "c"]), id(&["a", "bc"]));
+    assert_ne!(id(&["ab", ""]), id(&["ab"]));
+    assert_eq!(id(&["a", "bc"]), id(&["a", "bc"]));
+    assert!(id(&["ab", "c"]).starts_with("0x"));
+    assert_eq!(66, id(&[]).len());
+}
+
+#[tokio::test]
+async fn ethereum_event_by_topic0() {
+    let mut data_source = mock_data_source("wasm_test/abort.wasm");
+    data_source.mapping.abis = vec![Arc::new(MappingABI {
+        name: "Token".to_string(),
+        contract: Contract::load(
+            r#"[
+            {
+                "anonymous": false,
+                "inputs": [
+                    { "indexed": true, "name": "from", "type": "address" },
+                    { "indexed": true, "name": "to", "type": "address" },
+                    { "indexed": false, "name": "value", "type": "uint256" }
+                ],
+                "name": "Transfer",
+                "type": "event"
+            }
+        ]"#
+            .as_bytes(),
+        )
+        .unwrap(),
+    })];
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumEventByTopic0",
+        data_source,
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    let transfer =
+        hex::decode("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").unwrap();
+    assert_eq!(
+        Some("Transfer(address,address,uint256)".to_string()),
+        host_exports.ethereum_event_by_topic0(transfer).unwrap()
+    );
+    assert_eq!(
+        None,
+        host_exports
+            .ethereum_event_by_topic0(vec![0u8; 32])
+            .unwrap()
+    );
+    assert!(host_exports.ethereum_event_by_topic0(vec![0u8; 4]).is_err());
+}
+
+#[tokio::test]
+async fn ethereum_function_selector() {
+    let host_exports = test_host_exports("ethereumFunctionSelector");
+    assert_eq!(
+        [0xa9, 0x05, 0x9c, 0xbb],
+        host_exports
+            .ethereum_function_selector("transfer(address,uint256)".to_string())
+            .unwrap()
+    );
+    assert_eq!(
+        [0x70, 0xa0, 0x82, 0x31],
+        host_exports
+            .ethereum_function_selector("balanceOf(address)".to_string())
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn ethereum_verify_merkle_proof() {
+    let host_exports = test_host_exports("ethereumVerifyMerkleProof");
+    let hash = |data: &[u8]| {
+        host_exports
+            .crypto_keccak_256(data.to_vec())
+            .unwrap()
+            .to_vec()
+    };
+    let hash_pair = |a: &[u8], b: &[u8]| match a <= b {
+        true => hash(&[a, b].concat()),
+        false => hash(&[b, a].concat()),
+    };
+
+    // A tree with four leaves
+    let leaves: Vec<_> = ["a", "b", "c", "d"]
+        .iter()
+        .map(|leaf| hash(leaf.as_bytes()))
+        .collect();
+    let ab = hash_pair(&leaves[0], &leaves[1]);
+    let cd = hash_pair(&leaves[2], &leaves[3]);
+    let root = hash_pair(&ab, &cd);
+
+    let verify = |leaf: &[u8], proof: &[&[u8]], root: &[u8]| {
+        host_exports.ethereum_verify_merkle_proof(
+            leaf.to_vec(),
+            proof.iter().map(|node| node.to_vec()).collect(),
+            root.to_vec(),
+        )
+    };
+
+    assert!(verify(&leaves[2], &[&leaves[3], &ab], &root).unwrap());
+    assert!(verify(&leaves[1], &[&leaves[0], &cd], &root).unwrap());
+    assert!(!verify(&leaves[2], &[&leaves[0], &ab], &root).unwrap());
+    assert!(!verify(&leaves[2], &[&leaves[3], &ab], &ab).unwrap());
+    assert!(verify(&leaves[2], &[&leaves[3], &ab[..31]], &root).is_err());
+}
+
+#[tokio::test]
+async fn store_set_merges_fields() {
+    let (mut module, _, _) = test_valid_module_and_store(
+        "storeSetMergesFields",
+        mock_data_source("wasm_test/abort.wasm"),
+    );
+    let mut ctx = module.take_ctx();
+
+    for (field, value) in &[("value", "a"), ("extra", "b")] {
+        let mut data = HashMap::new();
+        data.insert(field.to_string(), Value::from(*value));
+        ctx.ctx
+            .host_exports
+            .store_set(
+                &ctx.ctx.logger,
+                &mut ctx.ctx.state,
+                &ctx.ctx.proof_of_indexing,
+                DataOrigin::Chain,
+                "Thing".to_string(),
+                "merged".to_string(),
+                data,
+                None,
+                &ctx.host_metrics.stopwatch,
+            )
+            .unwrap();
+    }
+
+    let mut mods = ctx
+        .ctx
+        .state
+        .entity_cache
+        .as_modifications()
+        .unwrap()
+        .modifications;
+    assert_eq!(1, mods.len());
+    match mods.pop().unwrap() {
+        EntityModification::Insert { data, .. } => {
+            assert_eq!(Some(&Value::from("a")), data.get("value"));
+            assert_eq!(Some(&Value::from("b")), data.get("extra"));
+        }
+        _ => assert!(false, "expected Insert modification"),
+    }
+}
+
+#[tokio::test]
+async fn big_int_bit_length() {
+    let host_exports = test_host_exports("bigIntBitLength");
+    for (x, bits) in &[(0, 0), (1, 1), (255, 8), (256, 9), (-256, 9)] {
+        assert_eq!(
+            *bits,
+            host_exports.big_int_bit_length(BigInt::from(*x)).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn big_int_count_ones() {
+    let host_exports = test_host_exports("bigIntCountOnes");
+    for (x, ones) in &[(0, 0), (7, 3), (256, 1), (-7, 3), (i64::MAX, 63)] {
+        assert_eq!(
+            *ones,
+            host_exports.big_int_count_ones(BigInt::from(*x)).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn to_f64() {
+    let host_exports = test_host_exports("toF64");
+    let x = host_exports.big_int_to_f64(BigInt::from(1000000)).unwrap();
+    assert!((x - 1e6).abs() < f64::EPSILON);
+    let x = host_exports
+        .big_decimal_to_f64(BigDecimal::from_str("-0.1").unwrap())
+        .unwrap();
+    assert!((x + 0.1).abs() < f64::EPSILON);
+
+    let huge = format!("1{}", "0".repeat(400));
+    assert!(host_exports
+        .big_int_to_f64(BigInt::from_str(&huge).unwrap())
+        .is_err());
+    assert!(host_exports
+        .big_decimal_to_f64(BigDecimal::from_str(&huge).unwrap())
+        .is_err());
+}
+
+#[tokio::test]
+async fn crypto_keccak_256_incremental() {
+    let host_exports = test_host_exports("cryptoKeccak256Incremental");
+    let input: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+    let mut hashers = KeccakHashers::new();
+
+    let handle = host_exports.keccak_init(&mut hashers).unwrap();
+    for chunk in input.chunks(4096) {
+        host_exports
+            .keccak_update(&mut hashers, handle, chunk.to_vec())
+            .unwrap();
+    }
+    assert_eq!(
+        host_exports.crypto_keccak_256(input).unwrap(),
+        host_exports.keccak_finalize(&mut hashers, handle).unwrap()
+    );
+
+    // Finalized hashers can not be used again
+    assert!(host_exports
+        .keccak_update(&mut hashers, handle, vec![1])
+        .is_err());
+    assert!(host_exports.keccak_finalize(&mut hashers, handle).is_err());
+}
+
+#[tokio::test]
+async fn big_int_pow_mod() {
+    let host_exports = test_host_exports("bigIntPowMod");
+    let pow_mod = |base: &str, exponent: &str, modulus: &str| {
+        host_exports.big_int_pow_mod(
+            BigInt::from_str(base).unwrap(),
+            BigInt::from_str(exponent).unwrap(),
+            BigInt::from_str(modulus).unwrap(),
+        )
+    };
+
+    assert_eq!(BigInt::from(445), pow_mod("4", "13", "497").unwrap());
+    assert_eq!(BigInt::from(52), pow_mod("-4", "13", "497").unwrap());
+    // Textbook RSA with p = 61, q = 53
+    assert_eq!(BigInt::from(2790), pow_mod("65", "17", "3233").unwrap());
+    assert_eq!(BigInt::from(65), pow_mod("2790", "413", "3233").unwrap());
+    // An exponent far too large for `bigInt.pow`
+    assert_eq!(
+        BigInt::from(870513414),
+        pow_mod("3", &(1u128 << 100).to_string(), "1000000007").unwrap()
+    );
+
+    assert!(pow_mod("4", "-1", "497").is_err());
+    assert!(pow_mod("4", "13", "0").is_err());
+    assert!(pow_mod("4", "13", "-497").is_err());
+}
+
+#[tokio::test]
+async fn big_int_from_integers() {
+    let host_exports = test_host_exports("bigIntFromIntegers");
+    assert_eq!(
+        host_exports.big_int_from_string("-5".to_string()).unwrap(),
+        host_exports.big_int_from_i64(-5).unwrap()
+    );
+    assert_eq!(
+        host_exports
+            .big_int_from_string("18446744073709551615".to_string())
+            .unwrap(),
+        host_exports.big_int_from_u64(u64::MAX).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn bytes_reverse() {
+    let host_exports = test_host_exports("bytesReverse");
+    assert_eq!(
+        vec![3, 2, 1],
+        host_exports.bytes_reverse(vec![1, 2, 3]).unwrap()
+    );
+    assert_eq!(
+        Vec::<u8>::new(),
+        host_exports.bytes_reverse(vec![]).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn bytes_slice() {
+    let host_exports = test_host_exports("bytesSlice");
+    assert_eq!(
+        vec![2, 3],
+        host_exports.bytes_slice(vec![1, 2, 3, 4], 1, 3).unwrap()
+    );
+    assert_eq!(
+        Vec::<u8>::new(),
+        host_exports.bytes_slice(vec![1, 2, 3, 4], 4, 4).unwrap()
+    );
+    assert!(host_exports.bytes_slice(vec![1, 2, 3, 4], 2, 5).is_err());
+    assert!(host_exports.bytes_slice(vec![1, 2, 3, 4], 3, 1).is_err());
+}
+
+#[tokio::test]
+async fn bytes_equal() {
+    let host_exports = test_host_exports("bytesEqual");
+    assert!(host_exports
+        .bytes_equal(vec![1, 2, 3], vec![1, 2, 3])
+        .unwrap());
+    assert!(host_exports.bytes_equal(vec![], vec![]).unwrap());
+    assert!(!host_exports.bytes_equal(vec![1, 2, 3], vec![1, 2]).unwrap());
+    assert!(!host_exports
+        .bytes_equal(vec![1, 2, 3], vec![1, 2, 4])
+        .unwrap());
+}
+
+#[tokio::test]
+async fn bytes_pad() {
+    let host_exports = test_host_exports("bytesPad");
+    assert_eq!(
+        vec![0, 0, 1, 2],
+        host_exports.bytes_pad_left(vec![1, 2], 4).unwrap()
+    );
+    assert_eq!(
+        vec![1, 2, 0, 0],
+        host_exports.bytes_pad_right(vec![1, 2], 4).unwrap()
+    );
+    assert_eq!(
+        vec![1, 2],
+        host_exports.bytes_pad_left(vec![1, 2], 2).unwrap()
+    );
+    assert!(host_exports.bytes_pad_left(vec![1, 2, 3], 2).is_err());
+    assert!(host_exports.bytes_pad_right(vec![1, 2, 3], 2).is_err());
+}
+
+#[tokio::test]
+async fn string_split() {
+    let host_exports = test_host_exports("stringSplit");
+    let split =
+        |s: &str, delimiter: &str| host_exports.string_split(s.to_string(), delimiter.to_string());
+
+    assert_eq!(vec!["a", "b", "c"], split("a-b-c", "-").unwrap());
+    assert_eq!(vec!["a", "", "b", ""], split("a--b-", "-").unwrap());
+    assert_eq!(vec!["0xab", "12"], split("0xab::12", "::").unwrap());
+    assert_eq!(vec!["abc"], split("abc", "-").unwrap());
+    assert_eq!(vec![""], split("", "-").unwrap());
+    assert!(split("a-b-c", "").is_err());
+}
+
+#[tokio::test]
+async fn string_case() {
+    let host_exports = test_host_exports("stringCase");
+    let lower = |s: &str| host_exports.string_to_lower(s.to_string()).unwrap();
+    let upper = |s: &str| host_exports.string_to_upper(s.to_string()).unwrap();
+
+    assert_eq!(
+        "0xc0a47dfe034b400b47bdad5fecda2621de6c4d95",
+        lower("0xc0a47dFe034B400B47bDaD5FecDa2621de6c4d95")
+    );
+    assert_eq!("WETH", upper("wEth"));
+    // Only ASCII letters change case
+    assert_eq!("straße ÄÖ", lower("STRAßE ÄÖ"));
+    assert_eq!("STRAßE äö", upper("straße äö"));
+}
+
+#[tokio::test]
+async fn json_to_big_int_scientific() {
+    let host_exports = test_host_exports("jsonToBigIntScientific");
+    let to_big_int = |json: &str| {
+        host_exports
+            .json_to_big_int(json.to_string())
+            .map(|bytes| BigInt::from_signed_bytes_le(&bytes))
+    };
+
+    for (json, expected) in &[
+        ("1e18", "1000000000000000000"),
+        ("-2.5e3", "-2500"),
+        ("1.50E1", "15"),
+        ("3e+2", "300"),
+        ("12300e-2", "123"),
+        ("0.0e-7", "0"),
+    ] {
+        assert_eq!(
+            BigInt::from_str(expected).unwrap(),
+            to_big_int(json).unwrap()
+        );
+    }
+    for json in &["1.5", "1.5e0", "1e-3", "e3", "1e", "1x3", "1e100000"] {
+        assert!(to_big_int(json).is_err());
+    }
+}
+
+#[tokio::test]
+async fn json_to_f64_finite() {
+    let host_exports = test_host_exports("jsonToF64Finite");
+    assert_eq!(1.5, host_exports.json_to_f64("1.5".to_string()).unwrap());
+    for json in &["NaN", "inf", "-inf", "infinity", "1e400"] {
+        assert!(host_exports.json_to_f64(json.to_string()).is_err());
+    }
+}
+
+#[tokio::test]
+async fn json_to_big_decimal() {
+    let host_exports = test_host_exports("jsonToBigDecimal");
+    assert_eq!(
+        BigDecimal::new(BigInt::from(1), -18),
+        host_exports
+            .json_to_big_decimal("0.000000000000000001".to_string())
+            .unwrap()
+    );
+    assert_eq!(
+        BigDecimal::new(
+            BigInt::from_str("123456789012345678901234567890").unwrap(),
+            -20
+        ),
+        host_exports
+            .json_to_big_decimal("1234567890.12345678901234567890".to_string())
+            .unwrap()
+    );
+    assert!(host_exports
+        .json_to_big_decimal("twelve".to_string())
+        .is_err());
+}
+
+#[tokio::test]
+async fn big_decimal_scale() {
+    let host_exports = test_host_exports("bigDecimalScale");
+    for (x, scale) in &[("1.230", 2), ("5", 0), ("500", 0), ("-0.001", 3)] {
+        assert_eq!(
+            *scale,
+            host_exports
+                .big_decimal_scale(BigDecimal::from_str(x).unwrap())
+                .unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn big_decimal_with_scale() {
+    let host_exports = test_host_exports("bigDecimalWithScale");
+    let big_decimal = |x: &str| BigDecimal::from_str(x).unwrap();
+    for (x, scale, expected) in &[
+        ("1.5", 4, "1.5000"),
+        ("1.2345", 2, "1.23"),
+        ("1.235", 2, "1.24"),
+        ("-1.235", 2, "-1.24"),
+        ("0.004", 2, "0"),
+        ("7", 18, "7"),
+    ] {
+        assert_eq!(
+            big_decimal(expected),
+            host_exports
+                .big_decimal_with_scale(big_decimal(x), *scale)
+                .unwrap()
+        );
+    }
+    assert!(host_exports
+        .big_decimal_with_scale(big_decimal("1.5"), -1)
+        .is_err());
+}
+
+#[tokio::test]
+async fn big_decimal_sqrt() {
+    let host_exports = test_host_exports("bigDecimalSqrt");
+    let big_decimal = |x: &str| BigDecimal::from_str(x).unwrap();
+    for (x, root) in &[
+        ("2", "1.414213562373095048801688724209698"),
+        ("0", "0"),
+        ("4", "2"),
+        ("0.0004", "0.02"),
+        ("1e10", "100000"),
+        ("1e-11", "0.000003162277660168379331998893544432719"),
+    ] {
+        assert_eq!(
+            big_decimal(root),
+            host_exports.big_decimal_sqrt(big_decimal(x)).unwrap()
+        );
+    }
+    assert!(host_exports.big_decimal_sqrt(big_decimal("-1")).is_err());
+
+    // Squares of roots with at most 17 significant digits fit into the 34
+    // digits of a `BigDecimal`, and their root is the exact one
+    for root in &[
+        "12",
+        "0.25",
+        "1e-10",
+        "12345678.87654321",
+        "99999999999999999",
+    ] {
+        let root = big_decimal(root);
+        let square = root.clone() * root.clone();
+        assert_eq!(root, host_exports.big_decimal_sqrt(square).unwrap());
+    }
+
+    // The root of a non-square is rounded half up to 34 significant digits;
+    // the 35th digit of the exact root is in the comment
+    for (x, root) in &[
+        // 1.732050807568877293527446341505872|3...
+        ("3", "1.732050807568877293527446341505872"),
+        // 3.162277660168379331998893544432718|5...
+        ("10", "3.162277660168379331998893544432719"),
+        // 11111.11106609055546434728617210443|9...
+        ("123456789.123", "11111.11106609055546434728617210444"),
+        // 2.645751311064590590501615753639260|4...e150
+        ("7e300", "2.64575131106459059050161575363926e150"),
+    ] {
+        assert_eq!(
+            big_decimal(root),
+            host_exports.big_decimal_sqrt(big_decimal(x)).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn big_decimal_abs_and_neg() {
+    let host_exports = test_host_exports("bigDecimalAbsAndNeg");
+    let big_decimal = |x: &str| BigDecimal::from_str(x).unwrap();
+    for (x, abs, neg) in &[
+        ("-1.5", "1.5", "1.5"),
+        ("1.5", "1.5", "-1.5"),
+        ("0", "0", "0"),
+        ("-0.0", "0", "0"),
+    ] {
+        assert_eq!(
+            big_decimal(abs),
+            host_exports.big_decimal_abs(big_decimal(x)).unwrap()
+        );
+        assert_eq!(
+            big_decimal(neg),
+            host_exports.big_decimal_neg(big_decimal(x)).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn big_decimal_divided_by_with_precision() {
+    let host_exports = test_host_exports("bigDecimalDividedByWithPrecision");
+    let divide = |precision| {
+        host_exports.big_decimal_divided_by_with_precision(
+            BigDecimal::from(1),
+            BigDecimal::from(3),
+            precision,
+        )
+    };
+
+    assert_eq!(BigDecimal::from_str("0.33333").unwrap(), divide(5).unwrap());
+    assert_eq!(
+        BigDecimal::from_str(&format!("0.{}", "3".repeat(34))).unwrap(),
+        divide(34).unwrap()
+    );
+    assert!(divide(0).is_err());
+    assert!(divide(50).is_err());
+    assert!(host_exports
+        .big_decimal_divided_by_with_precision(BigDecimal::from(1), BigDecimal::from(0), 5)
+        .is_err());
+}
+
+#[tokio::test]
+async fn big_decimal_to_big_int() {
+    let host_exports = test_host_exports("bigDecimalToBigInt");
+    let to_big_int = |x: &str, mode| {
+        host_exports
+            .big_decimal_to_big_int(BigDecimal::from_str(x).unwrap(), mode)
+            .unwrap()
+    };
+
+    for (x, truncate, floor, ceil, half_up) in &[
+        ("2.7", 2, 2, 3, 3),
+        ("-2.7", -2, -3, -2, -3),
+        ("2.5", 2, 2, 3, 3),
+        ("-2.5", -2, -3, -2, -3),
+        ("2.2", 2, 2, 3, 2),
+        ("7", 7, 7, 7, 7),
+        ("1200", 1200, 1200, 1200, 1200),
+    ] {
+        assert_eq!(
+            BigInt::from(*truncate),
+            to_big_int(x, RoundingMode::Truncate)
+        );
+        assert_eq!(BigInt::from(*floor), to_big_int(x, RoundingMode::Floor));
+        assert_eq!(BigInt::from(*ceil), to_big_int(x, RoundingMode::Ceil));
+        assert_eq!(BigInt::from(*half_up), to_big_int(x, RoundingMode::HalfUp));
+    }
+    assert!(RoundingMode::try_from(4).is_err());
+}
+
+#[tokio::test]
+async fn big_int_string_radix() {
+    let host_exports = test_host_exports("bigIntStringRadix");
+    let to_string = |x: i64, radix| host_exports.big_int_to_string_radix(BigInt::from(x), radix);
+    let from_string = |s: &str, radix| host_exports.big_int_from_string_radix(s.to_string(), radix);
+
+    assert_eq!("ff", to_string(255, 16).unwrap());
+    assert_eq!("-ff", to_string(-255, 16).unwrap());
+    assert_eq!("1010", to_string(10, 2).unwrap());
+    assert_eq!("zz", to_string(1295, 36).unwrap());
+    assert_eq!(BigInt::from(255), from_string("FF", 16).unwrap());
+    assert_eq!(BigInt::from(10), from_string("1010", 2).unwrap());
+    assert_eq!(BigInt::from(1295), from_string("zz", 36).unwrap());
+
+    assert!(from_string("12", 2).is_err());
+    for radix in &[0, 1, 37] {
+        assert!(to_string(10, *radix).is_err());
+        assert!(from_string("10", *radix).is_err());
+    }
+}
+
+#[tokio::test]
+async fn big_int_from_ascii_bytes() {
+    let host_exports = test_host_exports("bigIntFromAsciiBytes");
+    let parse = |bytes: &[u8]| host_exports.big_int_from_ascii_bytes(bytes.to_vec());
+
+    assert_eq!(BigInt::from(12345), parse(b"12345").unwrap());
+    assert_eq!(BigInt::from(7), parse(b"007").unwrap());
+    assert_eq!(
+        BigInt::from_str("123456789012345678901234567890").unwrap(),
+        parse(b"123456789012345678901234567890").unwrap()
+    );
+    assert!(parse(b"12a45").is_err());
+    assert!(parse(b"-1").is_err());
+    assert!(parse(b"").is_err());
+}
+
+#[tokio::test]
+async fn min_and_max() {
+    let host_exports = test_host_exports("minAndMax");
+    for (x, y, min, max) in &[(1, 2, 1, 2), (2, 1, 1, 2), (-3, 3, -3, 3), (5, 5, 5, 5)] {
+        let (x, y) = (BigInt::from(*x), BigInt::from(*y));
+        assert_eq!(
+            BigInt::from(*min),
+            host_exports.big_int_min(x.clone(), y.clone()).unwrap()
+        );
+        assert_eq!(BigInt::from(*max), host_exports.big_int_max(x, y).unwrap());
+    }
+
+    let big_decimal = |x: &str| BigDecimal::from_str(x).unwrap();
+    for (x, y, min, max) in &[
+        ("1.5", "2", "1.5", "2"),
+        ("2", "1.5", "1.5", "2"),
+        ("-0.1", "0.1", "-0.1", "0.1"),
+        ("1.0", "1", "1", "1"),
+    ] {
+        assert_eq!(
+            big_decimal(min),
+            host_exports
+                .big_decimal_min(big_decimal(x), big_decimal(y))
+                .unwrap()
+        );
+        assert_eq!(
+            big_decimal(max),
+            host_exports
+                .big_decimal_max(big_decimal(x), big_decimal(y))
+                .unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn store_set_size_limits() {
+    let subgraph_id = "storeSetSizeLimits";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let mut host_exports = test_host_exports(subgraph_id);
+    host_exports.max_entity_field_bytes = 8;
+    // The id `limited` counts towards the size of the entity
+    host_exports.max_entity_bytes = 20;
+
+    let mut store_set = |value: &str, extra: &str| {
+        let mut data = HashMap::new();
+        data.insert("value".to_string(), Value::from(value));
+        data.insert("extra".to_string(), Value::from(extra));
+        host_exports.store_set(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Thing".to_string(),
+            "limited".to_string(),
+            data,
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+    };
+
+    assert!(store_set("12345678", "1234").is_ok());
+    // Field over the limit
+    assert!(matches!(
+        store_set("123456789", ""),
+        Err(HostExportError::Deterministic(_))
+    ));
+    // Each field is fine, but the entity is too big
+    assert!(matches!(
+        store_set("12345678", "123456"),
+        Err(HostExportError::Deterministic(_))
+    ));
+}
+
+#[tokio::test]
+async fn store_read_only() {
+    let subgraph_id = "storeReadOnly";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let mut host_exports = test_host_exports(subgraph_id);
+
+    let mut store_set = |host_exports: &HostExports<Chain>, value: &str| {
+        let mut data = HashMap::new();
+        data.insert("value".to_string(), Value::from(value));
+        host_exports.store_set(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Thing".to_string(),
+            "one".to_string(),
+            data,
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+    };
+    store_set(&host_exports, "before").unwrap();
+    host_exports.read_only = true;
+    store_set(&host_exports, "after").unwrap();
+
+    host_exports
+        .store_remove(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Thing".to_string(),
+            "one".to_string(),
+        )
+        .unwrap();
+
+    let entity = host_exports
+        .store_get(&mut ctx.ctx.state, "Thing".to_string(), "one".to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some(&Value::from("before")), entity.get("value"));
+}
+
+#[tokio::test]
+async fn store_unknown_entity_type() {
+    let subgraph_id = "storeUnknownEntityType";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+
+    let err = host_exports
+        .store_get(&mut ctx.ctx.state, "Foo".to_string(), "1".to_string())
+        .unwrap_err();
+    match err {
+        HostExportError::Deterministic(e) => assert_eq!(
+            "Entity type 'Foo' is not defined in the subgraph schema",
+            e.to_string()
+        ),
+        e => panic!("expected a deterministic error, got {}", e),
+    }
+
+    let err = host_exports
+        .store_set(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Foo".to_string(),
+            "1".to_string(),
+            HashMap::new(),
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+        .unwrap_err();
+    assert!(matches!(err, HostExportError::Deterministic(_)));
+}
+
+#[tokio::test]
+async fn store_increment() {
+    let subgraph_id = "storeIncrement";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+
+    let mut store_increment = |field: &str, delta: i32| {
+        host_exports.store_increment(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Counter".to_string(),
+            "c1".to_string(),
+            field.to_string(),
+            BigInt::from(delta),
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+    };
+
+    store_increment("count", 3).unwrap();
+    store_increment("count", 4).unwrap();
+    store_increment("total", -2).unwrap();
+    assert!(matches!(
+        store_increment("name", 1),
+        Err(HostExportError::Deterministic(_))
+    ));
+    assert!(matches!(
+        store_increment("missing", 1),
+        Err(HostExportError::Deterministic(_))
+    ));
+
+    let counter = host_exports
+        .store_get(&mut ctx.ctx.state, "Counter".to_string(), "c1".to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some(&Value::BigInt(BigInt::from(7))), counter.get("count"));
+    assert_eq!(
+        Some(&Value::BigDecimal(BigDecimal::from(-2))),
+        counter.get("total")
+    );
+}
+
+#[tokio::test]
+async fn store_set_timestamp_field() {
+    let subgraph_id = "storeSetTimestampField";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let mut host_exports = test_host_exports(subgraph_id);
+    host_exports.timestamp_field = Some("updatedAt".to_string());
+
+    let mut store_set = |entity_type: &str, data: Vec<(&str, Value)>, timestamp: i32| {
+        host_exports.store_set(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            entity_type.to_string(),
+            "one".to_string(),
+            data.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            Some(&BigInt::from(timestamp)),
+            &ctx.host_metrics.stopwatch,
+        )
+    };
+
+    store_set("Counter", vec![("name", Value::from("a"))], 100).unwrap();
+    // Entity types without the field are left alone
+    store_set("Thing", vec![("value", Value::from("a"))], 100).unwrap();
+    // The same value as the block timestamp is fine, a different one is not
+    store_set(
+        "Counter",
+        vec![("updatedAt", BigInt::from(200).into())],
+        200,
+    )
+    .unwrap();
+    assert!(matches!(
+        store_set("Counter", vec![("updatedAt", BigInt::from(1).into())], 300),
+        Err(HostExportError::Deterministic(_))
+    ));
+
+    // Increments take the timestamp of their own block
+    host_exports
+        .store_increment(
+            &ctx.ctx.logger,
+            &mut ctx.ctx.state,
+            &None,
+            DataOrigin::Chain,
+            "Counter".to_string(),
+            "one".to_string(),
+            "count".to_string(),
+            BigInt::from(1),
+            Some(&BigInt::from(400)),
+            &ctx.host_metrics.stopwatch,
+        )
+        .unwrap();
+
+    let counter = host_exports
+        .store_get(&mut ctx.ctx.state, "Counter".to_string(), "one".to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Some(&Value::BigInt(BigInt::from(400))),
+        counter.get("updatedAt")
+    );
+    let thing = host_exports
+        .store_get(&mut ctx.ctx.state, "Thing".to_string(), "one".to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(None, thing.get("updatedAt"));
+}
+
+#[tokio::test]
+async fn store_set_returning() {
+    let subgraph_id = "storeSetReturning";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+
+    let mut store_set_returning = |field: &str, value: &str| {
+        let mut data = HashMap::new();
+        data.insert(field.to_string(), Value::from(value));
+        host_exports
+            .store_set_returning(
+                &ctx.ctx.logger,
+                &mut ctx.ctx.state,
+                &None,
+                DataOrigin::Chain,
+                "Thing".to_string(),
+                "t1".to_string(),
+                data,
+                None,
+                &ctx.host_metrics.stopwatch,
+            )
+            .unwrap()
+    };
+
+    let thing = store_set_returning("value", "one");
+    assert_eq!(Some(&Value::from("t1")), thing.get("id"));
+    assert_eq!(Some(&Value::from("one")), thing.get("value"));
+
+    // Fields from earlier writes are merged into the result
+    let thing = store_set_returning("extra", "two");
+    assert_eq!(Some(&Value::from("t1")), thing.get("id"));
+    assert_eq!(Some(&Value::from("one")), thing.get("value"));
+    assert_eq!(Some(&Value::from("two")), thing.get("extra"));
+}
+
+#[tokio::test]
+async fn store_set_unique() {
+    let subgraph_id = "storeSetUnique";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let stopwatch = ctx.host_metrics.stopwatch.clone();
+    let state = &mut ctx.ctx.state;
+
+    let mut set_symbol = |id: &str, symbol: &str| {
+        let mut data = HashMap::new();
+        data.insert("symbol".to_string(), Value::from(symbol));
+        host_exports.store_set(
+            &logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "Token".to_string(),
+            id.to_string(),
+            data,
+            None,
+            &stopwatch,
+        )
+    };
+
+    set_symbol("t1", "WETH").unwrap();
+    set_symbol("t2", "DAI").unwrap();
+    // Setting the same value again is not a conflict
+    set_symbol("t1", "WETH").unwrap();
+    assert!(matches!(
+        set_symbol("t2", "WETH"),
+        Err(HostExportError::Deterministic(_))
+    ));
+}
+
+#[tokio::test]
+async fn store_get_by_field() {
+    let subgraph_id = "storeGetByField";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let state = &mut ctx.ctx.state;
+
+    for (id, value) in &[("t1", "a"), ("t2", "b")] {
+        let mut data = HashMap::new();
+        data.insert("value".to_string(), Value::from(*value));
+        host_exports
+            .store_set(
+                &logger,
+                state,
+                &None,
+                DataOrigin::Chain,
+                "Thing".to_string(),
+                id.to_string(),
+                data,
+                None,
+                &ctx.host_metrics.stopwatch,
+            )
+            .unwrap();
+    }
+
+    let mut get_by_field = |field: &str, value: &str| {
+        host_exports.store_get_by_field(
+            state,
+            "Thing".to_string(),
+            field.to_string(),
+            Value::from(value),
+        )
+    };
+
+    let thing = get_by_field("value", "b").unwrap().unwrap();
+    assert_eq!(Some(&Value::from("t2")), thing.get("id"));
+    assert!(get_by_field("value", "c").unwrap().is_none());
+    assert!(matches!(
+        get_by_field("missing", "a"),
+        Err(HostExportError::Deterministic(_))
+    ));
+
+    // A value that more than one entity has does not identify an entity
+    let mut data = HashMap::new();
+    data.insert("value".to_string(), Value::from("b"));
+    host_exports
+        .store_set(
+            &logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "Thing".to_string(),
+            "t3".to_string(),
+            data,
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+        .unwrap();
+    assert!(matches!(
+        host_exports.store_get_by_field(
+            state,
+            "Thing".to_string(),
+            "value".to_string(),
+            Value::from("b"),
+        ),
+        Err(HostExportError::Deterministic(_))
+    ));
+}
+
+#[tokio::test]
+async fn store_get_with_version() {
+    let subgraph_id = "storeGetWithVersion";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let host_metrics = ctx.host_metrics.cheap_clone();
+    let state = &mut ctx.ctx.state;
+
+    let store_set = |state: &mut BlockState<Chain>, name: &str| {
+        let mut data = HashMap::new();
+        data.insert("name".to_string(), Value::from(name));
+        host_exports
+            .store_set(
+                &logger,
+                state,
+                &None,
+                DataOrigin::Chain,
+                "User".to_string(),
+                "u1".to_string(),
+                data,
+                None,
+                &host_metrics.stopwatch,
+            )
+            .unwrap()
+    };
+    let version = |state: &mut BlockState<Chain>, block_number| {
+        host_exports
+            .store_get_with_version(state, block_number, "User".to_string(), "u1".to_string())
+            .unwrap()
+            .map(|(_, version)| version)
+    };
+
+    assert_eq!(None, version(state, 1));
+
+    store_set(state, "alice");
+    let first = version(state, 1).unwrap();
+    assert_eq!(Some(first), version(state, 1));
+
+    store_set(state, "bob");
+    let second = version(state, 1).unwrap();
+    assert!(second > first);
+
+    // Versions from a later block are always larger
+    assert!(version(state, 2).unwrap() > second);
+
+    // Writes of a handler that fails do not change the version
+    state.enter_handler();
+    store_set(state, "carol");
+    assert!(version(state, 1).unwrap() > second);
+    state.exit_handler_and_discard_changes_due_to_error(SubgraphError {
+        subgraph_id: DeploymentHash::new(subgraph_id).unwrap(),
+        message: "handler failed".to_string(),
+        block_ptr: None,
+        handler: None,
+        deterministic: true,
+    });
+    assert_eq!(Some(second), version(state, 1));
+
+    // Those of a handler that completes do
+    state.enter_handler();
+    store_set(state, "dave");
+    state.exit_handler();
+    assert!(version(state, 1).unwrap() > second);
+}
+
+#[tokio::test]
+async fn next_sequence() {
+    let subgraph_id = "nextSequence";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+
+    for expected in 0..3 {
+        assert_eq!(
+            expected,
+            host_exports.next_sequence(&mut ctx.ctx.state).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn block_timestamp() {
+    let host_exports = test_host_exports("blockTimestamp");
+    let timestamp = BigInt::from(1_600_000_000u64);
+    assert_eq!(
+        timestamp,
+        host_exports.block_timestamp(Some(&timestamp)).unwrap()
+    );
+
+    // Outside of a handler, there is no block
+    let module = test_module(
+        "blockTimestampOutsideHandler",
+        mock_data_source("wasm_test/abort.wasm"),
+    );
+    assert_eq!(None, module.instance_ctx().block_timestamp);
+    assert!(host_exports.block_timestamp(None).is_err());
+}
+
+#[tokio::test]
+async fn store_get_with_status() {
+    let subgraph_id = "storeGetWithStatus";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let state = &mut ctx.ctx.state;
+
+    let mut data = HashMap::new();
+    data.insert("name".to_string(), Value::from("alice"));
+    host_exports
+        .store_set(
+            &ctx.ctx.logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "User".to_string(),
+            "u1".to_string(),
+            data,
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+        .unwrap();
+    let status = |state: &mut BlockState<Chain>, id: &str| {
+        host_exports
+            .store_get_with_status(state, "User".to_string(), id.to_string())
+            .unwrap()
+    };
+    assert!(matches!(status(state, "u1"), EntityStatus::Present(_)));
+
+    host_exports
+        .store_remove(
+            &ctx.ctx.logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "User".to_string(),
+            "u1".to_string(),
+        )
+        .unwrap();
+    assert_eq!(EntityStatus::DeletedThisBlock, status(state, "u1"));
+    assert_eq!(EntityStatus::Absent, status(state, "u2"));
+}
+
+#[tokio::test]
+async fn store_remove_if() {
+    let subgraph_id = "storeRemoveIf";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let state = &mut ctx.ctx.state;
+
+    let mut data = HashMap::new();
+    data.insert("name".to_string(), Value::from("alice"));
+    host_exports
+        .store_set(
+            &logger,
+            state,
+            &None,
+            DataOrigin::Chain,
+            "User".to_string(),
+            "u1".to_string(),
+            data,
+            None,
+            &ctx.host_metrics.stopwatch,
+        )
+        .unwrap();
+
+    let remove_if = |state: &mut BlockState<Chain>, id: &str, name: Value| {
+        host_exports
+            .store_remove_if(
+                &logger,
+                state,
+                &None,
+                DataOrigin::Chain,
+                "User".to_string(),
+                id.to_string(),
+                "name".to_string(),
+                name,
+            )
+            .unwrap()
+    };
+    let get = |state: &mut BlockState<Chain>| {
+        host_exports
+            .store_get(state, "User".to_string(), "u1".to_string())
+            .unwrap()
+    };
+
+    assert!(!remove_if(state, "u1", Value::from("bob")));
+    assert!(!remove_if(state, "u1", Value::Null));
+    assert!(get(state).is_some());
+
+    assert!(remove_if(state, "u1", Value::from("alice")));
+    assert!(get(state).is_none());
+
+    // There is nothing left to remove
+    assert!(!remove_if(state, "u1", Value::from("alice")));
+    assert!(!remove_if(state, "u2", Value::Null));
+}
+
+#[tokio::test]
+async fn store_remove_cascade() {
+    let subgraph_id = "storeRemoveCascade";
+    let (mut module, _, _) =
+        test_valid_module_and_store(subgraph_id, mock_data_source("wasm_test/abort.wasm"));
+    let mut ctx = module.take_ctx();
+    let host_exports = test_host_exports(subgraph_id);
+    let logger = ctx.ctx.logger.clone();
+    let state = &mut ctx.ctx.state;
+
+    let set = |state: &mut BlockState<Chain>, entity_type: &str, id: &str, parent: &str| {
+        let mut data = HashMap::new();
+        match entity_type {
+            "Comment" => data.insert("post".to_string(), Value::from(parent)),
+            "Reply" => data.insert("comment".to_string(), Value::from(parent)),
+            _ => None,
+        };
+        host_exports
+            .store_set(
+                &logger,
+                state,
+                &None,
+                DataOrigin::Chain,
+                entity_type.to_string(),
+                id.to_string(),
+                data,
+                None,
+                &ctx.host_metrics.stopwatch,
+            )
+            .unwrap();
+    };
+    for post in &["p1", "p2"] {
+        set(state, "Post", post, "");
+        set(state, "Comment", &format!("{}-c1", post), post);
+        set(state, "Comment", &format!("{}-c2", post), post);
+        set(
+            state,
+            "Reply",
+            &format!("{}-r1", post),
+            &format!("{}-c1", post),
+        );
+    }
+
+    let remove_cascade = |state: &mut BlockState<Chain>, id: &str, recursive: bool| {
+        host_exports
+            .store_remove_cascade(
+                &logger,
+                state,
+                &None,
+                DataOrigin::Chain,
+                "Post".to_string(),
+                id.to_string(),
+                recursive,
+            )
+            .unwrap()
+    };
+    let exists = |state: &mut BlockState<Chain>, entity_type: &str, id: &str| {
+        host_exports
+            .store_get(state, entity_type.to_string(), id.to_string())
+            .unwrap()
+            .is_some()
+    };
+
+    // The post and its comments are removed, but not the reply to a comment
+    remove_cascade(state, "p1", false);
+    assert!(!exists(state, "Post", "p1"));
+    assert!(!exists(state, "Comment", "p1-c1"));
+    assert!(!exists(state, "Comment", "p1-c2"));
+    assert!(exists(state, "Reply", "p1-r1"));
+    assert!(exists(state, "Post", "p2"));
+    assert!(exists(state, "Comment", "p2-c1"));
+
+    remove_cascade(state, "p2", true);
+    assert!(!exists(state, "Post", "p2"));
+    assert!(!exists(state, "Comment", "p2-c1"));
+    assert!(!exists(state, "Comment", "p2-c2"));
+    assert!(!exists(state, "Reply", "p2-r1"));
+}
+
+#[tokio::test]
+async fn big_decimal_from_big_int() {
+    let host_exports = test_host_exports("bigDecimalFromBigInt");
+    assert_eq!(
+        host_exports
+            .big_decimal_from_big_int(BigInt::from(123), -2)
+            .unwrap(),
+        BigDecimal::from_str("1.23").unwrap()
+    );
+    assert_eq!(
+        host_exports
+            .big_decimal_from_big_int(BigInt::from(-5), 3)
+            .unwrap(),
+        BigDecimal::from(-5000)
+    );
+    assert!(host_exports
+        .big_decimal_from_big_int(BigInt::from(1), BigDecimal::MAX_EXP + 1)
+        .is_err());
+}
+
+#[tokio::test]
+async fn data_source_template_names() {
+    let host_exports = test_host_exports("dataSourceTemplateNames");
+    assert_eq!(
+        vec!["example template".to_string()],
+        host_exports.data_source_template_names().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn data_source_template_kind() {
+    let host_exports = test_host_exports("dataSourceTemplateKind");
+    assert_eq!(
+        "ethereum/contract",
+        host_exports
+            .data_source_template_kind("example template".to_string())
+            .unwrap()
+    );
+    assert!(host_exports
+        .data_source_template_kind("nonexistent template".to_string())
+        .is_err());
+}
+
+#[tokio::test]
+async fn data_source_context_typed_getters() {
+    let mut data_source = mock_data_source("wasm_test/abort.wasm");
+    let mut context = DataSourceContext::new();
+    context.set("name", "pool");
+    context.set("token", scalar::Bytes::from(&[0xab, 0xcd][..]));
+    context.set("fee", BigInt::from(3000));
+    context.set("missing", Value::Null);
+    data_source.context = Arc::new(Some(context));
+    let host_exports = test_host_exports_with_adapters(
+        "dataSourceContextTypedGetters",
+        data_source,
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    assert_eq!(
+        Some("pool".to_string()),
+        host_exports.data_source_context_string("name").unwrap()
+    );
+    assert_eq!(
+        Some(vec![0xab, 0xcd]),
+        host_exports.data_source_context_bytes("token").unwrap()
+    );
+    assert_eq!(
+        Some(BigInt::from(3000)),
+        host_exports.data_source_context_big_int("fee").unwrap()
+    );
+
+    assert_eq!(
+        None,
+        host_exports.data_source_context_string("missing").unwrap()
+    );
+    assert_eq!(
+        None,
+        host_exports.data_source_context_big_int("unset").unwrap()
+    );
+
+    assert!(host_exports.data_source_context_big_int("name").is_err());
+    assert!(host_exports.data_source_context_string("token").is_err());
+    assert!(host_exports.data_source_context_bytes("fee").is_err());
+}
+
+#[tokio::test]
+async fn subgraph_id() {
+    let host_exports = test_host_exports("subgraphIdAccessor");
+    assert_eq!("subgraphIdAccessor", host_exports.subgraph_id().unwrap());
+}
+
+#[tokio::test]
+async fn data_source_chain_id() {
+    let host_exports = test_host_exports("dataSourceChainId");
+    assert_eq!(1, host_exports.data_source_chain_id().unwrap());
+
+    let mut data_source = mock_data_source("wasm_test/abort.wasm");
+    data_source.network = Some("unknown-network".to_string());
+    let host_exports = test_host_exports_with_adapters(
+        "dataSourceChainIdUnknown",
+        data_source,
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+    assert!(host_exports.data_source_chain_id().is_err());
+}
+
+#[tokio::test]
+async fn data_source_address_opt() {
+    let host_exports = test_host_exports("dataSourceAddressOpt");
+    assert_eq!(
+        Some(Address::from_str("0123123123012312312301231231230123123123").unwrap()),
+        host_exports.data_source_address_opt().unwrap()
+    );
+
+    let mut data_source = mock_data_source("wasm_test/abort.wasm");
+    data_source.source.address = None;
+    let host_exports = test_host_exports_with_adapters(
+        "dataSourceAddressOptNone",
+        data_source,
+        Arc::new(MockEthereumAdapter::default()),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+    assert_eq!(None, host_exports.data_source_address_opt().unwrap());
+    assert_eq!(H160::zero(), host_exports.data_source_address());
+}
+
+#[tokio::test]
+async fn host_exports_builder() {
+    let data_source = mock_data_source("wasm_test/abort.wasm");
+    let positional = test_host_exports("hostExportsBuilder");
+
+    let store = STORE.clone();
+    let builder = HostExportsBuilder::<Chain>::new(
+        DeploymentHash::new("hostExportsBuilder").unwrap(),
+        &data_source,
+    )
+    .data_source_network(data_source.network.clone().unwrap())
+    .templates(Arc::new(vec![]))
+    .ethereum_adapter(Arc::new(MockEthereumAdapter::default()))
+    .link_resolver(Arc::new(graph_core::LinkResolver::from(
+        IpfsClient::localhost(),
+    )))
+    .store(store.subgraph_store())
+    .call_cache(
+        store
+            .block_store()
+            .ethereum_call_cache(NETWORK_NAME)
+            .expect("call cache for test network"),
+    )
+    .arweave_adapter(Arc::new(ArweaveAdapter::new(
+        "https://arweave.net".to_string(),
+    )))
+    .arweave_cache(Arc::new(ArweaveCache::new(1_000_000)));
+
+    // Every field is required
+    assert!(HostExportsBuilder::<Chain>::new(
+        DeploymentHash::new("hostExportsBuilder").unwrap(),
+        &data_source,
+    )
+    .build()
+    .is_err());
+
+    let built = builder
+        .three_box_adapter(Arc::new(ThreeBoxAdapter::new(
+            "https://ipfs.3box.io/".to_string(),
+        )))
+        .build()
+        .unwrap();
+    assert_eq!(positional.subgraph_id, built.subgraph_id);
+    assert_eq!(positional.api_version, built.api_version);
+    assert_eq!(
+        positional.data_source_network(),
+        built.data_source_network()
+    );
+    assert_eq!(
+        positional.data_source_address(),
+        built.data_source_address()
+    );
+    assert_eq!(
+        positional.causality_region(DataOrigin::Chain),
+        built.causality_region(DataOrigin::Chain)
+    );
+    assert_eq!(
+        positional.data_source_chain_id().unwrap(),
+        built.data_source_chain_id().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn crypto_verify_ed25519() {
+    // Test 2 from RFC 8032, section 7.1
+    let public_key =
+        hex::decode("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c").unwrap();
+    let signature = hex::decode(
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+         085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+    )
+    .unwrap();
+
+    let host_exports = test_host_exports("cryptoVerifyEd25519");
+    assert!(host_exports
+        .crypto_verify_ed25519(vec![0x72], signature.clone(), public_key.clone())
+        .unwrap());
+    assert!(!host_exports
+        .crypto_verify_ed25519(vec![0x73], signature.clone(), public_key.clone())
+        .unwrap());
+    assert!(host_exports
+        .crypto_verify_ed25519(vec![0x72], signature[1..].to_vec(), public_key)
+        .is_err());
+}
+
+#[tokio::test]
+async fn crypto_hmac_sha256() {
+    let host_exports = test_host_exports("cryptoHmacSha256");
+
+    // Test cases 2 and 6 from RFC 4231, section 4
+    let hmac = host_exports
+        .crypto_hmac_sha256(b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec())
+        .unwrap();
+    assert_eq!(
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        hex::encode(hmac)
+    );
+
+    let hmac = host_exports
+        .crypto_hmac_sha256(
+            vec![0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+        )
+        .unwrap();
+    assert_eq!(
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        hex::encode(hmac)
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_filter() {
+    let allowed = Address::repeat_byte(0xaa);
+    let denied = Address::repeat_byte(0xbb);
+
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .times(1)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(7.into())])));
+    let mut host_exports = test_host_exports_with_adapters(
+        "ethereumCallFilter",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+    host_exports.contract_call_filter =
+        ContractCallFilter::Allow(vec![allowed].into_iter().collect());
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+
+        let tokens = host_exports
+            .ethereum_call(
+                &logger,
+                &None,
+                DataOrigin::Chain,
+                &block_ptr,
+                balance_of_call(allowed),
+                &mut EthereumCallMemo::new(),
+                &host_metrics,
+                None,
+            )
+            .ok()
+            .unwrap();
+        assert_eq!(tokens, Some(vec![Token::Uint(7.into())]));
+
+        match host_exports.ethereum_call(
+            &logger,
+            &None,
+            DataOrigin::Chain,
+            &block_ptr,
+            balance_of_call(denied),
+            &mut EthereumCallMemo::new(),
+            &host_metrics,
+            None,
+        ) {
+            Err(EthereumCallError::Deterministic(e)) => {
+                assert!(e.to_string().contains("is not allowed on this node"))
+            }
+            _ => panic!("expected the call to be rejected"),
+        }
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_memo() {
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .times(2)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(7.into())])));
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallMemo",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let token = Address::repeat_byte(0xaa);
+
+        // The second identical call within the same handler is memoized
+        let mut call_memo = EthereumCallMemo::new();
+        for _ in 0..2 {
+            let tokens = host_exports
+                .ethereum_call(
+                    &logger,
+                    &None,
+                    DataOrigin::Chain,
+                    &block_ptr,
+                    balance_of_call(token),
+                    &mut call_memo,
+                    &host_metrics,
+                    None,
+                )
+                .ok()
+                .unwrap();
+            assert_eq!(tokens, Some(vec![Token::Uint(7.into())]));
+        }
+
+        // A new handler starts with an empty memo
+        host_exports
+            .ethereum_call(
+                &logger,
+                &None,
+                DataOrigin::Chain,
+                &block_ptr,
+                balance_of_call(token),
+                &mut EthereumCallMemo::new(),
+                &host_metrics,
+                None,
+            )
+            .ok()
+            .unwrap();
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_overrides() {
+    let sender = Address::repeat_byte(0x0f);
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .withf(move |_, call, _| call.from == Some(sender) && call.gas == Some(1_000_000))
+        .times(1)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(7.into())])));
+    ethereum_adapter
+        .expect_contract_call()
+        .withf(|_, call, _| call.from.is_none() && call.gas.is_none())
+        .times(1)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(3.into())])));
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallOverrides",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let token = Address::repeat_byte(0xaa);
+        let mut call_memo = EthereumCallMemo::new();
+
+        let call = UnresolvedContractCall {
+            from: Some(sender),
+            gas_limit: Some(1_000_000),
+            ..balance_of_call(token)
+        };
+        let tokens = host_exports
+            .ethereum_call(
+                &logger,
+                &None,
+                DataOrigin::Chain,
+                &block_ptr,
+                call,
+                &mut call_memo,
+                &host_metrics,
+                None,
+            )
+            .ok()
+            .unwrap();
+        assert_eq!(tokens, Some(vec![Token::Uint(7.into())]));
+
+        // Without overrides, the same call is not answered from the memo
+        let tokens = host_exports
+            .ethereum_call(
+                &logger,
+                &None,
+                DataOrigin::Chain,
+                &block_ptr,
+                balance_of_call(token),
+                &mut call_memo,
+                &host_metrics,
+                None,
+            )
+            .ok()
+            .unwrap();
+        assert_eq!(tokens, Some(vec![Token::Uint(3.into())]));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_state_overrides() {
+    let token = Address::repeat_byte(0xaa);
+    let mut overrides = EthereumStateOverrides::new();
+    overrides.insert(
+        token,
+        EthereumAccountOverride {
+            state_diff: vec![(H256::zero(), H256::repeat_byte(0x01))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        },
+    );
+
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    let expected = overrides.clone();
+    ethereum_adapter
+        .expect_contract_call_with_overrides()
+        .withf(move |_, _, overrides| overrides == &expected)
+        .times(2)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(9.into())])));
+    ethereum_adapter
+        .expect_contract_call()
+        .times(1)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(7.into())])));
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallStateOverrides",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let proof_of_indexing = Arc::new(AtomicRefCell::new(ProofOfIndexing::new(0)));
+        let mut call_memo = EthereumCallMemo::new();
+        let regions = |proof_of_indexing: &AtomicRefCell<ProofOfIndexing>| {
+            std::mem::take(&mut *proof_of_indexing.borrow_mut())
+                .take()
+                .len()
+        };
+        let mut call = |state_overrides: Option<EthereumStateOverrides>| {
+            host_exports
+                .ethereum_call(
+                    &logger,
+                    &Some(proof_of_indexing.cheap_clone()),
+                    DataOrigin::Chain,
+                    &block_ptr,
+                    UnresolvedContractCall {
+                        state_overrides,
+                        ..balance_of_call(token)
+                    },
+                    &mut call_memo,
+                    &host_metrics,
+                    None,
+                )
+                .ok()
+                .unwrap()
+        };
+
+        // A call without overrides is not recorded
+        assert_eq!(call(None), Some(vec![Token::Uint(7.into())]));
+        assert_eq!(0, regions(&proof_of_indexing));
+
+        // Calls with overrides are neither memoized nor left out of the PoI
+        assert_eq!(
+            call(Some(overrides.clone())),
+            Some(vec![Token::Uint(9.into())])
+        );
+        assert_eq!(call(Some(overrides)), Some(vec![Token::Uint(9.into())]));
+        assert_eq!(1, regions(&proof_of_indexing));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_transient_failures() {
+    // The node times out on every call except the fourth
+    let mut calls = 0;
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .times(5)
+        .returning(move |_, _, _| {
+            calls += 1;
+            if calls == 4 {
+                Box::new(future::ok(vec![Token::Uint(7.into())]))
+            } else {
+                Box::new(future::err(EthereumContractCallError::Timeout))
+            }
+        });
+    let mut host_exports = test_host_exports_with_adapters(
+        "ethereumCallTransientFailures",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+    host_exports.max_transient_call_failures = 2;
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let call = || {
+            host_exports.ethereum_call(
+                &logger,
+                &None,
+                DataOrigin::Chain,
+                &block_ptr,
+                balance_of_call(Address::repeat_byte(0xaa)),
+                &mut EthereumCallMemo::new(),
+                &host_metrics,
+                None,
+            )
+        };
+
+        assert!(matches!(call(), Err(EthereumCallError::PossibleReorg(_))));
+        assert!(matches!(call(), Err(EthereumCallError::PossibleReorg(_))));
+        // The budget is used up
+        assert!(matches!(call(), Err(EthereumCallError::Unknown(_))));
+        // A successful call resets the budget
+        assert!(matches!(call(), Ok(Some(_))));
+        assert!(matches!(call(), Err(EthereumCallError::PossibleReorg(_))));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_deadline() {
+    // The node never answers
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .times(1)
+        .returning(|_, _, _| Box::new(future::empty()));
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallDeadline",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let start = Instant::now();
+
+        match host_exports.ethereum_call(
+            &logger,
+            &None,
+            DataOrigin::Chain,
+            &block_ptr,
+            balance_of_call(Address::repeat_byte(0xaa)),
+            &mut EthereumCallMemo::new(),
+            &host_metrics,
+            Some(start + Duration::from_millis(100)),
+        ) {
+            Err(EthereumCallError::Unknown(e)) => assert!(DeadlineExceeded::caused(&e)),
+            _ => panic!("expected the call to run into the deadline"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_metrics() {
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    ethereum_adapter
+        .expect_contract_call()
+        .times(1)
+        .returning(|_, _, _| Box::new(future::ok(vec![Token::Uint(7.into())])));
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallMetrics",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let mut call_memo = EthereumCallMemo::new();
+        let mut call = || {
+            host_exports
+                .ethereum_call(
+                    &logger,
+                    &None,
+                    DataOrigin::Chain,
+                    &block_ptr,
+                    balance_of_call(Address::repeat_byte(0xaa)),
+                    &mut call_memo,
+                    &host_metrics,
+                    None,
+                )
+                .ok()
+                .unwrap();
+        };
+
+        call();
+        call();
+        call();
+
+        assert_eq!(host_metrics.eth_call_count("node"), 1);
+        assert_eq!(host_metrics.eth_call_count("memo"), 2);
+        assert_eq!(host_metrics.eth_call_count("call_cache"), 0);
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ethereum_call_by_selector() {
+    let selector = vec![0x12, 0x34, 0x56, 0x78];
+    let mut ethereum_adapter = MockEthereumAdapter::default();
+    let expected_selector = selector.clone();
+    ethereum_adapter
+        .expect_raw_contract_call()
+        .withf(move |_, call, _| {
+            call.call_data[..4] == expected_selector[..]
+                && call.call_data[4..] == ethabi::encode(&[Token::Uint(1.into())])[..]
+        })
+        .times(2)
+        .returning({
+            // The node answers the first call and times out on the second
+            let calls = AtomicUsize::new(0);
+            move |_, _, _| match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Box::new(future::ok(ethabi::encode(&[
+                    Token::Uint(7.into()),
+                    Token::Bool(true),
+                ]))),
+                _ => Box::new(future::err(EthereumContractCallError::Timeout)),
+            }
+        });
+    let host_exports = test_host_exports_with_adapters(
+        "ethereumCallBySelector",
+        contract_call_data_source(),
+        Arc::new(ethereum_adapter),
+        Arc::new(ArweaveAdapter::new("https://arweave.net".to_string())),
+    );
+
+    run_in_sync_context(move || {
+        let logger = test_store::LOGGER.clone();
+        let block_ptr = BlockPtr::from((H256::zero(), 1));
+        let host_metrics = test_host_metrics(&host_exports.subgraph_id);
+        let call = |selector: Vec<u8>| {
+            host_exports.ethereum_call_by_selector(
+                &logger,
+                &block_ptr,
+                Address::repeat_byte(0xaa),
+                selector,
+                vec![Token::Uint(1.into())],
+                "(uint256,bool)".to_string(),
+                &host_metrics,
+                None,
+            )
+        };
+
+        assert_eq!(
+            call(selector.clone()).ok().unwrap(),
+            Some(vec![Token::Uint(7.into()), Token::Bool(true)])
+        );
+        assert!(matches!(
+            call(vec![0x12, 0x34]),
+            Err(EthereumCallError::Deterministic(_))
+        ));
+        assert!(matches!(
+            call(selector.clone()),
+            Err(EthereumCallError::PossibleReorg(_))
+        ));
+    });
+}
+
+#[tokio::test]
+async fn deterministic_random() {
+    let host_exports = test_host_exports("deterministicRandom");
+    let sequence = |block_ptr: &BlockPtr, trigger_seed: &[u8]| {
+        (0..3)
+            .map(|counter| {
+                host_exports
+                    .deterministic_random(block_ptr, trigger_seed, counter)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    // The seeds of two events in the same transaction, like the handlers
+    // set them
+    let event_seed = |log_index: u64| {
+        let mut seed = H256::repeat_byte(0xaa).as_bytes().to_vec();
+        let mut index = [0u8; 32];
+        U256::from(log_index).to_big_endian(&mut index);
+        seed.extend_from_slice(&index);
+        seed
+    };
+
+    let block_ptr = BlockPtr::from((H256::repeat_byte(0x01), 1));
+    let first = sequence(&block_ptr, &event_seed(0));
+    assert_eq!(first, sequence(&block_ptr, &event_seed(0)));
+    assert_ne!(first[0], first[1]);
+    assert_ne!(first[1], first[2]);
+
+    let other_block_ptr = BlockPtr::from((H256::repeat_byte(0x02), 2));
+    assert_ne!(first, sequence(&other_block_ptr, &event_seed(0)));
+
+    // Other triggers in the same block get other sequences
+    assert_ne!(first, sequence(&block_ptr, &event_seed(1)));
+    assert_ne!(
+        first,
+        sequence(&block_ptr, H256::repeat_byte(0xaa).as_bytes())
+    );
+    assert_ne!(first, sequence(&block_ptr, b"block"));
+}
+
+#[tokio::test]
+async fn big_int_random_below() {
+    let host_exports = test_host_exports("bigIntRandomBelow");
+    let block_ptr = BlockPtr::from((H256::repeat_byte(0x01), 1));
+    let sample = |n: &BigInt| {
+        let mut counter = 0;
+        (0..20)
+            .map(|_| {
+                host_exports
+                    .big_int_random_below(&block_ptr, b"block", &mut counter, n.clone())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Small, not a power of two, and wider than one random value
+    for n in &[
+        BigInt::from(1),
+        BigInt::from(10),
+        BigInt::from(1) + BigInt::from(2).pow(255),
+        BigInt::from(3).pow(200),
+    ] {
+        let first = sample(n);
+        assert_eq!(first, sample(n));
+        assert!(first.iter().all(|x| x >= &BigInt::from(0) && x < n));
+    }
+    assert!(sample(&BigInt::from(1))
+        .iter()
+        .all(|x| x == &BigInt::from(0)));
+
+    let mut counter = 0;
+    assert!(host_exports
+        .big_int_random_below(&block_ptr, b"block", &mut counter, BigInt::from(0))
+        .is_err());
+    assert!(host_exports
+        .big_int_random_below(&block_ptr, b"block", &mut counter, BigInt::from(-5))
+        .is_err());
+}
@@ -16,6 +16,7 @@ use graph::{
 
 use crate::asc_abi::class::*;
 
+use crate::host_exports::EntityStatus;
 use crate::UnresolvedContractCall;
 
 impl ToAscObj<Uint8Array> for web3::H160 {
@@ -324,6 +325,35 @@ impl ToAscObj<AscEntity> for Vec<(String, store::Value)> {
     }
 }
 
+// Used for `store.getWithVersion`.
+impl ToAscObj<AscEntityWithVersion> for (Vec<(String, store::Value)>, u64) {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+    ) -> Result<AscEntityWithVersion, DeterministicHostError> {
+        Ok(AscEntityWithVersion {
+            entity: asc_new(heap, &self.0)?,
+            _padding: 0,
+            version: self.1,
+        })
+    }
+}
+
+// Used for `store.getWithStatus`.
+impl ToAscObj<AscEntityWithStatus> for EntityStatus {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+    ) -> Result<AscEntityWithStatus, DeterministicHostError> {
+        let (entity, status) = match self {
+            EntityStatus::Absent => (AscPtr::null(), 0),
+            EntityStatus::Present(entity) => (asc_new(heap, &entity.clone().sorted())?, 1),
+            EntityStatus::DeletedThisBlock => (AscPtr::null(), 2),
+        };
+        Ok(AscEntityWithStatus { entity, status })
+    }
+}
+
 impl ToAscObj<AscEnum<JsonValueKind>> for serde_json::Value {
     fn to_asc_obj<H: AscHeap + ?Sized>(
         &self,
@@ -348,6 +378,82 @@ impl ToAscObj<AscEnum<JsonValueKind>> for serde_json::Value {
     }
 }
 
+impl TryFromAscObj<AscJson> for serde_json::Map<String, serde_json::Value> {
+    fn try_from_asc_obj<H: AscHeap + ?Sized>(
+        asc_map: AscJson,
+        heap: &H,
+    ) -> Result<Self, DeterministicHostError> {
+        let entries: Vec<(String, serde_json::Value)> = try_asc_get(heap, asc_map.entries)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+impl TryFromAscObj<AscEnum<JsonValueKind>> for serde_json::Value {
+    fn try_from_asc_obj<H: AscHeap + ?Sized>(
+        asc_enum: AscEnum<JsonValueKind>,
+        heap: &H,
+    ) -> Result<Self, DeterministicHostError> {
+        use serde_json::Value;
+
+        let payload = asc_enum.payload;
+        Ok(match asc_enum.kind {
+            JsonValueKind::Null => Value::Null,
+            JsonValueKind::Bool => Value::Bool(bool::from(payload)),
+            JsonValueKind::Number => {
+                let ptr: AscPtr<AscString> = AscPtr::from(payload);
+                let number: String = asc_get(heap, ptr)?;
+                Value::Number(
+                    number
+                        .parse()
+                        .map_err(|e: serde_json::Error| DeterministicHostError(e.into()))?,
+                )
+            }
+            JsonValueKind::String => {
+                let ptr: AscPtr<AscString> = AscPtr::from(payload);
+                Value::String(asc_get(heap, ptr)?)
+            }
+            JsonValueKind::Array => {
+                let ptr: AscEnumArray<JsonValueKind> = AscPtr::from(payload);
+                Value::Array(try_asc_get(heap, ptr)?)
+            }
+            JsonValueKind::Object => {
+                let ptr: AscPtr<AscJson> = AscPtr::from(payload);
+                Value::Object(try_asc_get(heap, ptr)?)
+            }
+        })
+    }
+}
+
+// Used for `ipfs.catWithContentType`, the content type is optional.
+impl ToAscObj<AscIpfsFile> for (Vec<u8>, Option<String>) {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+    ) -> Result<AscIpfsFile, DeterministicHostError> {
+        Ok(AscIpfsFile {
+            data: asc_new(heap, self.0.as_slice())?,
+            content_type: self
+                .1
+                .as_ref()
+                .map(|content_type| asc_new(heap, content_type.as_str()))
+                .unwrap_or(Ok(AscPtr::null()))?,
+        })
+    }
+}
+
+// Used for `ipfs.ls`, a pair of entry name and CID.
+impl ToAscObj<AscIpfsLink> for (String, String) {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+    ) -> Result<AscIpfsLink, DeterministicHostError> {
+        Ok(AscIpfsLink {
+            name: asc_new(heap, self.0.as_str())?,
+            cid: asc_new(heap, self.1.as_str())?,
+        })
+    }
+}
+
 impl ToAscObj<AscEthereumBlock> for EthereumBlockData {
     fn to_asc_obj<H: AscHeap + ?Sized>(
         &self,
@@ -485,6 +591,9 @@ impl FromAscObj<AscUnresolvedContractCall> for UnresolvedContractCall {
             function_name: asc_get(heap, asc_call.function_name)?,
             function_signature: None,
             function_args: asc_get(heap, asc_call.function_args)?,
+            from: None,
+            gas_limit: None,
+            state_overrides: None,
         })
     }
 }
@@ -500,6 +609,9 @@ impl FromAscObj<AscUnresolvedContractCall_0_0_4> for UnresolvedContractCall {
             function_name: asc_get(heap, asc_call.function_name)?,
             function_signature: Some(asc_get(heap, asc_call.function_signature)?),
             function_args: asc_get(heap, asc_call.function_args)?,
+            from: None,
+            gas_limit: None,
+            state_overrides: None,
         })
     }
 }
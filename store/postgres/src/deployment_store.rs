@@ -28,9 +28,9 @@ use graph::constraint_violation;
 use graph::data::subgraph::schema::{SubgraphError, POI_OBJECT};
 use graph::prelude::{
     anyhow, debug, futures03, info, lazy_static, o, web3, ApiSchema, AttributeNames, BlockNumber,
-    BlockPtr, CheapClone, DeploymentHash, DeploymentState, DynTryFuture, Entity, EntityKey,
-    EntityModification, EntityQuery, Error, Logger, QueryExecutionError, Schema, StopwatchMetrics,
-    StoreError, StoreEvent, Value, BLOCK_NUMBER_MAX,
+    BlockPtr, CheapClone, DeploymentHash, DeploymentState, DynTryFuture, Entity, EntityFilter,
+    EntityKey, EntityModification, EntityQuery, Error, Logger, QueryExecutionError, Schema,
+    StopwatchMetrics, StoreError, StoreEvent, Value, BLOCK_NUMBER_MAX,
 };
 use graph_graphql::prelude::api_schema;
 use web3::types::Address;
@@ -818,6 +818,23 @@ impl DeploymentStore {
         layout.find_many(&conn, ids_for_type, BLOCK_NUMBER_MAX)
     }
 
+    pub(crate) fn find_matching(
+        &self,
+        site: Arc<Site>,
+        entity_type: &EntityType,
+        filter: EntityFilter,
+        first: u32,
+    ) -> Result<Vec<Entity>, QueryExecutionError> {
+        let conn = self
+            .get_conn()
+            .map_err(|e| QueryExecutionError::StoreError(e.into()))?;
+        let collection = EntityCollection::All(vec![(entity_type.clone(), AttributeNames::All)]);
+        let query = EntityQuery::new(site.deployment.clone(), BLOCK_NUMBER_MAX, collection)
+            .filter(filter)
+            .first(first);
+        self.execute_query(&conn, site, query)
+    }
+
     // Only used by tests
     #[cfg(debug_assertions)]
     pub(crate) fn find(
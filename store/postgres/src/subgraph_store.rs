@@ -21,9 +21,10 @@ use graph::{
     prelude::SubgraphDeploymentEntity,
     prelude::{
         anyhow, futures03::future::join_all, lazy_static, o, web3::types::Address, ApiSchema,
-        BlockPtr, DeploymentHash, DynTryFuture, Entity, EntityKey, EntityModification, Error,
-        Logger, NodeId, QueryExecutionError, Schema, StopwatchMetrics, StoreError, SubgraphName,
-        SubgraphStore as SubgraphStoreTrait, SubgraphVersionSwitchingMode,
+        BlockPtr, DeploymentHash, DynTryFuture, Entity, EntityFilter, EntityKey,
+        EntityModification, Error, Logger, NodeId, QueryExecutionError, Schema, StopwatchMetrics,
+        StoreError, SubgraphName, SubgraphStore as SubgraphStoreTrait,
+        SubgraphVersionSwitchingMode,
     },
     util::timed_cache::TimedCache,
 };
@@ -1099,6 +1100,16 @@ impl WritableStoreTrait for WritableStore {
         self.writable.get_many(self.site.clone(), ids_for_type)
     }
 
+    fn find_matching(
+        &self,
+        entity_type: &EntityType,
+        filter: EntityFilter,
+        first: u32,
+    ) -> Result<Vec<Entity>, QueryExecutionError> {
+        self.writable
+            .find_matching(self.site.clone(), entity_type, filter, first)
+    }
+
     fn is_deployment_synced(&self) -> Result<bool, Error> {
         Ok(self.writable.exists_and_synced(&self.site.deployment)?)
     }